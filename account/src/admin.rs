@@ -0,0 +1,535 @@
+//! 管理员接口
+//!
+//! 所有修改操作均记录到审计日志
+
+use crate::auth::jwt::{self, Claims, JWTConfig};
+use crate::auth::lockout;
+use crate::guards::{ClientInfo, Require};
+use crate::rbac::perm;
+use crate::rbac::PermissionType;
+use crate::routes::send_password_reset_email;
+use chrono::{DateTime, Utc};
+use email::EmailBackend;
+use entity::audit::AuditLog;
+use entity::identity::UserIdentity;
+use entity::invitation::InvitationCode;
+use entity::password::UNUSABLE_PASSWORD;
+use entity::role::{Permission, Role};
+use entity::school::Cohort;
+use entity::session::UserSession;
+use entity::user::{AccountStatus, User as UserModel};
+use rocket::form::Form;
+use rocket::http::Status;
+use rocket::serde::json::{json, Json, Value};
+use rocket::{delete, get, post, put, routes, FromForm, FromFormField, State};
+use serde::Serialize;
+use sqlx::{FromRow, PgPool, Postgres, QueryBuilder};
+use utils::pagination::{Page, Pagination};
+use utils::query::like_pattern;
+
+/// 代为登录 token 的最长有效期（秒）
+const IMPERSONATION_MAX_EXPIRATION: i64 = 3600;
+
+/// 记录审计日志
+async fn audit(
+    pool: &PgPool,
+    admin: &UserModel,
+    action: &str,
+    target_user_id: Option<i32>,
+    detail: Value,
+    client: &ClientInfo,
+) {
+    AuditLog::record(
+        pool,
+        admin.id,
+        action,
+        target_user_id,
+        detail,
+        client.ip.as_deref(),
+    )
+    .await
+    .unwrap();
+}
+
+async fn find_user(pool: &PgPool, id: i32) -> Result<UserModel, (Status, &'static str)> {
+    sqlx::query_as::<_, UserModel>(r#"SELECT * FROM "user" WHERE id = $1"#)
+        .bind(id)
+        .fetch_optional(pool)
+        .await
+        .unwrap()
+        .ok_or((Status::NotFound, "User not found"))
+}
+
+/// 账户状态查询参数
+#[derive(Clone, Copy, Debug, FromFormField)]
+enum StatusParam {
+    Inactive,
+    Active,
+    Suspended,
+}
+
+impl From<StatusParam> for AccountStatus {
+    fn from(status: StatusParam) -> Self {
+        match status {
+            StatusParam::Inactive => AccountStatus::Inactive,
+            StatusParam::Active => AccountStatus::Active,
+            StatusParam::Suspended => AccountStatus::Suspended,
+        }
+    }
+}
+
+/// 用户列表项
+#[derive(Debug, FromRow, Serialize)]
+struct UserSummary {
+    id: i32,
+    email: String,
+    username: String,
+    status: AccountStatus,
+    totp_enabled: bool,
+    deletion_scheduled_at: Option<DateTime<Utc>>,
+    created_at: DateTime<Utc>,
+    updated_at: DateTime<Utc>,
+}
+
+/// 用户列表筛选条件
+fn push_user_filters<'a>(
+    query: &mut QueryBuilder<'a, Postgres>,
+    q: Option<&'a str>,
+    status: Option<StatusParam>,
+) {
+    query.push(" WHERE TRUE");
+    if let Some(q) = q.map(str::trim).filter(|q| !q.is_empty()) {
+        let pattern = like_pattern(q);
+        query.push(" AND (email ILIKE ");
+        query.push_bind(pattern.clone());
+        query.push(" OR username ILIKE ");
+        query.push_bind(pattern);
+        query.push(")");
+    }
+    if let Some(status) = status {
+        query.push(" AND status = ");
+        query.push_bind(AccountStatus::from(status));
+    }
+}
+
+/// 用户列表
+///
+/// * `q` - 按邮箱或用户名模糊搜索
+/// * `status` - `inactive` / `active` / `suspended`
+#[get("/admin/users?<q>&<status>&<pagination..>")]
+async fn users(
+    pool: &State<PgPool>,
+    _admin: Require<perm::UserManage>,
+    q: Option<&str>,
+    status: Option<StatusParam>,
+    pagination: Pagination,
+) -> Json<Page<UserSummary>> {
+    let mut query = QueryBuilder::new(
+        r#"SELECT id, email, username, status, totp_enabled, deletion_scheduled_at, created_at, updated_at FROM "user""#,
+    );
+    push_user_filters(&mut query, q, status);
+    query.push(" ORDER BY id LIMIT ");
+    query.push_bind(pagination.limit());
+    query.push(" OFFSET ");
+    query.push_bind(pagination.offset());
+    let items = query
+        .build_query_as::<UserSummary>()
+        .fetch_all(pool.inner())
+        .await
+        .unwrap();
+
+    let mut query = QueryBuilder::new(r#"SELECT COUNT(*) FROM "user""#);
+    push_user_filters(&mut query, q, status);
+    let total: i64 = query
+        .build_query_scalar()
+        .fetch_one(pool.inner())
+        .await
+        .unwrap();
+    Json(Page::new(items, total, pagination))
+}
+
+#[derive(Serialize)]
+struct UserDetail {
+    #[serde(flatten)]
+    user: UserModel,
+    roles: Vec<String>,
+    identities: Vec<UserIdentity>,
+    sessions: Vec<UserSession>,
+}
+
+#[get("/admin/users/<id>")]
+async fn user_detail(
+    pool: &State<PgPool>,
+    _admin: Require<perm::UserManage>,
+    id: i32,
+) -> Result<Json<UserDetail>, (Status, &'static str)> {
+    let user = find_user(pool.inner(), id).await?;
+    Ok(Json(UserDetail {
+        roles: Role::of_user(pool.inner(), id)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|role| role.name)
+            .collect(),
+        identities: UserIdentity::of_user(pool.inner(), id).await.unwrap(),
+        sessions: UserSession::active_of_user(pool.inner(), id).await.unwrap(),
+        user,
+    }))
+}
+
+/// 管理员可设置的账户状态
+#[derive(Clone, Copy, Debug, FromFormField)]
+enum StatusChange {
+    Active,
+    Suspended,
+}
+
+#[derive(Debug, FromForm)]
+struct UpdateStatusReq {
+    status: StatusChange,
+}
+
+/// 启用或停用账户
+///
+/// 停用后账户的全部登录立即失效
+#[put("/admin/users/<id>/status", data = "<data>")]
+async fn update_status(
+    pool: &State<PgPool>,
+    admin: Require<perm::UserManage>,
+    client: ClientInfo,
+    id: i32,
+    data: Form<UpdateStatusReq>,
+) -> Result<(Status, &'static str), (Status, &'static str)> {
+    let admin = admin.0;
+    if admin.id == id {
+        return Err((Status::BadRequest, "Cannot change your own status"));
+    }
+    let user = find_user(pool.inner(), id).await?;
+    let status = match data.status {
+        StatusChange::Active => AccountStatus::Active,
+        StatusChange::Suspended => AccountStatus::Suspended,
+    };
+    sqlx::query(r#"UPDATE "user" SET status=$1 WHERE id=$2"#)
+        .bind(&status)
+        .bind(id)
+        .execute(pool.inner())
+        .await
+        .unwrap();
+    if status == AccountStatus::Suspended {
+        UserModel::invalidate_tokens(pool.inner(), id)
+            .await
+            .unwrap();
+    }
+    audit(
+        pool.inner(),
+        &admin,
+        "user.status",
+        Some(id),
+        json!({ "from": user.status, "to": status }),
+        &client,
+    )
+    .await;
+    Ok((Status::Ok, "Success"))
+}
+
+#[derive(Debug, FromForm)]
+struct UpdateRolesReq {
+    /// 角色名称，替换用户现有的全部角色
+    roles: Vec<String>,
+}
+
+#[put("/admin/users/<id>/roles", data = "<data>")]
+async fn update_roles(
+    pool: &State<PgPool>,
+    admin: Require<perm::RoleManage>,
+    client: ClientInfo,
+    id: i32,
+    data: Form<UpdateRolesReq>,
+) -> Result<(Status, &'static str), (Status, &'static str)> {
+    let admin = admin.0;
+    if admin.id == id {
+        return Err((Status::BadRequest, "Cannot change your own roles"));
+    }
+    find_user(pool.inner(), id).await?;
+    let all_roles = Role::all(pool.inner()).await.unwrap();
+    if !data
+        .roles
+        .iter()
+        .all(|name| all_roles.iter().any(|role| &role.name == name))
+    {
+        return Err((Status::BadRequest, "Unknown role"));
+    }
+    let current: Vec<String> = Role::of_user(pool.inner(), id)
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|role| role.name)
+        .collect();
+    let mut tx = pool.begin().await.unwrap();
+    for name in current.iter().filter(|name| !data.roles.contains(name)) {
+        Role::revoke(&mut *tx, id, name).await.unwrap();
+    }
+    for name in data.roles.iter().filter(|name| !current.contains(name)) {
+        Role::grant(&mut *tx, id, name).await.unwrap();
+    }
+    tx.commit().await.unwrap();
+    audit(
+        pool.inner(),
+        &admin,
+        "user.roles",
+        Some(id),
+        json!({ "from": current, "to": data.roles }),
+        &client,
+    )
+    .await;
+    Ok((Status::Ok, "Success"))
+}
+
+/// 强制重置密码
+///
+/// 将原密码替换为无法匹配的 hash 并使全部登录失效，向用户发送密码重置邮件。
+/// 不能置为 `NULL`, 否则账户会被视为没有密码的单点登录账户
+#[post("/admin/users/<id>/password-reset")]
+async fn force_password_reset(
+    pool: &State<PgPool>,
+    email_backend: &State<EmailBackend>,
+    admin: Require<perm::UserManage>,
+    client: ClientInfo,
+    id: i32,
+) -> Result<(Status, &'static str), (Status, &'static str)> {
+    let admin = admin.0;
+    let user = find_user(pool.inner(), id).await?;
+    sqlx::query(r#"UPDATE "user" SET password=$1 WHERE id=$2"#)
+        .bind(UNUSABLE_PASSWORD)
+        .bind(id)
+        .execute(pool.inner())
+        .await
+        .unwrap();
+    UserModel::invalidate_tokens(pool.inner(), id)
+        .await
+        .unwrap();
+    send_password_reset_email(email_backend.inner(), pool.inner(), id, &user.email).await;
+    audit(
+        pool.inner(),
+        &admin,
+        "user.password_reset",
+        Some(id),
+        json!({}),
+        &client,
+    )
+    .await;
+    Ok((Status::Ok, "Success"))
+}
+
+#[derive(Serialize)]
+struct ImpersonationResp {
+    token: String,
+    expire: DateTime<Utc>,
+}
+
+/// 代为登录，用于排查用户问题
+///
+/// 仅签发短期 access token, 不签发 refresh token；token 中的 `act` 为管理员 ID,
+/// 该 token 不能管理令牌、两步验证、会话、密码或删除账户。
+/// 不能代为登录拥有用户管理权限，或拥有管理员自身没有的权限的账户
+#[post("/admin/users/<id>/impersonate")]
+async fn impersonate(
+    pool: &State<PgPool>,
+    jwt_config: &State<JWTConfig>,
+    admin: Require<perm::UserManage>,
+    client: ClientInfo,
+    id: i32,
+) -> Result<Json<ImpersonationResp>, (Status, &'static str)> {
+    let admin = admin.0;
+    let user = find_user(pool.inner(), id).await?;
+    let admin_permissions = Permission::of_user(pool.inner(), admin.id).await.unwrap();
+    let user_permissions = Permission::of_user(pool.inner(), id).await.unwrap();
+    if user.id == admin.id
+        || user_permissions.iter().any(|permission| {
+            permission.code == perm::UserManage::CODE
+                || !admin_permissions.iter().any(|p| p.code == permission.code)
+        })
+    {
+        return Err((Status::Forbidden, "Cannot impersonate this user"));
+    }
+    if user.status != AccountStatus::Active {
+        return Err((Status::BadRequest, "User is not active"));
+    }
+    let mut claims = Claims::new(id, None, jwt_config.inner());
+    let expire = claims.issued_at()
+        + chrono::Duration::seconds(jwt_config.expiration.min(IMPERSONATION_MAX_EXPIRATION));
+    claims.exp = expire.timestamp() as usize;
+    claims.act = Some(admin.id);
+    audit(
+        pool.inner(),
+        &admin,
+        "user.impersonate",
+        Some(id),
+        json!({ "jti": claims.jti, "expire": expire }),
+        &client,
+    )
+    .await;
+    Ok(Json(ImpersonationResp {
+        token: jwt::create_token(&claims, jwt_config.inner()),
+        expire,
+    }))
+}
+
+/// 审计日志
+///
+/// * `user_id` - 仅查询与该用户相关的记录
+#[get("/admin/audit-log?<user_id>&<pagination..>")]
+async fn audit_log(
+    pool: &State<PgPool>,
+    _admin: Require<perm::UserManage>,
+    user_id: Option<i32>,
+    pagination: Pagination,
+) -> Json<Page<AuditLog>> {
+    let (items, total) = AuditLog::list(
+        pool.inner(),
+        user_id,
+        pagination.limit(),
+        pagination.offset(),
+    )
+    .await
+    .unwrap();
+    Json(Page::new(items, total, pagination))
+}
+
+/// 账户锁定状态
+#[derive(Debug, FromRow, Serialize)]
+struct LockoutState {
+    failed_login_count: i32,
+    locked_until: Option<DateTime<Utc>>,
+}
+
+#[get("/admin/users/<id>/lockout")]
+async fn lockout_state(
+    pool: &State<PgPool>,
+    _admin: Require<perm::UserManage>,
+    id: i32,
+) -> Result<Json<LockoutState>, (Status, &'static str)> {
+    sqlx::query_as::<_, LockoutState>(
+        r#"SELECT failed_login_count, locked_until FROM "user" WHERE id=$1"#,
+    )
+    .bind(id)
+    .fetch_optional(pool.inner())
+    .await
+    .unwrap()
+    .map(Json)
+    .ok_or((Status::NotFound, "User not found"))
+}
+
+#[delete("/admin/users/<id>/lockout")]
+async fn clear_lockout(
+    pool: &State<PgPool>,
+    admin: Require<perm::UserManage>,
+    client: ClientInfo,
+    id: i32,
+) -> Result<(Status, &'static str), (Status, &'static str)> {
+    find_user(pool.inner(), id).await?;
+    lockout::clear(pool.inner(), id).await.unwrap();
+    audit(
+        pool.inner(),
+        &admin.0,
+        "user.lockout_clear",
+        Some(id),
+        json!({}),
+        &client,
+    )
+    .await;
+    Ok((Status::Ok, "Success"))
+}
+
+#[get("/admin/invitations")]
+async fn invitations(
+    pool: &State<PgPool>,
+    _admin: Require<perm::UserManage>,
+) -> Json<Vec<InvitationCode>> {
+    Json(InvitationCode::all(pool.inner()).await.unwrap())
+}
+
+#[derive(Debug, FromForm)]
+struct CreateInvitationReq {
+    /// 邀请注册的届，使用该邀请码注册的账户只能认领该届的人员
+    cohort_id: i32,
+    /// 可使用次数，默认为 1 (一次性)
+    #[field(validate = range(1..), default = 1)]
+    max_uses: i32,
+    /// 有效天数，默认 30
+    #[field(validate = range(1..=365), default = 30)]
+    expires_in_days: i64,
+}
+
+#[post("/admin/invitations", data = "<data>")]
+async fn create_invitation(
+    pool: &State<PgPool>,
+    admin: Require<perm::UserManage>,
+    client: ClientInfo,
+    data: Form<CreateInvitationReq>,
+) -> Result<Json<InvitationCode>, (Status, &'static str)> {
+    Cohort::find(pool.inner(), data.cohort_id)
+        .await
+        .unwrap()
+        .ok_or((Status::BadRequest, "Cohort not found"))?;
+    let expire = Utc::now() + chrono::Duration::days(data.expires_in_days);
+    let invitation = InvitationCode::create(
+        pool.inner(),
+        data.cohort_id,
+        data.max_uses,
+        expire,
+        admin.0.id,
+    )
+    .await
+    .unwrap();
+    audit(
+        pool.inner(),
+        &admin.0,
+        "invitation.create",
+        None,
+        json!({ "id": invitation.id, "cohort_id": invitation.cohort_id, "max_uses": invitation.max_uses }),
+        &client,
+    )
+    .await;
+    Ok(Json(invitation))
+}
+
+#[delete("/admin/invitations/<id>")]
+async fn delete_invitation(
+    pool: &State<PgPool>,
+    admin: Require<perm::UserManage>,
+    client: ClientInfo,
+    id: i32,
+) -> (Status, &'static str) {
+    if InvitationCode::delete(pool.inner(), id).await.unwrap() {
+        audit(
+            pool.inner(),
+            &admin.0,
+            "invitation.delete",
+            None,
+            json!({ "id": id }),
+            &client,
+        )
+        .await;
+        (Status::Ok, "Success")
+    } else {
+        (Status::NotFound, "Invitation not found")
+    }
+}
+
+pub fn routes() -> Vec<rocket::Route> {
+    routes![
+        users,
+        user_detail,
+        update_status,
+        update_roles,
+        force_password_reset,
+        impersonate,
+        audit_log,
+        lockout_state,
+        clear_lockout,
+        invitations,
+        create_invitation,
+        delete_invitation
+    ]
+}
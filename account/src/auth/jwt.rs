@@ -1,56 +1,329 @@
 // src/auth/jwt.rs
-use jsonwebtoken::{decode, encode, Algorithm, DecodingKey, EncodingKey, Header, Validation};
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use jsonwebtoken::jwk::{
+    AlgorithmParameters, CommonParameters, EllipticCurve, Jwk, JwkSet, KeyAlgorithm,
+    OctetKeyPairParameters, OctetKeyPairType, PublicKeyUse, RSAKeyParameters, RSAKeyType,
+};
+use jsonwebtoken::{
+    decode, decode_header, encode, Algorithm, DecodingKey, EncodingKey, Header, Validation,
+};
+use rsa::pkcs1::DecodeRsaPublicKey;
+use rsa::pkcs8::DecodePublicKey;
+use rsa::traits::PublicKeyParts;
+use rsa::RsaPublicKey;
 use serde::{Deserialize, Serialize};
+use uuid::Uuid;
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Claims {
     pub sub: i32,   // 用户ID
     pub exp: usize, // 过期时间戳
+    pub iat: usize, // 签发时间戳
+    pub jti: Uuid,  // token ID, 用于吊销
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub iss: Option<String>, // 签发者
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub aud: Option<String>, // 受众
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sid: Option<Uuid>, // 会话ID, 即 refresh token family
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub act: Option<i32>, // 代为登录的管理员ID
+    /// 个人访问令牌的权限范围, 不出现在 jwt 中
+    #[serde(skip)]
+    pub scopes: Option<Vec<String>>,
 }
 
+impl Claims {
+    /// 新的 access token claims
+    pub fn new(user_id: i32, session_id: Option<Uuid>, config: &JWTConfig) -> Self {
+        let now = chrono::Utc::now();
+        let expiration = now
+            .checked_add_signed(chrono::Duration::seconds(config.expiration))
+            .expect("Invalid timestamp")
+            .timestamp() as usize;
+        Self {
+            sub: user_id,
+            exp: expiration,
+            iat: now.timestamp() as usize,
+            jti: Uuid::new_v4(),
+            iss: config.issuer.clone(),
+            aud: config.audience.clone(),
+            sid: session_id,
+            act: None,
+            scopes: None,
+        }
+    }
+
+    /// 签发时间
+    pub fn issued_at(&self) -> chrono::DateTime<chrono::Utc> {
+        chrono::DateTime::from_timestamp(self.iat as i64, 0).expect("Invalid timestamp")
+    }
+
+    /// 过期时间
+    pub fn expire(&self) -> chrono::DateTime<chrono::Utc> {
+        chrono::DateTime::from_timestamp(self.exp as i64, 0).expect("Invalid timestamp")
+    }
+
+    /// 是否来自个人访问令牌
+    pub fn is_personal_access_token(&self) -> bool {
+        self.scopes.is_some()
+    }
+
+    /// 权限范围是否包含 `code`
+    ///
+    /// 普通 jwt 不限制权限范围
+    pub fn allows(&self, code: &str) -> bool {
+        self.scopes
+            .as_ref()
+            .is_none_or(|scopes| scopes.iter().any(|scope| scope == code))
+    }
+}
+
+/// jwt 签名密钥
+pub struct SigningKey {
+    pub kid: String,
+    pub algorithm: Algorithm,
+    /// 仅保留公钥的旧密钥只用于校验
+    encoding: Option<EncodingKey>,
+    decoding: DecodingKey,
+    /// 对称密钥不公开
+    jwk: Option<Jwk>,
+}
+
+impl SigningKey {
+    /// 对称密钥 (HS256)
+    pub fn from_secret(kid: impl Into<String>, secret: &[u8]) -> Self {
+        Self {
+            kid: kid.into(),
+            algorithm: Algorithm::HS256,
+            encoding: Some(EncodingKey::from_secret(secret)),
+            decoding: DecodingKey::from_secret(secret),
+            jwk: None,
+        }
+    }
+
+    /// 非对称密钥 (RS256 / EdDSA)
+    ///
+    /// * `public_pem` - 公钥 PEM
+    /// * `private_pem` - 私钥 PEM, 为 `None` 时仅用于校验
+    pub fn from_pem(
+        kid: impl Into<String>,
+        algorithm: Algorithm,
+        public_pem: &[u8],
+        private_pem: Option<&[u8]>,
+    ) -> Result<Self, String> {
+        let kid = kid.into();
+        let (encoding, decoding, parameters) = match algorithm {
+            Algorithm::RS256 | Algorithm::RS384 | Algorithm::RS512 => {
+                let pem = std::str::from_utf8(public_pem).map_err(|e| e.to_string())?;
+                let public_key = RsaPublicKey::from_public_key_pem(pem)
+                    .or_else(|_| RsaPublicKey::from_pkcs1_pem(pem))
+                    .map_err(|e| e.to_string())?;
+                let encoding = private_pem
+                    .map(EncodingKey::from_rsa_pem)
+                    .transpose()
+                    .map_err(|e| e.to_string())?;
+                let decoding = DecodingKey::from_rsa_pem(public_pem).map_err(|e| e.to_string())?;
+                let parameters = AlgorithmParameters::RSA(RSAKeyParameters {
+                    key_type: RSAKeyType::RSA,
+                    n: URL_SAFE_NO_PAD.encode(public_key.n().to_bytes_be()),
+                    e: URL_SAFE_NO_PAD.encode(public_key.e().to_bytes_be()),
+                });
+                (encoding, decoding, parameters)
+            }
+            Algorithm::EdDSA => {
+                // SubjectPublicKeyInfo 的最后 32 字节即为 Ed25519 公钥
+                let der = pem::parse(public_pem).map_err(|e| e.to_string())?;
+                let contents = der.contents();
+                if contents.len() < 32 {
+                    return Err("Invalid Ed25519 public key".to_string());
+                }
+                let encoding = private_pem
+                    .map(EncodingKey::from_ed_pem)
+                    .transpose()
+                    .map_err(|e| e.to_string())?;
+                let decoding = DecodingKey::from_ed_pem(public_pem).map_err(|e| e.to_string())?;
+                let parameters = AlgorithmParameters::OctetKeyPair(OctetKeyPairParameters {
+                    key_type: OctetKeyPairType::OctetKeyPair,
+                    curve: EllipticCurve::Ed25519,
+                    x: URL_SAFE_NO_PAD.encode(&contents[contents.len() - 32..]),
+                });
+                (encoding, decoding, parameters)
+            }
+            other => return Err(format!("Unsupported algorithm: {:?}", other)),
+        };
+        let jwk = Jwk {
+            common: CommonParameters {
+                public_key_use: Some(PublicKeyUse::Signature),
+                key_algorithm: Some(match algorithm {
+                    Algorithm::RS384 => KeyAlgorithm::RS384,
+                    Algorithm::RS512 => KeyAlgorithm::RS512,
+                    Algorithm::EdDSA => KeyAlgorithm::EdDSA,
+                    _ => KeyAlgorithm::RS256,
+                }),
+                key_id: Some(kid.clone()),
+                ..Default::default()
+            },
+            algorithm: parameters,
+        };
+        Ok(Self {
+            kid,
+            algorithm,
+            encoding,
+            decoding,
+            jwk: Some(jwk),
+        })
+    }
+
+    /// 丢弃签名密钥，仅用于校验
+    pub fn verify_only(mut self) -> Self {
+        self.encoding = None;
+        self
+    }
+
+    /// 是否可用于签名
+    pub fn can_sign(&self) -> bool {
+        self.encoding.is_some()
+    }
+}
+
+/// `JWT_SECRET` 对应密钥的 kid，不带 `kid` 的旧 token 使用此密钥校验
+const LEGACY_KID: &str = "default";
+
 pub struct JWTConfig {
-    pub secret: String,
-    pub expiration: i64, // 秒
+    /// 全部可用于校验的密钥
+    pub keys: Vec<SigningKey>,
+    /// 当前用于签名的密钥 kid
+    pub signing_kid: String,
+    pub expiration: i64,         // 秒
+    pub refresh_expiration: i64, // 秒
+    pub issuer: Option<String>,
+    pub audience: Option<String>,
 }
 
 impl JWTConfig {
+    /// * `JWT_KEYS` - 非对称密钥列表, 以 `,` 分隔, 每项为 `kid:ALG:public.pem[:private.pem]`,
+    ///   `ALG` 可选 `RS256` / `RS384` / `RS512` / `EdDSA`; 未设置时使用 `JWT_SECRET` (HS256)
+    /// * `JWT_SIGNING_KID` - 用于签名的 kid, 默认为第一个带私钥的密钥
+    /// * `JWT_EXPIRATION` - access token 有效期（秒），默认 86400
+    /// * `JWT_REFRESH_EXPIRATION` - refresh token 有效期（秒），默认 2592000
+    /// * `JWT_ISSUER` / `JWT_AUDIENCE` - 设置后签发并校验 `iss` / `aud`
+    ///
+    /// 轮换密钥时，将新密钥（带私钥）放在最前，旧密钥只保留公钥，
+    /// 待旧 token 全部过期后再移除旧密钥。
+    /// 从 `JWT_SECRET` 迁移到 `JWT_KEYS` 时保留 `JWT_SECRET`，其仅用于校验旧 token
     pub fn from_env() -> Self {
+        let mut keys: Vec<SigningKey> = match std::env::var("JWT_KEYS") {
+            Ok(keys) if !keys.trim().is_empty() => keys
+                .split(',')
+                .map(|entry| {
+                    let parts: Vec<&str> = entry.trim().split(':').collect();
+                    if parts.len() < 3 || parts.len() > 4 {
+                        panic!("Invalid JWT_KEYS entry: {}", entry);
+                    }
+                    let algorithm: Algorithm = parts[1]
+                        .parse()
+                        .unwrap_or_else(|_| panic!("Invalid JWT algorithm: {}", parts[1]));
+                    let read = |path: &str| {
+                        std::fs::read(path)
+                            .unwrap_or_else(|e| panic!("Cannot read key {}: {}", path, e))
+                    };
+                    let public_pem = read(parts[2]);
+                    let private_pem = parts.get(3).map(|path| read(path));
+                    SigningKey::from_pem(parts[0], algorithm, &public_pem, private_pem.as_deref())
+                        .unwrap_or_else(|e| panic!("Invalid JWT key {}: {}", parts[0], e))
+                })
+                .collect(),
+            _ => vec![SigningKey::from_secret(
+                LEGACY_KID,
+                std::env::var("JWT_SECRET")
+                    .expect("JWT_SECRET must be set")
+                    .as_bytes(),
+            )],
+        };
+        if let Ok(secret) = std::env::var("JWT_SECRET")
+            && !keys.iter().any(|key| key.kid == LEGACY_KID)
+        {
+            keys.push(SigningKey::from_secret(LEGACY_KID, secret.as_bytes()).verify_only());
+        }
+        let signing_kid = std::env::var("JWT_SIGNING_KID").unwrap_or_else(|_| {
+            keys.iter()
+                .find(|key| key.can_sign())
+                .expect("At least one JWT key must have a private key")
+                .kid
+                .clone()
+        });
+        if !keys
+            .iter()
+            .any(|key| key.kid == signing_kid && key.can_sign())
+        {
+            panic!("JWT signing key {} not found", signing_kid);
+        }
+        let env_secs = |key: &str, default: i64| match std::env::var(key) {
+            Ok(secs) => secs
+                .parse()
+                .unwrap_or_else(|_| panic!("{} must be a number", key)),
+            _ => default,
+        };
         Self {
-            secret: std::env::var("JWT_SECRET").expect("JWT_SECRET must be set"),
-            expiration: 3600 * 24,
+            keys,
+            signing_kid,
+            expiration: env_secs("JWT_EXPIRATION", 3600 * 24),
+            refresh_expiration: env_secs("JWT_REFRESH_EXPIRATION", 3600 * 24 * 30),
+            issuer: std::env::var("JWT_ISSUER").ok(),
+            audience: std::env::var("JWT_AUDIENCE").ok(),
         }
     }
-}
 
-/// 构建 jwt token
-pub fn create_token(user_id: i32, config: &JWTConfig) -> String {
-    let expiration = chrono::Utc::now()
-        .checked_add_signed(chrono::Duration::seconds(config.expiration))
-        .expect("Invalid timestamp")
-        .timestamp() as usize;
+    /// 当前用于签名的密钥
+    pub fn signing_key(&self) -> &SigningKey {
+        self.keys
+            .iter()
+            .find(|key| key.kid == self.signing_kid)
+            .unwrap()
+    }
 
-    let claims = Claims {
-        sub: user_id,
-        exp: expiration,
-    };
+    /// 公开的密钥集合
+    pub fn jwks(&self) -> JwkSet {
+        JwkSet {
+            keys: self.keys.iter().filter_map(|key| key.jwk.clone()).collect(),
+        }
+    }
+}
 
-    encode(
-        &Header::default(),
-        &claims,
-        &EncodingKey::from_secret(config.secret.as_ref()),
-    )
-    .unwrap()
+/// 构建 jwt token
+pub fn create_token(claims: &Claims, config: &JWTConfig) -> String {
+    let key = config.signing_key();
+    let mut header = Header::new(key.algorithm);
+    header.kid = Some(key.kid.clone());
+    encode(&header, claims, key.encoding.as_ref().unwrap()).unwrap()
 }
 
 /// 校验 jwt token
+///
+/// 按 header 中的 `kid` 选择密钥，没有 `kid` 时使用 `JWT_SECRET` 对应的密钥，
+/// 未配置时使用签名密钥
 pub fn validate_token(
     token: &str,
     config: &JWTConfig,
 ) -> Result<Claims, jsonwebtoken::errors::Error> {
-    decode::<Claims>(
-        token,
-        &DecodingKey::from_secret(config.secret.as_ref()),
-        &Validation::new(Algorithm::HS256),
-    )
-    .map(|data| data.claims)
+    let header = decode_header(token)?;
+    let key = match &header.kid {
+        Some(kid) => config.keys.iter().find(|key| &key.kid == kid),
+        None => config
+            .keys
+            .iter()
+            .find(|key| key.kid == LEGACY_KID)
+            .or_else(|| Some(config.signing_key())),
+    }
+    .ok_or(jsonwebtoken::errors::ErrorKind::InvalidToken)?;
+    let mut validation = Validation::new(key.algorithm);
+    if let Some(issuer) = &config.issuer {
+        validation.set_issuer(&[issuer]);
+    }
+    if let Some(audience) = &config.audience {
+        validation.set_audience(&[audience]);
+    }
+    decode::<Claims>(token, &key.decoding, &validation).map(|data| data.claims)
 }
@@ -0,0 +1,139 @@
+//! 登录暴力破解防护
+//!
+//! - 同一账户连续失败 `max_failures` 次后锁定，之后每再失败 `max_failures` 次锁定时间翻倍
+//! - 同一 ip 在 `ip_window` 内失败超过 `ip_max_failures` 次后拒绝该 ip 的登录请求
+use email::EmailBackend;
+use entity::user::{AuthUser, LoginAttempt};
+use sqlx::PgPool;
+
+pub struct LockoutConfig {
+    /// 账户连续失败多少次后锁定
+    pub max_failures: i32,
+    /// 首次锁定时长（秒）
+    pub lockout_duration: i64,
+    /// 最长锁定时长（秒）
+    pub max_lockout_duration: i64,
+    /// 单个 ip 在时间窗口内允许的失败次数
+    pub ip_max_failures: i64,
+    /// ip 统计时间窗口（秒）
+    pub ip_window: i64,
+}
+
+impl LockoutConfig {
+    /// * `APP_LOGIN_MAX_FAILURES` - 默认 5
+    /// * `APP_LOGIN_LOCKOUT_SECS` - 默认 900
+    /// * `APP_LOGIN_MAX_LOCKOUT_SECS` - 默认 86400
+    /// * `APP_LOGIN_IP_MAX_FAILURES` - 默认 20
+    /// * `APP_LOGIN_IP_WINDOW_SECS` - 默认 900
+    pub fn from_env() -> Self {
+        fn var<T: std::str::FromStr>(key: &str, default: T) -> T {
+            match std::env::var(key) {
+                Ok(value) => value
+                    .parse()
+                    .unwrap_or_else(|_| panic!("{} must be a number", key)),
+                _ => default,
+            }
+        }
+        Self {
+            max_failures: var("APP_LOGIN_MAX_FAILURES", 5),
+            lockout_duration: var("APP_LOGIN_LOCKOUT_SECS", 900),
+            max_lockout_duration: var("APP_LOGIN_MAX_LOCKOUT_SECS", 3600 * 24),
+            ip_max_failures: var("APP_LOGIN_IP_MAX_FAILURES", 20),
+            ip_window: var("APP_LOGIN_IP_WINDOW_SECS", 900),
+        }
+    }
+
+    /// 第 `failed_count` 次失败后的锁定时长
+    ///
+    /// 未达到阈值时返回 `None`
+    pub fn lockout_for(&self, failed_count: i32) -> Option<chrono::Duration> {
+        if self.max_failures <= 0 || failed_count == 0 || failed_count % self.max_failures != 0 {
+            return None;
+        }
+        let times = (failed_count / self.max_failures - 1).min(16) as u32;
+        let secs = self
+            .lockout_duration
+            .saturating_mul(2i64.saturating_pow(times))
+            .min(self.max_lockout_duration);
+        Some(chrono::Duration::seconds(secs))
+    }
+}
+
+/// ip 是否已超过失败次数限制
+pub async fn is_ip_blocked(
+    pool: &PgPool,
+    config: &LockoutConfig,
+    ip: Option<&str>,
+) -> Result<bool, sqlx::Error> {
+    let Some(ip) = ip else {
+        return Ok(false);
+    };
+    let since = chrono::Utc::now() - chrono::Duration::seconds(config.ip_window);
+    Ok(LoginAttempt::failures_from_ip(pool, ip, since).await? >= config.ip_max_failures)
+}
+
+/// 记录登录成功并清除失败计数
+pub async fn record_success(
+    pool: &PgPool,
+    user: &AuthUser,
+    ip: Option<&str>,
+) -> Result<(), sqlx::Error> {
+    LoginAttempt::record(pool, Some(user.id), ip, true).await?;
+    if user.failed_login_count != 0 || user.locked_until.is_some() {
+        clear(pool, user.id).await?;
+    }
+    Ok(())
+}
+
+/// 记录登录失败，达到阈值时锁定账户并通知用户
+pub async fn record_failure(
+    pool: &PgPool,
+    config: &LockoutConfig,
+    email_backend: &EmailBackend,
+    user: Option<&AuthUser>,
+    ip: Option<&str>,
+) -> Result<(), sqlx::Error> {
+    LoginAttempt::record(pool, user.map(|user| user.id), ip, false).await?;
+    let Some(user) = user else {
+        return Ok(());
+    };
+    let failed_count: i32 = sqlx::query_scalar(
+        r#"UPDATE "user" SET failed_login_count = failed_login_count + 1 WHERE id = $1 RETURNING failed_login_count"#,
+    )
+    .bind(user.id)
+    .fetch_one(pool)
+    .await?;
+    if let Some(duration) = config.lockout_for(failed_count) {
+        let locked_until = chrono::Utc::now() + duration;
+        sqlx::query(r#"UPDATE "user" SET locked_until = $1 WHERE id = $2"#)
+            .bind(locked_until)
+            .bind(user.id)
+            .execute(pool)
+            .await?;
+        let mut context = tera::Context::new();
+        context.insert("failed_count", &failed_count);
+        context.insert("locked_until", &locked_until.to_rfc3339());
+        context.insert("ip", ip.unwrap_or("unknown"));
+        if let Err(e) = email_backend
+            .send_template(
+                user.email.parse().unwrap(),
+                "Your account has been locked",
+                "account_locked.html",
+                &context,
+            )
+            .await
+        {
+            eprintln!("{}", e);
+        }
+    }
+    Ok(())
+}
+
+/// 清除账户的失败计数与锁定状态
+pub async fn clear(pool: &PgPool, user_id: i32) -> Result<(), sqlx::Error> {
+    sqlx::query(r#"UPDATE "user" SET failed_login_count = 0, locked_until = NULL WHERE id = $1"#)
+        .bind(user_id)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
@@ -1 +1,5 @@
 pub mod jwt;
+pub mod lockout;
+pub mod oidc;
+pub mod registration;
+pub mod totp;
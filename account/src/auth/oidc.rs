@@ -0,0 +1,207 @@
+//! OpenID Connect 单点登录 (relying party)
+//!
+//! 授权码模式 + PKCE, 每次授权时通过 discovery 获取提供方配置与 JWKS,
+//! id token 的签名、`iss`、`aud`、`exp` 与 `nonce` 均由 [`openidconnect`] 校验
+use openidconnect::core::{CoreAuthenticationFlow, CoreClient, CoreProviderMetadata};
+use openidconnect::{
+    reqwest, AccessTokenHash, AuthorizationCode, ClientId, ClientSecret, CsrfToken, IssuerUrl,
+    Nonce, OAuth2TokenResponse, PkceCodeChallenge, PkceCodeVerifier, RedirectUrl, Scope,
+    TokenResponse,
+};
+
+/// 身份提供方
+pub struct OidcProvider {
+    /// 名称，用于路由与关联外部身份, 不超过 32 个字符
+    pub name: String,
+    pub issuer: String,
+    pub client_id: String,
+    /// 为 `None` 时作为 public client
+    pub client_secret: Option<String>,
+    /// 授权完成后跳转的前端地址
+    pub redirect_uri: String,
+    pub scopes: Vec<String>,
+    /// 是否信任提供方已验证的邮箱，信任时按邮箱关联已有账户，新建的账户无需邮箱验证
+    pub trust_email: bool,
+}
+
+/// 发起授权所需的信息
+pub struct AuthorizationRequest {
+    pub url: String,
+    pub state: String,
+    pub pkce_verifier: String,
+    pub nonce: String,
+}
+
+/// id token 中的用户信息
+pub struct OidcUserInfo {
+    pub subject: String,
+    pub email: Option<String>,
+    pub email_verified: bool,
+    /// `name` 或 `preferred_username`
+    pub name: Option<String>,
+}
+
+impl OidcProvider {
+    async fn metadata(
+        &self,
+        http_client: &reqwest::Client,
+    ) -> Result<CoreProviderMetadata, String> {
+        let issuer = IssuerUrl::new(self.issuer.clone()).map_err(|e| e.to_string())?;
+        CoreProviderMetadata::discover_async(issuer, http_client)
+            .await
+            .map_err(|e| e.to_string())
+    }
+
+    /// 生成授权地址
+    pub async fn authorize(
+        &self,
+        http_client: &reqwest::Client,
+    ) -> Result<AuthorizationRequest, String> {
+        let client = CoreClient::from_provider_metadata(
+            self.metadata(http_client).await?,
+            ClientId::new(self.client_id.clone()),
+            self.client_secret.clone().map(ClientSecret::new),
+        )
+        .set_redirect_uri(RedirectUrl::new(self.redirect_uri.clone()).map_err(|e| e.to_string())?);
+        let (pkce_challenge, pkce_verifier) = PkceCodeChallenge::new_random_sha256();
+        let mut request = client
+            .authorize_url(
+                CoreAuthenticationFlow::AuthorizationCode,
+                CsrfToken::new_random,
+                Nonce::new_random,
+            )
+            .set_pkce_challenge(pkce_challenge);
+        // `openid` 总是会被加入
+        for scope in self.scopes.iter().filter(|scope| *scope != "openid") {
+            request = request.add_scope(Scope::new(scope.clone()));
+        }
+        let (url, state, nonce) = request.url();
+        Ok(AuthorizationRequest {
+            url: url.to_string(),
+            state: state.secret().clone(),
+            pkce_verifier: pkce_verifier.secret().clone(),
+            nonce: nonce.secret().clone(),
+        })
+    }
+
+    /// 使用授权码换取并校验 id token
+    pub async fn exchange(
+        &self,
+        http_client: &reqwest::Client,
+        code: &str,
+        pkce_verifier: &str,
+        nonce: &str,
+    ) -> Result<OidcUserInfo, String> {
+        let client = CoreClient::from_provider_metadata(
+            self.metadata(http_client).await?,
+            ClientId::new(self.client_id.clone()),
+            self.client_secret.clone().map(ClientSecret::new),
+        )
+        .set_redirect_uri(RedirectUrl::new(self.redirect_uri.clone()).map_err(|e| e.to_string())?);
+        let token_response = client
+            .exchange_code(AuthorizationCode::new(code.to_string()))
+            .map_err(|e| e.to_string())?
+            .set_pkce_verifier(PkceCodeVerifier::new(pkce_verifier.to_string()))
+            .request_async(http_client)
+            .await
+            .map_err(|e| e.to_string())?;
+        let id_token = token_response
+            .id_token()
+            .ok_or("Server did not return an ID token")?;
+        let verifier = client.id_token_verifier();
+        let claims = id_token
+            .claims(&verifier, &Nonce::new(nonce.to_string()))
+            .map_err(|e| e.to_string())?;
+        // 防止 access token 被替换
+        if let Some(expected_hash) = claims.access_token_hash() {
+            let actual_hash = AccessTokenHash::from_token(
+                token_response.access_token(),
+                id_token.signing_alg().map_err(|e| e.to_string())?,
+                id_token.signing_key(&verifier).map_err(|e| e.to_string())?,
+            )
+            .map_err(|e| e.to_string())?;
+            if actual_hash != *expected_hash {
+                return Err("Invalid access token".to_string());
+            }
+        }
+        Ok(OidcUserInfo {
+            subject: claims.subject().to_string(),
+            email: claims.email().map(|email| email.to_string()),
+            email_verified: claims.email_verified().unwrap_or(false),
+            name: claims
+                .name()
+                .and_then(|name| name.get(None))
+                .map(|name| name.to_string())
+                .or_else(|| {
+                    claims
+                        .preferred_username()
+                        .map(|username| username.to_string())
+                }),
+        })
+    }
+}
+
+pub struct OidcConfig {
+    pub providers: Vec<OidcProvider>,
+    /// 不跟随重定向，避免 SSRF
+    pub http_client: reqwest::Client,
+}
+
+impl OidcConfig {
+    /// * `OIDC_PROVIDERS` - 身份提供方名称列表，以 `,` 分隔，未设置时不启用单点登录
+    ///
+    /// 对于每个名称 `<NAME>` (大写):
+    /// * `OIDC_<NAME>_ISSUER` - issuer 地址, discovery 地址为 `<issuer>/.well-known/openid-configuration`,
+    ///   测试时可指向本地的模拟提供方
+    /// * `OIDC_<NAME>_CLIENT_ID`
+    /// * `OIDC_<NAME>_CLIENT_SECRET` - 可选
+    /// * `OIDC_<NAME>_REDIRECT_URI` - 前端回调地址，前端取得 `code` 与 `state` 后请求
+    ///   `/auth/oidc/<name>/callback`
+    /// * `OIDC_<NAME>_SCOPES` - 以空格分隔，默认 `openid email profile`
+    /// * `OIDC_<NAME>_TRUST_EMAIL` - 是否按已验证的邮箱关联已有账户并跳过新账户的邮箱验证，
+    ///   默认 `false`, 仅在信任提供方的邮箱验证时开启
+    pub fn from_env() -> Self {
+        let providers = std::env::var("OIDC_PROVIDERS")
+            .unwrap_or_default()
+            .split(',')
+            .map(str::trim)
+            .filter(|name| !name.is_empty())
+            .map(|name| {
+                let prefix = format!("OIDC_{}_", name.to_uppercase());
+                let var = |key: &str| std::env::var(format!("{}{}", prefix, key));
+                let required = |key: &str| {
+                    var(key).unwrap_or_else(|_| panic!("{}{} must be set", prefix, key))
+                };
+                if name.len() > 32 {
+                    panic!("OIDC provider name too long: {}", name);
+                }
+                OidcProvider {
+                    name: name.to_string(),
+                    issuer: required("ISSUER"),
+                    client_id: required("CLIENT_ID"),
+                    client_secret: var("CLIENT_SECRET").ok(),
+                    redirect_uri: required("REDIRECT_URI"),
+                    scopes: var("SCOPES")
+                        .unwrap_or("openid email profile".to_string())
+                        .split_whitespace()
+                        .map(str::to_string)
+                        .collect(),
+                    trust_email: var("TRUST_EMAIL")
+                        .map(|value| value.parse().unwrap())
+                        .unwrap_or(false),
+                }
+            })
+            .collect();
+        Self {
+            providers,
+            http_client: reqwest::ClientBuilder::new()
+                .redirect(reqwest::redirect::Policy::none())
+                .build()
+                .expect("Failed to build http client"),
+        }
+    }
+
+    pub fn provider(&self, name: &str) -> Option<&OidcProvider> {
+        self.providers.iter().find(|provider| provider.name == name)
+    }
+}
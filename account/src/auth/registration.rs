@@ -0,0 +1,73 @@
+//! 注册方式控制
+use serde::Serialize;
+
+/// 注册模式
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RegistrationMode {
+    /// 任何人均可注册
+    Open,
+    /// 关闭注册
+    Closed,
+    /// 凭邀请码注册
+    Invitation,
+    /// 仅允许指定域名的邮箱注册
+    Domain,
+}
+
+pub struct RegistrationConfig {
+    pub mode: RegistrationMode,
+    /// 小写，不含 `@`
+    pub allowed_domains: Vec<String>,
+}
+
+impl RegistrationConfig {
+    /// * `APP_REGISTRATION_MODE` - `open` / `closed` / `invitation` / `domain`, 默认 `open`
+    /// * `APP_REGISTRATION_ALLOWED_DOMAINS` - `domain` 模式下允许的邮箱域名，以 `,` 分隔
+    pub fn from_env() -> Self {
+        let mode = match std::env::var("APP_REGISTRATION_MODE")
+            .unwrap_or("open".to_string())
+            .to_lowercase()
+            .as_str()
+        {
+            "open" => RegistrationMode::Open,
+            "closed" => RegistrationMode::Closed,
+            "invitation" => RegistrationMode::Invitation,
+            "domain" => RegistrationMode::Domain,
+            other => panic!("Invalid APP_REGISTRATION_MODE: {}", other),
+        };
+        let allowed_domains: Vec<String> = std::env::var("APP_REGISTRATION_ALLOWED_DOMAINS")
+            .unwrap_or_default()
+            .split(',')
+            .map(|domain| domain.trim().trim_start_matches('@').to_lowercase())
+            .filter(|domain| !domain.is_empty())
+            .collect();
+        if mode == RegistrationMode::Domain && allowed_domains.is_empty() {
+            panic!("APP_REGISTRATION_ALLOWED_DOMAINS must be set in domain mode");
+        }
+        Self {
+            mode,
+            allowed_domains,
+        }
+    }
+
+    /// 邮箱域名是否在允许列表中
+    pub fn is_domain_allowed(&self, email: &str) -> bool {
+        email
+            .rsplit_once('@')
+            .is_some_and(|(_, domain)| self.allowed_domains.contains(&domain.to_lowercase()))
+    }
+
+    /// 不需要邀请码时，校验是否允许该邮箱注册
+    ///
+    /// 失败时返回错误信息
+    pub fn check_email(&self, email: &str) -> Result<(), &'static str> {
+        match self.mode {
+            RegistrationMode::Open => Ok(()),
+            RegistrationMode::Closed => Err("Registration is closed"),
+            RegistrationMode::Invitation => Err("Registration requires an invitation code"),
+            RegistrationMode::Domain if self.is_domain_allowed(email) => Ok(()),
+            RegistrationMode::Domain => Err("Email domain not allowed"),
+        }
+    }
+}
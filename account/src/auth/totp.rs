@@ -0,0 +1,64 @@
+//! TOTP 两步验证 (RFC 6238)
+use entity::user::User;
+use sqlx::PgPool;
+use totp_rs::{Algorithm, Secret, TOTP};
+
+/// 时间步长（秒）
+const STEP: u64 = 30;
+/// 允许的前后时间步偏差
+const SKEW: u64 = 1;
+
+/// 生成新的 TOTP 密钥
+///
+/// 160 位随机数, base32 编码
+pub fn generate_secret() -> String {
+    Secret::generate_secret().to_encoded().to_string()
+}
+
+/// 构建 TOTP
+///
+/// * `secret` - base32 编码的密钥
+/// * `account_name` - 在验证器应用中显示的账户名
+pub fn build(secret: &str, account_name: &str) -> TOTP {
+    TOTP::new(
+        Algorithm::SHA1,
+        6,
+        SKEW as u8,
+        STEP,
+        Secret::Encoded(secret.to_string()).to_bytes().unwrap(),
+        Some(std::env::var("APP_TOTP_ISSUER").unwrap_or("Blossom".to_string())),
+        account_name.to_string(),
+    )
+    .unwrap()
+}
+
+/// otpauth:// URI, 用于生成二维码
+pub fn otpauth_url(secret: &str, account_name: &str) -> String {
+    build(secret, account_name).get_url()
+}
+
+/// 校验用户的 TOTP 验证码
+///
+/// 每个时间步的验证码只能使用一次
+pub async fn verify(pool: &PgPool, user: &User, code: &str) -> Result<bool, sqlx::Error> {
+    let Some(secret) = &user.totp_secret else {
+        return Ok(false);
+    };
+    let totp = build(secret, &user.email);
+    let now = chrono::Utc::now().timestamp() as u64;
+    let current_step = now / STEP;
+    let Some(step) = (current_step.saturating_sub(SKEW)..=current_step + SKEW)
+        .find(|step| totp.generate(step * STEP) == code.trim())
+    else {
+        return Ok(false);
+    };
+    // 原子地更新最近使用的时间步，防止并发请求重复使用同一验证码
+    let result = sqlx::query(
+        r#"UPDATE "user" SET totp_last_step = $1 WHERE id = $2 AND (totp_last_step IS NULL OR totp_last_step < $1)"#,
+    )
+    .bind(step as i64)
+    .bind(user.id)
+    .execute(pool)
+    .await?;
+    Ok(result.rows_affected() == 1)
+}
@@ -0,0 +1,298 @@
+//! 个人数据导出与账户删除接口
+
+use crate::guards::{NoImpersonation, User};
+use chrono::Utc;
+use email::EmailBackend;
+use entity::history::{Education, Employment};
+use entity::identity::UserIdentity;
+use entity::password::PasswordConfig;
+use entity::person::{Person, PersonClaim};
+use entity::role::Role;
+use entity::session::UserSession;
+use entity::token::PersonalAccessToken;
+use entity::user::{AccountDeletionToken, LoginAttempt, User as UserModel};
+use image_service::{ImageServices, S3Client};
+use rocket::form::Form;
+use rocket::http::{Header, Status};
+use rocket::serde::json::Json;
+use rocket::{delete, get, post, routes, FromForm, Responder, State};
+use serde::Serialize;
+use sqlx::PgPool;
+use std::io::Write;
+use uuid::Uuid;
+use zip::write::SimpleFileOptions;
+use zip::ZipWriter;
+
+/// 账户删除宽限期（天）
+///
+/// * `APP_ACCOUNT_DELETION_GRACE_DAYS` - 默认 14
+fn deletion_grace_period() -> chrono::Duration {
+    chrono::Duration::days(match std::env::var("APP_ACCOUNT_DELETION_GRACE_DAYS") {
+        Ok(days) => days.parse().unwrap(),
+        _ => 14,
+    })
+}
+
+/// 导出的个人数据
+#[derive(Serialize)]
+struct AccountExport {
+    exported_at: chrono::DateTime<Utc>,
+    user: UserModel,
+    roles: Vec<String>,
+    identities: Vec<UserIdentity>,
+    sessions: Vec<UserSession>,
+    login_attempts: Vec<LoginAttempt>,
+    personal_access_tokens: Vec<PersonalAccessToken>,
+    /// 认领的人员
+    person: Option<Person>,
+    person_claims: Vec<PersonClaim>,
+    /// 认领人员的教育和工作经历
+    education: Vec<Education>,
+    employment: Vec<Employment>,
+}
+
+#[derive(Responder)]
+#[response(content_type = "application/zip")]
+struct ZipArchive {
+    data: Vec<u8>,
+    disposition: Header<'static>,
+}
+
+#[derive(Responder)]
+enum ExportResp {
+    Json(Json<Box<AccountExport>>),
+    Zip(ZipArchive),
+}
+
+/// 导出个人数据
+///
+/// * `format` - `json` (默认) 或 `zip`, zip 中包含 `account.json` 与头像、人员照片
+#[get("/account/export?<format>")]
+async fn export(
+    pool: &State<PgPool>,
+    image_services: &State<ImageServices>,
+    s3_client: &State<S3Client>,
+    user: User,
+    format: Option<&str>,
+) -> Result<ExportResp, (Status, &'static str)> {
+    let User(user) = user;
+    let person = Person::of_user(pool.inner(), user.id).await.unwrap();
+    let (education, employment) = match &person {
+        Some(person) => (
+            Education::of_person(pool.inner(), person.id).await.unwrap(),
+            Employment::of_person(pool.inner(), person.id)
+                .await
+                .unwrap(),
+        ),
+        None => (Vec::new(), Vec::new()),
+    };
+    let export = AccountExport {
+        exported_at: Utc::now(),
+        roles: Role::of_user(pool.inner(), user.id)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|role| role.name)
+            .collect(),
+        identities: UserIdentity::of_user(pool.inner(), user.id).await.unwrap(),
+        sessions: UserSession::of_user(pool.inner(), user.id).await.unwrap(),
+        login_attempts: LoginAttempt::of_user(pool.inner(), user.id).await.unwrap(),
+        personal_access_tokens: PersonalAccessToken::of_user(pool.inner(), user.id)
+            .await
+            .unwrap(),
+        person,
+        person_claims: PersonClaim::of_user(pool.inner(), user.id).await.unwrap(),
+        education,
+        employment,
+        user,
+    };
+    match format.unwrap_or("json") {
+        "json" => Ok(ExportResp::Json(Json(Box::new(export)))),
+        "zip" => {
+            let mut zip = ZipWriter::new(std::io::Cursor::new(Vec::new()));
+            let options = SimpleFileOptions::default();
+            zip.start_file("account.json", options).unwrap();
+            zip.write_all(
+                rocket::serde::json::to_pretty_string(&export)
+                    .unwrap()
+                    .as_bytes(),
+            )
+            .unwrap();
+            if let Some(avatar_id) = &export.user.avatar_id {
+                let avatar = image_services
+                    .avatar
+                    .get_image(&s3_client.internal, avatar_id)
+                    .await
+                    .map_err(|_| (Status::InternalServerError, "Cannot read avatar"))?;
+                zip.start_file(
+                    format!("avatar.{}", image_services.avatar.image_extension()),
+                    options,
+                )
+                .unwrap();
+                zip.write_all(&avatar).unwrap();
+            }
+            if let Some(photo_id) = export
+                .person
+                .as_ref()
+                .and_then(|person| person.photo_id.as_ref())
+            {
+                let photo = image_services
+                    .person_photo
+                    .get_image(&s3_client.internal, photo_id)
+                    .await
+                    .map_err(|_| (Status::InternalServerError, "Cannot read person photo"))?;
+                zip.start_file(
+                    format!(
+                        "person_photo.{}",
+                        image_services.person_photo.image_extension()
+                    ),
+                    options,
+                )
+                .unwrap();
+                zip.write_all(&photo).unwrap();
+            }
+            Ok(ExportResp::Zip(ZipArchive {
+                data: zip.finish().unwrap().into_inner(),
+                disposition: Header::new(
+                    "Content-Disposition",
+                    "attachment; filename=\"account.zip\"",
+                ),
+            }))
+        }
+        _ => Err((Status::BadRequest, "Unsupported format")),
+    }
+}
+
+#[derive(Debug, FromForm)]
+struct DeleteAccountReq {
+    /// 有密码的账户必填
+    password: Option<String>,
+}
+
+/// 计划删除账户，使所有登录立即失效并通知用户
+async fn schedule_deletion(pool: &PgPool, email_backend: &EmailBackend, user_id: i32, email: &str) {
+    let scheduled_at = Utc::now() + deletion_grace_period();
+    UserModel::schedule_deletion(pool, user_id, scheduled_at)
+        .await
+        .unwrap();
+    UserModel::invalidate_tokens(pool, user_id).await.unwrap();
+    let mut context = tera::Context::new();
+    context.insert("scheduled_at", &scheduled_at.to_rfc3339());
+    if let Err(e) = email_backend
+        .send_template(
+            email.parse().unwrap(),
+            "Your account is scheduled for deletion",
+            "account_deletion.html",
+            &context,
+        )
+        .await
+    {
+        eprintln!("{}", e);
+    }
+}
+
+/// 申请删除账户
+///
+/// 账户在宽限期后被删除，期间重新登录即取消删除；所有登录立即失效。
+/// 没有密码的账户不能仅凭 access token 确认，会收到确认邮件，
+/// 通过 `/account/delete/confirm/<token>` 确认后才计划删除
+#[delete("/account", data = "<data>")]
+async fn delete_account(
+    pool: &State<PgPool>,
+    email_backend: &State<EmailBackend>,
+    password_config: &State<PasswordConfig>,
+    user: NoImpersonation<User>,
+    data: Form<DeleteAccountReq>,
+) -> (Status, &'static str) {
+    let NoImpersonation(User(user)) = user;
+    if user.password.is_none() {
+        let deletion_token = AccountDeletionToken::new(user.id);
+        deletion_token.save(pool.inner()).await.unwrap();
+        let mut context = tera::Context::new();
+        context.insert(
+            "confirm_url",
+            std::env::var("APP_ACCOUNT_DELETION_URL_FORMAT")
+                .unwrap_or("http://localhost:8000/account/delete/confirm/<token>".to_string())
+                .replace("<token>", &deletion_token.token.to_string())
+                .as_str(),
+        );
+        context.insert("expire_minutes", &AccountDeletionToken::EXPIRE_MINUTES);
+        if let Err(e) = email_backend
+            .send_template(
+                user.email.parse().unwrap(),
+                "Confirm deleting your account",
+                "account_deletion_confirm.html",
+                &context,
+            )
+            .await
+        {
+            eprintln!("{}", e);
+        }
+        return (Status::Ok, "Confirmation email sent");
+    }
+    let confirmed = data.password.as_deref().is_some_and(|password| {
+        user.verify_password(password_config.inner(), password)
+            .unwrap_or(false)
+    });
+    if !confirmed {
+        return (Status::BadRequest, "wrong password");
+    }
+    schedule_deletion(pool.inner(), email_backend.inner(), user.id, &user.email).await;
+    (Status::Ok, "Success")
+}
+
+/// 通过邮件确认删除没有密码的账户
+#[post("/account/delete/confirm/<token>")]
+async fn confirm_delete_account(
+    pool: &State<PgPool>,
+    email_backend: &State<EmailBackend>,
+    token: Uuid,
+) -> (Status, &'static str) {
+    let Some(token) = AccountDeletionToken::consume(pool.inner(), &token)
+        .await
+        .unwrap()
+    else {
+        return (Status::Unauthorized, "Invalid token");
+    };
+    let email: String = sqlx::query_scalar(r#"SELECT email FROM "user" WHERE id = $1"#)
+        .bind(token.user_id)
+        .fetch_one(pool.inner())
+        .await
+        .unwrap();
+    schedule_deletion(pool.inner(), email_backend.inner(), token.user_id, &email).await;
+    (Status::Ok, "Success")
+}
+
+/// 删除宽限期已过的账户及其头像
+///
+/// 单个账户删除失败时记录错误并继续，不影响其他账户
+pub(crate) async fn purge_deleted_accounts(
+    pool: &PgPool,
+    image_services: &ImageServices,
+    s3_client: &S3Client,
+) -> Result<(), sqlx::Error> {
+    let users = sqlx::query_as::<_, UserModel>(
+        r#"SELECT * FROM "user" WHERE deletion_scheduled_at <= CURRENT_TIMESTAMP"#,
+    )
+    .fetch_all(pool)
+    .await?;
+    for user in users {
+        if let Err(e) = UserModel::delete(pool, user.id).await {
+            eprintln!("Failed to delete user {}: {}", user.id, e);
+            continue;
+        }
+        if let Some(avatar_id) = &user.avatar_id
+            && let Err(e) = image_services
+                .avatar
+                .delete_image(&s3_client.internal, avatar_id)
+                .await
+        {
+            eprintln!("{:?}", e);
+        }
+    }
+    Ok(())
+}
+
+pub fn routes() -> Vec<rocket::Route> {
+    routes![export, delete_account, confirm_delete_account]
+}
@@ -1,21 +1,55 @@
 use crate::auth::jwt::{validate_token, Claims, JWTConfig};
-use entity::user::User as UserModel;
+use crate::rbac::{PermissionType, RoleType};
+use entity::role::{Permission, Role};
+use entity::session::UserSession;
+use entity::token::{PersonalAccessToken, RevokedToken};
+use entity::user::{AccountStatus, User as UserModel};
 use rocket::http::Status;
 use rocket::request::{self, FromRequest, Request};
 use sqlx::PgPool;
+use std::marker::PhantomData;
 
 /// jwt claims
 ///
-/// 仅校验 jwt claims 正确性，不进行数据库校验
+/// 校验 jwt claims 正确性及是否已被吊销，不校验用户是否存在
+///
+/// 个人访问令牌只能通过 [`Require`] 访问其权限范围内的接口，其余守卫均返回 403
 pub struct UserClaims(pub Claims);
 /// user id
 ///
-/// 仅判定用户是否存在
+/// 仅判定用户是否存在且未被停用
 pub struct UserId(pub i32);
 /// full user
 ///
-/// 判定用户是否存在并返回用户表全部内容
+/// 判定用户是否存在且未被停用，并返回用户表全部内容
 pub struct User(pub UserModel);
+/// user with role
+///
+/// 判定用户是否拥有角色 `R`，否则返回 403
+pub struct UserWithRole<R: RoleType>(pub UserModel, PhantomData<R>);
+/// user with permission
+///
+/// 判定用户是否（通过任一角色）拥有权限 `P`，否则返回 403
+///
+/// 使用个人访问令牌时还要求令牌的权限范围包含 `P`
+pub struct Require<P: PermissionType>(pub UserModel, PhantomData<P>);
+
+/// 拒绝代为登录的 token
+///
+/// 包装 [`User`] 或 [`UserClaims`], 用于令牌、两步验证、会话、密码与删除账户等只允许用户本人进行的操作
+pub struct NoImpersonation<G>(pub G);
+
+/// 客户端信息
+///
+/// 总是成功，取不到时为 `None`
+pub struct ClientInfo {
+    pub ip: Option<String>,
+    /// 最多保留 512 个字符
+    pub user_agent: Option<String>,
+}
+
+/// 无过期时间的个人访问令牌的 `exp` (9999-12-31T23:59:59Z)
+const PERSONAL_ACCESS_TOKEN_MAX_EXP: usize = 253402300799;
 
 #[doc(hidden)]
 async fn get_claims_from_req(req: &Request<'_>) -> Result<Claims, &'static str> {
@@ -24,13 +58,85 @@ async fn get_claims_from_req(req: &Request<'_>) -> Result<Claims, &'static str>
         .headers()
         .get_one("Authorization")
         .and_then(|h| h.strip_prefix("Bearer "));
-    match token {
-        Some(token) => match validate_token(token, jwt_config) {
-            Ok(claims) => Ok(claims),
-            Err(_) => Err("Invalid token"),
-        },
-        None => Err("Not logged in"),
+    let pool = req.rocket().state::<PgPool>().unwrap();
+    let claims = match token {
+        Some(token) if token.starts_with(PersonalAccessToken::PREFIX) => {
+            let token = PersonalAccessToken::authenticate(pool, token)
+                .await
+                .unwrap()
+                .ok_or("Invalid token")?;
+            Claims {
+                sub: token.user_id,
+                exp: token
+                    .expire
+                    .map_or(PERSONAL_ACCESS_TOKEN_MAX_EXP, |expire| {
+                        expire.timestamp() as usize
+                    }),
+                iat: token.created_at.timestamp() as usize,
+                jti: token.id,
+                iss: None,
+                aud: None,
+                sid: None,
+                act: None,
+                scopes: Some(token.scopes),
+            }
+        }
+        Some(token) => validate_token(token, jwt_config).map_err(|_| "Invalid token")?,
+        None => return Err("Not logged in"),
+    };
+    // 个人访问令牌由 `invalidate_tokens` 直接删除，不受 `tokens_valid_after` 影响
+    let issued_at = (!claims.is_personal_access_token()).then(|| claims.issued_at());
+    if RevokedToken::is_revoked(pool, &claims.jti, claims.sub, issued_at)
+        .await
+        .unwrap()
+    {
+        return Err("Token revoked");
     }
+    // 会话被吊销后，该会话此前签发的 access token 一并失效
+    if let Some(sid) = &claims.sid
+        && UserSession::is_revoked(pool, sid).await.unwrap()
+    {
+        return Err("Token revoked");
+    }
+    if claims.is_personal_access_token() {
+        PersonalAccessToken::touch(pool, &claims.jti).await.unwrap();
+    }
+    Ok(claims)
+}
+
+/// 拒绝个人访问令牌
+#[doc(hidden)]
+async fn get_session_claims_from_req(req: &Request<'_>) -> Result<Claims, (Status, &'static str)> {
+    let claims = get_claims_from_req(req)
+        .await
+        .map_err(|err| (Status::Unauthorized, err))?;
+    if claims.is_personal_access_token() {
+        return Err((Status::Forbidden, "Personal access token not allowed"));
+    }
+    Ok(claims)
+}
+
+#[doc(hidden)]
+async fn get_user_by_claims(req: &Request<'_>, claims: &Claims) -> Result<UserModel, &'static str> {
+    let pool = req.rocket().state::<PgPool>().unwrap();
+    let user = sqlx::query_as::<_, UserModel>(r#"SELECT * FROM "user" WHERE id = $1"#)
+        .bind(claims.sub)
+        .fetch_optional(pool)
+        .await
+        .unwrap()
+        .ok_or("Invalid token")?;
+    if user.status == AccountStatus::Suspended {
+        return Err("Account suspended");
+    }
+    Ok(user)
+}
+
+#[doc(hidden)]
+async fn get_user_from_req(req: &Request<'_>) -> Result<UserModel, (Status, &'static str)> {
+    let claims = get_session_claims_from_req(req).await?;
+    get_user_by_claims(req, &claims)
+        .await
+        .map_err(|err| (Status::Unauthorized, err))
 }
 
 #[rocket::async_trait]
@@ -38,9 +144,9 @@ impl<'r> FromRequest<'r> for UserClaims {
     type Error = &'static str;
 
     async fn from_request(req: &'r Request<'_>) -> request::Outcome<Self, Self::Error> {
-        match get_claims_from_req(req).await {
+        match get_session_claims_from_req(req).await {
             Ok(claims) => request::Outcome::Success(UserClaims(claims)),
-            Err(err) => request::Outcome::Error((Status::Unauthorized, err)),
+            Err(err) => request::Outcome::Error(err),
         }
     }
 }
@@ -50,15 +156,17 @@ impl<'r> FromRequest<'r> for UserId {
     type Error = &'static str;
 
     async fn from_request(req: &'r Request<'_>) -> request::Outcome<Self, Self::Error> {
-        match get_claims_from_req(req).await {
+        match get_session_claims_from_req(req).await {
             Ok(claims) => {
                 let pool = req.rocket().state::<PgPool>().unwrap();
-                let exists: bool =
-                    sqlx::query_scalar(r#"SELECT EXISTS(SELECT 1 FROM user WHERE id = $1)"#)
-                        .bind(claims.sub)
-                        .fetch_one(pool)
-                        .await
-                        .unwrap();
+                let exists: bool = sqlx::query_scalar(
+                    r#"SELECT EXISTS(SELECT 1 FROM "user" WHERE id = $1 AND status <> $2)"#,
+                )
+                .bind(claims.sub)
+                .bind(AccountStatus::Suspended)
+                .fetch_one(pool)
+                .await
+                .unwrap();
 
                 if exists {
                     request::Outcome::Success(UserId(claims.sub))
@@ -66,7 +174,7 @@ impl<'r> FromRequest<'r> for UserId {
                     request::Outcome::Error((Status::Unauthorized, "Invalid token"))
                 }
             }
-            Err(err) => request::Outcome::Error((Status::Unauthorized, err)),
+            Err(err) => request::Outcome::Error(err),
         }
     }
 }
@@ -76,22 +184,85 @@ impl<'r> FromRequest<'r> for User {
     type Error = &'static str;
 
     async fn from_request(req: &'r Request<'_>) -> request::Outcome<Self, Self::Error> {
-        match get_claims_from_req(req).await {
-            Ok(claims) => {
-                let pool = req.rocket().state::<PgPool>().unwrap();
-                let user = sqlx::query_as::<_, UserModel>(r#"SELECT * FROM "user" WHERE id = $1"#)
-                    .bind(claims.sub)
-                    .fetch_optional(pool)
-                    .await
-                    .unwrap();
-                match user {
-                    Some(user) => request::Outcome::Success(User(user)),
-                    None => request::Outcome::Error((Status::Unauthorized, "Invalid token")),
-                }
+        match get_user_from_req(req).await {
+            Ok(user) => request::Outcome::Success(User(user)),
+            Err(err) => request::Outcome::Error(err),
+        }
+    }
+}
+
+#[rocket::async_trait]
+impl<'r, R: RoleType> FromRequest<'r> for UserWithRole<R> {
+    type Error = &'static str;
+
+    async fn from_request(req: &'r Request<'_>) -> request::Outcome<Self, Self::Error> {
+        let user = match get_user_from_req(req).await {
+            Ok(user) => user,
+            Err(err) => return request::Outcome::Error(err),
+        };
+        let pool = req.rocket().state::<PgPool>().unwrap();
+        if Role::user_has(pool, user.id, R::NAME).await.unwrap() {
+            request::Outcome::Success(UserWithRole(user, PhantomData))
+        } else {
+            request::Outcome::Error((Status::Forbidden, "Permission denied"))
+        }
+    }
+}
+
+#[rocket::async_trait]
+impl<'r, P: PermissionType> FromRequest<'r> for Require<P> {
+    type Error = &'static str;
+
+    async fn from_request(req: &'r Request<'_>) -> request::Outcome<Self, Self::Error> {
+        let claims = match get_claims_from_req(req).await {
+            Ok(claims) => claims,
+            Err(err) => return request::Outcome::Error((Status::Unauthorized, err)),
+        };
+        let user = match get_user_by_claims(req, &claims).await {
+            Ok(user) => user,
+            Err(err) => return request::Outcome::Error((Status::Unauthorized, err)),
+        };
+        if !claims.allows(P::CODE) {
+            return request::Outcome::Error((Status::Forbidden, "Permission denied"));
+        }
+        let pool = req.rocket().state::<PgPool>().unwrap();
+        if Permission::user_has(pool, user.id, P::CODE).await.unwrap() {
+            request::Outcome::Success(Require(user, PhantomData))
+        } else {
+            request::Outcome::Error((Status::Forbidden, "Permission denied"))
+        }
+    }
+}
+
+#[rocket::async_trait]
+impl<'r, G> FromRequest<'r> for NoImpersonation<G>
+where
+    G: FromRequest<'r, Error = &'static str>,
+{
+    type Error = &'static str;
+
+    async fn from_request(req: &'r Request<'_>) -> request::Outcome<Self, Self::Error> {
+        match get_session_claims_from_req(req).await {
+            Ok(claims) if claims.act.is_some() => {
+                request::Outcome::Error((Status::Forbidden, "Not allowed while impersonating"))
             }
-            Err(err) => request::Outcome::Error((Status::Unauthorized, err)),
+            Ok(_) => G::from_request(req).await.map(NoImpersonation),
+            Err(err) => request::Outcome::Error(err),
         }
     }
 }
 
-//TODO: UserWithRole<RoleType>
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for ClientInfo {
+    type Error = std::convert::Infallible;
+
+    async fn from_request(req: &'r Request<'_>) -> request::Outcome<Self, Self::Error> {
+        request::Outcome::Success(ClientInfo {
+            ip: req.client_ip().map(|ip| ip.to_string()),
+            user_agent: req
+                .headers()
+                .get_one("User-Agent")
+                .map(|user_agent| user_agent.chars().take(512).collect()),
+        })
+    }
+}
@@ -1,6 +1,24 @@
+mod admin;
 pub mod auth;
-mod guards;
+mod data;
+pub mod guards;
+mod mfa;
+pub mod rbac;
 mod routes;
+mod sessions;
+mod sso;
+pub mod tasks;
+mod tokens;
 mod validators;
 
-pub use routes::routes;
+/// 账户模块全部路由
+pub fn routes() -> Vec<rocket::Route> {
+    let mut routes = routes::routes();
+    routes.extend(mfa::routes());
+    routes.extend(data::routes());
+    routes.extend(sessions::routes());
+    routes.extend(sso::routes());
+    routes.extend(tokens::routes());
+    routes.extend(admin::routes());
+    routes
+}
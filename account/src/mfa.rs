@@ -0,0 +1,240 @@
+//! 两步验证接口
+
+use crate::auth::jwt::JWTConfig;
+use crate::auth::lockout::{self, LockoutConfig};
+use crate::auth::totp;
+use crate::guards::{ClientInfo, NoImpersonation, User};
+use crate::routes::{start_session, LoginResp};
+use email::EmailBackend;
+use entity::password::PasswordConfig;
+use entity::token::{MfaChallenge, TotpRecoveryCode};
+use entity::user::{AccountStatus, AuthUser, User as UserModel};
+use rocket::form::Form;
+use rocket::http::Status;
+use rocket::serde::json::Json;
+use rocket::{post, routes, FromForm, State};
+use serde::Serialize;
+use sqlx::PgPool;
+
+/// 每个临时凭证允许的尝试次数
+const MAX_MFA_ATTEMPTS: i32 = 5;
+
+#[derive(Serialize)]
+struct TotpSetupResp {
+    secret: String,
+    otpauth_url: String,
+}
+
+#[post("/account/totp/setup")]
+async fn setup(
+    pool: &State<PgPool>,
+    user: NoImpersonation<User>,
+) -> Result<Json<TotpSetupResp>, (Status, &'static str)> {
+    let NoImpersonation(User(user)) = user;
+    if user.totp_enabled {
+        return Err((
+            Status::Conflict,
+            "two-factor authentication already enabled",
+        ));
+    }
+    // 确认前仅保存密钥，不启用
+    let secret = totp::generate_secret();
+    sqlx::query(r#"UPDATE "user" SET totp_secret=$1, totp_last_step=NULL WHERE id=$2"#)
+        .bind(&secret)
+        .bind(user.id)
+        .execute(pool.inner())
+        .await
+        .unwrap();
+    Ok(Json(TotpSetupResp {
+        otpauth_url: totp::otpauth_url(&secret, &user.email),
+        secret,
+    }))
+}
+
+#[derive(Debug, FromForm)]
+struct TotpCodeReq {
+    code: String,
+}
+
+#[derive(Serialize)]
+struct RecoveryCodesResp {
+    recovery_codes: Vec<String>,
+}
+
+#[post("/account/totp/confirm", data = "<data>")]
+async fn confirm(
+    pool: &State<PgPool>,
+    user: NoImpersonation<User>,
+    data: Form<TotpCodeReq>,
+) -> Result<Json<RecoveryCodesResp>, (Status, &'static str)> {
+    let NoImpersonation(User(user)) = user;
+    if user.totp_enabled {
+        return Err((
+            Status::Conflict,
+            "two-factor authentication already enabled",
+        ));
+    }
+    if user.totp_secret.is_none() {
+        return Err((Status::BadRequest, "two-factor authentication not set up"));
+    }
+    if !totp::verify(pool.inner(), &user, &data.code).await.unwrap() {
+        return Err((Status::BadRequest, "wrong code"));
+    }
+    sqlx::query(r#"UPDATE "user" SET totp_enabled=TRUE WHERE id=$1"#)
+        .bind(user.id)
+        .execute(pool.inner())
+        .await
+        .unwrap();
+    let recovery_codes = TotpRecoveryCode::regenerate(pool.inner(), user.id)
+        .await
+        .unwrap();
+    Ok(Json(RecoveryCodesResp { recovery_codes }))
+}
+
+/// 校验 TOTP 验证码或恢复码
+async fn verify_code(pool: &PgPool, user: &UserModel, code: &str) -> bool {
+    totp::verify(pool, user, code).await.unwrap()
+        || TotpRecoveryCode::consume(pool, user.id, code)
+            .await
+            .unwrap()
+}
+
+#[post("/account/totp/recovery-codes", data = "<data>")]
+async fn regenerate_recovery_codes(
+    pool: &State<PgPool>,
+    user: NoImpersonation<User>,
+    data: Form<TotpCodeReq>,
+) -> Result<Json<RecoveryCodesResp>, (Status, &'static str)> {
+    let NoImpersonation(User(user)) = user;
+    if !user.totp_enabled {
+        return Err((Status::BadRequest, "two-factor authentication not enabled"));
+    }
+    if !totp::verify(pool.inner(), &user, &data.code).await.unwrap() {
+        return Err((Status::BadRequest, "wrong code"));
+    }
+    let recovery_codes = TotpRecoveryCode::regenerate(pool.inner(), user.id)
+        .await
+        .unwrap();
+    Ok(Json(RecoveryCodesResp { recovery_codes }))
+}
+
+#[derive(Debug, FromForm)]
+struct DisableTotpReq {
+    password: String,
+    code: String,
+}
+
+#[post("/account/totp/disable", data = "<data>")]
+async fn disable(
+    pool: &State<PgPool>,
+    password_config: &State<PasswordConfig>,
+    user: NoImpersonation<User>,
+    data: Form<DisableTotpReq>,
+) -> (Status, &'static str) {
+    let NoImpersonation(User(user)) = user;
+    if !user.totp_enabled {
+        return (Status::BadRequest, "two-factor authentication not enabled");
+    }
+    if !user
+        .verify_password(password_config.inner(), &data.password)
+        .unwrap_or(false)
+        || !verify_code(pool.inner(), &user, &data.code).await
+    {
+        return (Status::BadRequest, "wrong password or code");
+    }
+    sqlx::query(
+        r#"UPDATE "user" SET totp_secret=NULL, totp_enabled=FALSE, totp_last_step=NULL WHERE id=$1"#,
+    )
+    .bind(user.id)
+    .execute(pool.inner())
+    .await
+    .unwrap();
+    TotpRecoveryCode::delete_all(pool.inner(), user.id)
+        .await
+        .unwrap();
+    (Status::Ok, "Success")
+}
+
+#[derive(Debug, FromForm)]
+struct MfaLoginReq {
+    mfa_token: String,
+    /// TOTP 验证码或恢复码
+    code: String,
+}
+
+#[post("/auth/mfa", data = "<data>")]
+async fn mfa_login(
+    pool: &State<PgPool>,
+    jwt_config: &State<JWTConfig>,
+    lockout_config: &State<LockoutConfig>,
+    email_backend: &State<EmailBackend>,
+    client: ClientInfo,
+    data: Form<MfaLoginReq>,
+) -> Result<Json<LoginResp>, (Status, &'static str)> {
+    let challenge = MfaChallenge::attempt(pool.inner(), &data.mfa_token)
+        .await
+        .unwrap()
+        .ok_or((Status::Unauthorized, "Invalid token"))?;
+    if challenge.attempts > MAX_MFA_ATTEMPTS {
+        challenge.delete(pool.inner()).await.unwrap();
+        return Err((Status::Unauthorized, "Invalid token"));
+    }
+    let user = sqlx::query_as::<_, UserModel>(r#"SELECT * FROM "user" WHERE id = $1"#)
+        .bind(challenge.user_id)
+        .fetch_one(pool.inner())
+        .await
+        .unwrap();
+    if user.status != AccountStatus::Active {
+        challenge.delete(pool.inner()).await.unwrap();
+        return Err((Status::Unauthorized, "your account is inactive"));
+    }
+    // 验证码的失败次数同样计入账户锁定，避免反复获取临时凭证暴力破解
+    let auth_user = sqlx::query_as::<_, AuthUser>(
+        r#"SELECT id, email, password, status, failed_login_count, locked_until, totp_enabled FROM "user" WHERE id=$1"#,
+    )
+    .bind(user.id)
+    .fetch_one(pool.inner())
+    .await
+    .unwrap();
+    if auth_user.is_locked() {
+        challenge.delete(pool.inner()).await.unwrap();
+        return Err((Status::Unauthorized, "Invalid token"));
+    }
+    if !verify_code(pool.inner(), &user, &data.code).await {
+        lockout::record_failure(
+            pool.inner(),
+            lockout_config.inner(),
+            email_backend.inner(),
+            Some(&auth_user),
+            client.ip.as_deref(),
+        )
+        .await
+        .unwrap();
+        return Err((Status::Unauthorized, "wrong code"));
+    }
+    challenge.delete(pool.inner()).await.unwrap();
+    lockout::record_success(pool.inner(), &auth_user, client.ip.as_deref())
+        .await
+        .unwrap();
+    Ok(Json(
+        start_session(
+            pool.inner(),
+            jwt_config.inner(),
+            email_backend.inner(),
+            user.id,
+            &user.email,
+            &client,
+        )
+        .await,
+    ))
+}
+
+pub fn routes() -> Vec<rocket::Route> {
+    routes![
+        setup,
+        confirm,
+        regenerate_recovery_codes,
+        disable,
+        mfa_login
+    ]
+}
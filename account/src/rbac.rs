@@ -0,0 +1,82 @@
+//! RBAC 角色与权限类型
+//!
+//! 角色、权限本身储存在数据库中，这里的类型仅作为请求守卫的类型参数使用，
+//! 与数据库中的 `role.name` / `permission.code` 一一对应
+//!
+//! 授权只以 `user_role` 为准：原来的 `user.admin_level` 在迁移时转换为 `admin` 角色后已被删除
+//!
+//! Examples:
+//! ```ignore
+//! use account::guards::{Require, UserWithRole};
+//! use account::rbac::{perm, role};
+//!
+//! #[get("/admin/only")]
+//! async fn admin_only(user: UserWithRole<role::Admin>) {}
+//!
+//! #[post("/person")]
+//! async fn create_person(user: Require<perm::PersonWrite>) {}
+//! ```
+
+/// 角色类型
+pub trait RoleType: Send + Sync + 'static {
+    /// 对应 `role.name`
+    const NAME: &'static str;
+}
+
+/// 权限类型
+pub trait PermissionType: Send + Sync + 'static {
+    /// 对应 `permission.code`
+    const CODE: &'static str;
+}
+
+macro_rules! define_roles {
+    ($($(#[$attr:meta])* $ident:ident => $name:literal),* $(,)?) => {
+        $(
+            $(#[$attr])*
+            pub struct $ident;
+
+            impl super::RoleType for $ident {
+                const NAME: &'static str = $name;
+            }
+        )*
+    };
+}
+
+macro_rules! define_permissions {
+    ($($(#[$attr:meta])* $ident:ident => $code:literal),* $(,)?) => {
+        $(
+            $(#[$attr])*
+            pub struct $ident;
+
+            impl super::PermissionType for $ident {
+                const CODE: &'static str = $code;
+            }
+        )*
+    };
+}
+
+/// 内置角色
+pub mod role {
+    define_roles! {
+        /// 管理员
+        Admin => "admin",
+        /// 普通成员
+        Member => "member",
+    }
+}
+
+/// 内置权限
+pub mod perm {
+    define_permissions! {
+        /// 查看同学录
+        PersonRead => "person.read",
+        /// 新增、修改同学录
+        PersonWrite => "person.write",
+        /// 删除同学录
+        PersonDelete => "person.delete",
+        /// 管理用户
+        UserManage => "user.manage",
+        /// 管理角色与权限
+        RoleManage => "role.manage",
+    }
+}
@@ -1,13 +1,26 @@
 use crate::auth::jwt;
-use crate::auth::jwt::JWTConfig;
-use crate::guards::User;
+use crate::auth::jwt::{Claims, JWTConfig};
+use crate::auth::lockout;
+use crate::auth::lockout::LockoutConfig;
+use crate::auth::registration::{RegistrationConfig, RegistrationMode};
+use crate::guards::{ClientInfo, NoImpersonation, User, UserClaims};
+use crate::rbac::{role, RoleType};
 use crate::validators::validate_password_level;
 use chrono::Utc;
-use email::{templates, EmailBackend};
-use entity::user::{hash_password, AccountStatus, AuthUser, UserProfile, UserVerificationToken};
+use email::EmailBackend;
+use entity::invitation::InvitationCode;
+use entity::password::PasswordConfig;
+use entity::person::{Person, PersonProfile, Viewer};
+use entity::role::Role;
+use entity::session::UserSession;
+use entity::token::{MfaChallenge, RefreshToken, RevokedToken};
+use entity::user::{
+    AccountStatus, AuthUser, EmailChangeToken, PasswordResetToken, User as UserModel, UserProfile,
+    UserVerificationToken,
+};
 use image_service::utils::open_image;
 use image_service::{ImageServices, S3Client};
-use lettre::{AsyncTransport, Message};
+use jsonwebtoken::jwk::JwkSet;
 use rocket::form::Form;
 use rocket::fs::TempFile;
 use rocket::http::Status;
@@ -35,6 +48,73 @@ struct RegisterReq {
     )]
     #[field(validate = validate_password_level())]
     password: String,
+    /// 邀请码，除关闭注册外的任何模式下均可使用；使用后账户只能认领邀请码所属届的人员
+    invitation_code: Option<String>,
+}
+
+/// 创建激活码并发送激活邮件
+///
+/// 用户已有的激活码会被替换
+pub(crate) async fn send_verification_email(
+    email_backend: &EmailBackend,
+    pool: &PgPool,
+    user_id: i32,
+    email: &str,
+) {
+    let verification_token = UserVerificationToken::new(user_id);
+    verification_token.save(pool).await.unwrap();
+    let mut context = tera::Context::new();
+    context.insert(
+        "verification_url",
+        std::env::var("APP_ACCOUNT_ACTIVATION_URL_FORMAT")
+            .unwrap_or("http://localhost:8000/account/verification/<token>".to_string())
+            .replace("<token>", &verification_token.token.to_string())
+            .as_str(),
+    );
+    if let Err(e) = email_backend
+        .send_template(
+            email.parse().unwrap(),
+            "Welcome to our service!",
+            "verification.html",
+            &context,
+        )
+        .await
+    {
+        eprintln!("{}", e);
+    }
+}
+
+/// 创建密码重置 token 并发送重置邮件
+///
+/// 用户已有的重置 token 会被替换
+pub(crate) async fn send_password_reset_email(
+    email_backend: &EmailBackend,
+    pool: &PgPool,
+    user_id: i32,
+    email: &str,
+) {
+    let reset_token = PasswordResetToken::new(user_id);
+    reset_token.save(pool).await.unwrap();
+    let mut context = tera::Context::new();
+    context.insert(
+        "reset_url",
+        std::env::var("APP_PASSWORD_RESET_URL_FORMAT")
+            .unwrap_or("http://localhost:8000/account/password/reset?token=<token>".to_string())
+            .replace("<token>", &reset_token.token.to_string())
+            .as_str(),
+    );
+    context.insert("expire_minutes", &PasswordResetToken::EXPIRE_MINUTES);
+    if let Err(e) = email_backend
+        .send_template(
+            email.parse().unwrap(),
+            "Reset your password",
+            "password_reset.html",
+            &context,
+        )
+        .await
+    {
+        eprintln!("{}", e);
+    }
 }
 
 #[post("/account/register", data = "<form>")]
@@ -43,6 +123,8 @@ async fn register(
     image_services: &State<ImageServices>,
     s3_client: &State<S3Client>,
     email_backend: &State<EmailBackend>,
+    registration_config: &State<RegistrationConfig>,
+    password_config: &State<PasswordConfig>,
     pool: &State<PgPool>,
 ) -> Result<Json<UserProfile>, ValidateError> {
     // 表单校验
@@ -59,52 +141,57 @@ async fn register(
             Vec::from(["Email existed".to_string()]),
         )]))));
     }
-    // 创建用户
-    sqlx::query(r#"INSERT INTO "user" (email, username, password) VALUES ($1, $2, $3)"#)
-        .bind(&data.email)
-        .bind(&data.username)
-        .bind(hash_password(&data.password).unwrap())
-        .execute(pool.inner())
+    let mut tx = pool.begin().await.unwrap();
+    // 校验注册方式
+    let invitation = match data.invitation_code.as_deref() {
+        Some(code) if registration_config.mode != RegistrationMode::Closed => {
+            match InvitationCode::redeem(&mut *tx, code).await.unwrap() {
+                Some(invitation) => Some(invitation),
+                None => {
+                    return Err(BadRequest(Json(HashMap::from([(
+                        "invitation_code".to_string(),
+                        Vec::from(["Invalid invitation code".to_string()]),
+                    )]))));
+                }
+            }
+        }
+        _ => {
+            if let Err(e) = registration_config.check_email(&data.email) {
+                return Err(BadRequest(Json(HashMap::from([(
+                    "email".to_string(),
+                    Vec::from([e.to_string()]),
+                )]))));
+            }
+            None
+        }
+    };
+    // 创建用户并授予默认角色
+    let user_id: i32 = sqlx::query_scalar(
+        r#"INSERT INTO "user" (email, username, password, invitation_id) VALUES ($1, $2, $3, $4) RETURNING id"#,
+    )
+    .bind(&data.email)
+    .bind(&data.username)
+    .bind(password_config.hash(&data.password).unwrap())
+    .bind(invitation.map(|invitation| invitation.id))
+    .fetch_one(&mut *tx)
+    .await
+    .unwrap();
+    Role::grant(&mut *tx, user_id, role::Member::NAME)
         .await
         .unwrap();
+    tx.commit().await.unwrap();
 
     // 获取用户信息并返回
     let mut user = sqlx::query_as::<_, UserProfile>(
-        r#"SELECT id, email, admin_level, username, avatar_id, status, created_at, updated_at FROM "user" WHERE email=$1"#,
+        r#"SELECT id, email, username, avatar_id, status, totp_enabled, created_at, updated_at FROM "user" WHERE id=$1"#,
     )
-        .bind(&data.email)
+        .bind(user_id)
         .fetch_one(pool.inner())
         .await
         .unwrap();
 
-    // 创建激活码
-    let verification_token = UserVerificationToken::new(user.id);
-    println!("{:?}", verification_token.token);
-    // 发送邮件
-    let mut context = tera::Context::new();
-    context.insert(
-        "verification_url",
-        std::env::var("APP_ACCOUNT_ACTIVATION_URL_FORMAT")
-            .unwrap_or("http://localhost:8000/account/verification/<token>".to_string())
-            .replace("<token>", &verification_token.token.to_string())
-            .as_str(),
-    );
-    let body = templates().render("verification.html", &context).unwrap();
-    // 构建邮件
-    let email = Message::builder()
-        .from(email_backend.from.clone())
-        .to(user.email.parse().unwrap())
-        .subject("Welcome to our service!")
-        .header(lettre::message::header::ContentType::TEXT_HTML)
-        .body(body)
-        .unwrap();
-    match email_backend.send(email).await {
-        Ok(_) => {}
-        Err(e) => eprintln!("{}", e),
-    }
-
-    // 保存
-    verification_token.save(pool.inner()).await.unwrap();
+    // 创建激活码并发送邮件
+    send_verification_email(email_backend.inner(), pool.inner(), user.id, &user.email).await;
 
     user.sign_avatar(&image_services.avatar, &s3_client.external)
         .await
@@ -119,12 +206,17 @@ async fn verification(pool: &State<PgPool>, token: Uuid) -> (Status, &'static st
         .unwrap()
     {
         Some(token) => {
-            sqlx::query(r#"UPDATE "user" SET status=$1 WHERE id=$2"#)
-                .bind(AccountStatus::Active)
-                .bind(token.user_id)
-                .execute(pool.inner())
-                .await
-                .unwrap();
+            // 已被停用的账户不会因激活而恢复
+            sqlx::query(
+                r#"UPDATE "user" SET status = CASE WHEN status=$3 THEN $1 ELSE status END,
+                   email_verified_at = CURRENT_TIMESTAMP WHERE id=$2"#,
+            )
+            .bind(AccountStatus::Active)
+            .bind(token.user_id)
+            .bind(AccountStatus::Inactive)
+            .execute(pool.inner())
+            .await
+            .unwrap();
             token.delete(pool.inner()).await.unwrap();
             (Status::Ok, "Success")
         }
@@ -132,6 +224,51 @@ async fn verification(pool: &State<PgPool>, token: Uuid) -> (Status, &'static st
     }
 }
 
+/// 同一用户重新发送激活邮件或密码重置邮件的最小间隔（秒）
+const EMAIL_RESEND_INTERVAL: i64 = 60;
+
+#[derive(Debug, FromForm)]
+struct ResendVerificationReq {
+    #[field(validate = is_email())]
+    email: String,
+}
+
+#[post("/account/verification/resend", data = "<form>")]
+async fn resend_verification(
+    form: ValidatedFormResult<ResendVerificationReq>,
+    email_backend: &State<EmailBackend>,
+    pool: &State<PgPool>,
+) -> Result<(Status, &'static str), ValidateError> {
+    let ValidatedForm(data) = form?;
+    let user = sqlx::query_as::<_, AuthUser>(
+        r#"SELECT id, email, password, status, failed_login_count, locked_until, totp_enabled FROM "user" WHERE email=$1"#,
+    )
+    .bind(&data.email)
+    .fetch_optional(pool.inner())
+    .await
+    .unwrap();
+    // 仅未激活的账户会收到邮件，但无论如何都返回相同的结果
+    // 发送过于频繁时静默丢弃，避免泄露账户是否存在
+    if let Some(user) = user
+        && user.status == AccountStatus::Inactive
+    {
+        let throttled = UserVerificationToken::latest_of_user(pool.inner(), user.id)
+            .await
+            .unwrap()
+            .is_some_and(|last| {
+                Utc::now() - last.created_at < chrono::Duration::seconds(EMAIL_RESEND_INTERVAL)
+            });
+        if !throttled {
+            send_verification_email(email_backend.inner(), pool.inner(), user.id, &user.email)
+                .await;
+        }
+    }
+    Ok((
+        Status::Ok,
+        "If the account exists and is inactive, a verification email has been sent",
+    ))
+}
+
 #[allow(unused_variables)]
 #[get("/account/verification/<token>")]
 async fn verification_get(token: Uuid) -> Status {
@@ -146,49 +283,598 @@ struct LoginReq {
 }
 
 #[derive(Serialize)]
-struct LoginResp {
+pub(crate) struct LoginResp {
     token: String,
+    refresh_token: String,
+}
+
+/// 登录结果
+///
+/// 启用两步验证的账户需要使用 `mfa_token` 与验证码请求 `/auth/mfa`
+#[derive(Serialize)]
+#[serde(untagged)]
+pub(crate) enum LoginResult {
+    Tokens(LoginResp),
+    MfaRequired {
+        mfa_required: bool,
+        mfa_token: String,
+    },
+}
+
+/// 两步验证临时凭证有效期（秒）
+const MFA_TOKEN_EXPIRATION: i64 = 300;
+
+/// 签发 access token 与 refresh token
+///
+/// * `session_id` - 会话 ID, 即 refresh token family
+pub(crate) async fn issue_tokens(
+    pool: &PgPool,
+    jwt_config: &JWTConfig,
+    user_id: i32,
+    session_id: Uuid,
+) -> LoginResp {
+    let refresh_token = RefreshToken::issue(
+        pool,
+        user_id,
+        Some(session_id),
+        chrono::Duration::seconds(jwt_config.refresh_expiration),
+    )
+    .await
+    .unwrap();
+    let claims = Claims::new(user_id, Some(session_id), jwt_config);
+    UserSession::touch(pool, &session_id, &claims.jti, claims.expire())
+        .await
+        .unwrap();
+    LoginResp {
+        token: jwt::create_token(&claims, jwt_config),
+        refresh_token,
+    }
+}
+
+/// 开始新的会话并签发 token
+///
+/// 设置 `APP_NEW_DEVICE_ALERT=true` 时，从新设备登录会发送邮件提醒
+///
+/// 账户处于删除宽限期时取消删除
+pub(crate) async fn start_session(
+    pool: &PgPool,
+    jwt_config: &JWTConfig,
+    email_backend: &EmailBackend,
+    user_id: i32,
+    email: &str,
+    client: &ClientInfo,
+) -> LoginResp {
+    let alert = std::env::var("APP_NEW_DEVICE_ALERT").is_ok_and(|alert| alert == "true")
+        && UserSession::is_new_device(pool, user_id, client.user_agent.as_deref())
+            .await
+            .unwrap();
+    // 宽限期内重新登录即取消删除
+    UserModel::cancel_deletion(pool, user_id).await.unwrap();
+    let session = UserSession::create(
+        pool,
+        user_id,
+        client.ip.as_deref(),
+        client.user_agent.as_deref(),
+    )
+    .await
+    .unwrap();
+    if alert {
+        let mut context = tera::Context::new();
+        context.insert("time", &session.created_at.to_rfc3339());
+        context.insert("ip", client.ip.as_deref().unwrap_or("unknown"));
+        context.insert(
+            "user_agent",
+            client.user_agent.as_deref().unwrap_or("unknown"),
+        );
+        if let Err(e) = email_backend
+            .send_template(
+                email.parse().unwrap(),
+                "New sign-in to your account",
+                "new_device_login.html",
+                &context,
+            )
+            .await
+        {
+            eprintln!("{}", e);
+        }
+    }
+    issue_tokens(pool, jwt_config, user_id, session.id).await
+}
+
+/// 身份校验通过后完成登录
+///
+/// 检查账户状态，启用两步验证的账户返回临时凭证，否则开始新的会话
+pub(crate) async fn complete_login(
+    pool: &PgPool,
+    jwt_config: &JWTConfig,
+    email_backend: &EmailBackend,
+    user: &AuthUser,
+    client: &ClientInfo,
+) -> Result<Json<LoginResult>, (Status, &'static str)> {
+    match user.status {
+        AccountStatus::Inactive => Err((Status::Unauthorized, "your account is inactive")),
+        AccountStatus::Suspended => Err((Status::Forbidden, "your account is suspended")),
+        AccountStatus::Active if user.totp_enabled => {
+            let mfa_token = MfaChallenge::issue(
+                pool,
+                user.id,
+                chrono::Duration::seconds(MFA_TOKEN_EXPIRATION),
+            )
+            .await
+            .unwrap();
+            Ok(Json(LoginResult::MfaRequired {
+                mfa_required: true,
+                mfa_token,
+            }))
+        }
+        AccountStatus::Active => Ok(Json(LoginResult::Tokens(
+            start_session(
+                pool,
+                jwt_config,
+                email_backend,
+                user.id,
+                &user.email,
+                client,
+            )
+            .await,
+        ))),
+    }
 }
 
 #[post("/auth/login", data = "<credentials>")]
 async fn login(
     pool: &State<PgPool>,
     jwt_config: &State<JWTConfig>,
+    lockout_config: &State<LockoutConfig>,
+    password_config: &State<PasswordConfig>,
+    email_backend: &State<EmailBackend>,
+    client: ClientInfo,
     credentials: Form<LoginReq>,
-) -> Result<Json<LoginResp>, (Status, &'static str)> {
+) -> Result<Json<LoginResult>, (Status, &'static str)> {
+    let ip = client.ip.as_deref();
+    if lockout::is_ip_blocked(pool.inner(), lockout_config.inner(), ip)
+        .await
+        .unwrap()
+    {
+        return Err((Status::TooManyRequests, "too many failed attempts"));
+    }
     let user = sqlx::query_as::<_, AuthUser>(
-        r#"SELECT id, email, password, status FROM "user" WHERE email=$1"#,
+        r#"SELECT id, email, password, status, failed_login_count, locked_until, totp_enabled FROM "user" WHERE email=$1"#,
     )
     .bind(&credentials.email)
     .fetch_optional(pool.inner())
     .await
     .unwrap();
+    // 锁定的账户与不存在的账户返回相同的结果，避免泄露账户是否存在
+    if let Some(user) = &user
+        && user.is_locked()
+    {
+        return Err((Status::Unauthorized, "wrong email or password."));
+    }
     match user {
-        Some(user) => match user.verify_password(credentials.password.as_str()) {
-            Ok(true) => match user.status {
-                AccountStatus::Inactive => Err((Status::Unauthorized, "your account is inactive")),
-                AccountStatus::Active => Ok(Json(LoginResp {
-                    token: jwt::create_token(user.id, jwt_config.inner()),
-                })),
-            },
-            _ => Err((Status::Unauthorized, "wrong email or password.")),
-        },
-        None => Err((Status::Unauthorized, "wrong email or password.")),
+        Some(user)
+            if user.verify_password(password_config.inner(), credentials.password.as_str())
+                == Ok(true) =>
+        {
+            // 旧算法或旧参数的 hash 在登录成功时重新加密
+            if user
+                .password
+                .as_deref()
+                .is_some_and(|hash| password_config.needs_rehash(hash))
+            {
+                sqlx::query(r#"UPDATE "user" SET password=$1 WHERE id=$2"#)
+                    .bind(password_config.hash(&credentials.password).unwrap())
+                    .bind(user.id)
+                    .execute(pool.inner())
+                    .await
+                    .unwrap();
+            }
+            // 启用两步验证的账户在第二步通过后才清除失败计数
+            if !user.totp_enabled {
+                lockout::record_success(pool.inner(), &user, ip)
+                    .await
+                    .unwrap();
+            }
+            complete_login(
+                pool.inner(),
+                jwt_config.inner(),
+                email_backend.inner(),
+                &user,
+                &client,
+            )
+            .await
+        }
+        user => {
+            lockout::record_failure(
+                pool.inner(),
+                lockout_config.inner(),
+                email_backend.inner(),
+                user.as_ref(),
+                ip,
+            )
+            .await
+            .unwrap();
+            Err((Status::Unauthorized, "wrong email or password."))
+        }
     }
 }
 
+#[derive(Serialize)]
+struct RegistrationResp {
+    mode: RegistrationMode,
+}
+
+/// 当前的注册方式，供前端展示
+#[get("/account/registration")]
+fn registration(registration_config: &State<RegistrationConfig>) -> Json<RegistrationResp> {
+    Json(RegistrationResp {
+        mode: registration_config.mode,
+    })
+}
+
+#[get("/.well-known/jwks.json")]
+fn jwks(jwt_config: &State<JWTConfig>) -> Json<JwkSet> {
+    Json(jwt_config.jwks())
+}
+
+#[derive(Debug, FromForm)]
+struct RefreshReq {
+    refresh_token: String,
+}
+
+#[post("/auth/refresh", data = "<data>")]
+async fn refresh(
+    pool: &State<PgPool>,
+    jwt_config: &State<JWTConfig>,
+    data: Form<RefreshReq>,
+) -> Result<Json<LoginResp>, (Status, &'static str)> {
+    let token = RefreshToken::find(pool.inner(), &data.refresh_token)
+        .await
+        .unwrap()
+        .ok_or((Status::Unauthorized, "Invalid refresh token"))?;
+    // 已使用的 token 再次出现，说明 token 已泄露，吊销整个 family
+    if token.is_spent() || !token.consume(pool.inner()).await.unwrap() {
+        RefreshToken::revoke_family(pool.inner(), &token.family)
+            .await
+            .unwrap();
+        return Err((Status::Unauthorized, "Refresh token reused"));
+    }
+    if token.is_expired() {
+        return Err((Status::Unauthorized, "Refresh token expired"));
+    }
+    let status: Option<AccountStatus> =
+        sqlx::query_scalar(r#"SELECT status FROM "user" WHERE id=$1"#)
+            .bind(token.user_id)
+            .fetch_optional(pool.inner())
+            .await
+            .unwrap();
+    if status != Some(AccountStatus::Active) {
+        RefreshToken::revoke_family(pool.inner(), &token.family)
+            .await
+            .unwrap();
+        return Err((Status::Unauthorized, "your account is inactive"));
+    }
+    Ok(Json(
+        issue_tokens(
+            pool.inner(),
+            jwt_config.inner(),
+            token.user_id,
+            token.family,
+        )
+        .await,
+    ))
+}
+
+#[post("/auth/logout", data = "<data>")]
+async fn logout(
+    pool: &State<PgPool>,
+    claims: UserClaims,
+    data: Option<Form<RefreshReq>>,
+) -> (Status, &'static str) {
+    let UserClaims(claims) = claims;
+    RevokedToken::revoke(pool.inner(), &claims.jti, claims.sub, claims.expire())
+        .await
+        .unwrap();
+    if let Some(session_id) = &claims.sid
+        && let Some(session) = UserSession::find(pool.inner(), claims.sub, session_id)
+            .await
+            .unwrap()
+    {
+        session.revoke(pool.inner()).await.unwrap();
+    }
+    // 同时提供 refresh token 时一并吊销
+    if let Some(data) = data
+        && let Some(token) = RefreshToken::find(pool.inner(), &data.refresh_token)
+            .await
+            .unwrap()
+        && token.user_id == claims.sub
+    {
+        RefreshToken::revoke_family(pool.inner(), &token.family)
+            .await
+            .unwrap();
+    }
+    (Status::Ok, "Success")
+}
+
+#[post("/auth/logout-all")]
+async fn logout_all(
+    pool: &State<PgPool>,
+    claims: NoImpersonation<UserClaims>,
+) -> (Status, &'static str) {
+    UserModel::invalidate_tokens(pool.inner(), claims.0 .0.sub)
+        .await
+        .unwrap();
+    (Status::Ok, "Success")
+}
+
+#[derive(Debug, FromForm)]
+struct ForgotPasswordReq {
+    #[field(validate = is_email())]
+    email: String,
+}
+
+#[post("/account/password/forgot", data = "<form>")]
+async fn forgot_password(
+    form: ValidatedFormResult<ForgotPasswordReq>,
+    email_backend: &State<EmailBackend>,
+    pool: &State<PgPool>,
+) -> Result<(Status, &'static str), ValidateError> {
+    let ValidatedForm(data) = form?;
+    // 在后台查询与发送，无论邮箱是否注册都以相同的耗时返回相同的结果；
+    // 发送过于频繁时静默丢弃
+    let email_backend = email_backend.inner().clone();
+    let pool = pool.inner().clone();
+    rocket::tokio::spawn(async move {
+        let user_id: Option<i32> = sqlx::query_scalar(r#"SELECT id FROM "user" WHERE email=$1"#)
+            .bind(&data.email)
+            .fetch_optional(&pool)
+            .await
+            .unwrap();
+        let Some(user_id) = user_id else {
+            return;
+        };
+        let throttled = PasswordResetToken::of_user(&pool, user_id)
+            .await
+            .unwrap()
+            .is_some_and(|last| {
+                Utc::now() - last.created_at() < chrono::Duration::seconds(EMAIL_RESEND_INTERVAL)
+            });
+        if !throttled {
+            send_password_reset_email(&email_backend, &pool, user_id, &data.email).await;
+        }
+    });
+    Ok((
+        Status::Ok,
+        "If the email is registered, a password reset link has been sent",
+    ))
+}
+
+#[derive(Debug, FromForm)]
+struct ResetPasswordReq {
+    token: Uuid,
+    #[field(validate =
+        len(8..32).or_else(msg!("Password length must be between 8 and 32 characters"))
+    )]
+    #[field(validate = validate_password_level())]
+    password: String,
+}
+
+#[post("/account/password/reset", data = "<form>")]
+async fn reset_password(
+    form: ValidatedFormResult<ResetPasswordReq>,
+    pool: &State<PgPool>,
+    password_config: &State<PasswordConfig>,
+) -> Result<(Status, &'static str), ValidateError> {
+    let ValidatedForm(data) = form?;
+    let token = PasswordResetToken::consume(pool.inner(), &data.token)
+        .await
+        .unwrap()
+        .ok_or_else(|| {
+            BadRequest(Json(HashMap::from([(
+                "token".to_string(),
+                Vec::from(["Invalid or expired token".to_string()]),
+            )])))
+        })?;
+    sqlx::query(r#"UPDATE "user" SET password=$1 WHERE id=$2"#)
+        .bind(password_config.hash(&data.password).unwrap())
+        .bind(token.user_id)
+        .execute(pool.inner())
+        .await
+        .unwrap();
+    // 重置密码后旧的登录全部失效
+    UserModel::invalidate_tokens(pool.inner(), token.user_id)
+        .await
+        .unwrap();
+    Ok((Status::Ok, "Success"))
+}
+
+#[derive(Debug, FromForm)]
+struct ChangePasswordReq {
+    current_password: String,
+    #[field(validate =
+        len(8..32).or_else(msg!("Password length must be between 8 and 32 characters"))
+    )]
+    #[field(validate = validate_password_level())]
+    password: String,
+}
+
+#[put("/account/password", data = "<form>")]
+async fn change_password(
+    form: ValidatedFormResult<ChangePasswordReq>,
+    pool: &State<PgPool>,
+    jwt_config: &State<JWTConfig>,
+    password_config: &State<PasswordConfig>,
+    email_backend: &State<EmailBackend>,
+    client: ClientInfo,
+    user: NoImpersonation<User>,
+) -> Result<Json<LoginResp>, ValidateError> {
+    let ValidatedForm(data) = form?;
+    let NoImpersonation(User(mut user)) = user;
+    if !user
+        .verify_password(password_config.inner(), &data.current_password)
+        .unwrap_or(false)
+    {
+        return Err(BadRequest(Json(HashMap::from([(
+            "current_password".to_string(),
+            Vec::from(["Wrong password".to_string()]),
+        )]))));
+    }
+    user.set_password(password_config.inner(), &data.password)
+        .unwrap();
+    sqlx::query(r#"UPDATE "user" SET password=$1 WHERE id=$2"#)
+        .bind(&user.password)
+        .bind(user.id)
+        .execute(pool.inner())
+        .await
+        .unwrap();
+    // 其他登录全部失效，为当前客户端开始新的会话
+    UserModel::invalidate_tokens(pool.inner(), user.id)
+        .await
+        .unwrap();
+    Ok(Json(
+        start_session(
+            pool.inner(),
+            jwt_config.inner(),
+            email_backend.inner(),
+            user.id,
+            &user.email,
+            &client,
+        )
+        .await,
+    ))
+}
+
+#[derive(Debug, FromForm)]
+struct ChangeEmailReq {
+    #[field(validate = is_email())]
+    email: String,
+    password: String,
+}
+
+#[post("/account/email", data = "<form>")]
+async fn change_email(
+    form: ValidatedFormResult<ChangeEmailReq>,
+    email_backend: &State<EmailBackend>,
+    pool: &State<PgPool>,
+    password_config: &State<PasswordConfig>,
+    user: NoImpersonation<User>,
+) -> Result<(Status, &'static str), ValidateError> {
+    let ValidatedForm(data) = form?;
+    let NoImpersonation(User(user)) = user;
+    if !user
+        .verify_password(password_config.inner(), &data.password)
+        .unwrap_or(false)
+    {
+        return Err(BadRequest(Json(HashMap::from([(
+            "password".to_string(),
+            Vec::from(["Wrong password".to_string()]),
+        )]))));
+    }
+    let exists: bool = sqlx::query_scalar(r#"SELECT EXISTS(SELECT 1 FROM "user" WHERE email=$1)"#)
+        .bind(&data.email)
+        .fetch_one(pool.inner())
+        .await
+        .unwrap();
+    if exists {
+        return Err(BadRequest(Json(HashMap::from([(
+            "email".to_string(),
+            Vec::from(["Email existed".to_string()]),
+        )]))));
+    }
+    let change_token = EmailChangeToken::new(user.id, data.email);
+    change_token.save(pool.inner()).await.unwrap();
+    let mut context = tera::Context::new();
+    context.insert(
+        "confirm_url",
+        std::env::var("APP_EMAIL_CHANGE_URL_FORMAT")
+            .unwrap_or("http://localhost:8000/account/email/confirm/<token>".to_string())
+            .replace("<token>", &change_token.token.to_string())
+            .as_str(),
+    );
+    context.insert("new_email", &change_token.new_email);
+    if let Err(e) = email_backend
+        .send_template(
+            change_token.new_email.parse().unwrap(),
+            "Confirm your new email",
+            "email_change.html",
+            &context,
+        )
+        .await
+    {
+        eprintln!("{}", e);
+    }
+    Ok((Status::Ok, "Confirmation email sent"))
+}
+
+#[post("/account/email/confirm/<token>")]
+async fn confirm_email_change(pool: &State<PgPool>, token: Uuid) -> (Status, &'static str) {
+    let Some(token) = EmailChangeToken::consume(pool.inner(), &token)
+        .await
+        .unwrap()
+    else {
+        return (Status::Unauthorized, "Invalid token");
+    };
+    let result = sqlx::query(
+        r#"UPDATE "user" SET email=$1, email_verified_at=CURRENT_TIMESTAMP WHERE id=$2"#,
+    )
+    .bind(&token.new_email)
+    .bind(token.user_id)
+    .execute(pool.inner())
+    .await;
+    match result {
+        Ok(_) => {
+            UserModel::invalidate_tokens(pool.inner(), token.user_id)
+                .await
+                .unwrap();
+            (Status::Ok, "Success")
+        }
+        // 确认期间该邮箱已被其他账户注册
+        Err(sqlx::Error::Database(e)) if e.is_unique_violation() => {
+            (Status::Conflict, "Email existed")
+        }
+        Err(e) => panic!("{}", e),
+    }
+}
+
+#[derive(Serialize)]
+struct ProfileResp {
+    #[serde(flatten)]
+    user: UserProfile,
+    /// 认领的人员
+    person: Option<PersonProfile>,
+}
+
 #[get("/account/profile", rank = 1)]
 async fn profile(
+    pool: &State<PgPool>,
     image_services: &State<ImageServices>,
     s3_client: &State<S3Client>,
     user: User,
-) -> Json<UserProfile> {
+) -> Json<ProfileResp> {
+    let user_id = user.0.id;
+    let person = Person::of_user(pool.inner(), user_id).await.unwrap();
     let mut user_profile = UserProfile::from_user(user.0);
     user_profile
         .sign_avatar(&image_services.avatar, &s3_client.external)
         .await
         .unwrap();
-    Json(user_profile)
+    let person = match person {
+        Some(person) => {
+            let mut person = PersonProfile::from_person(person);
+            // 本人可以看到全部字段，这里只填写 `birth_month_day`
+            person.mask(&Viewer::load(pool.inner(), user_id, false).await.unwrap());
+            person
+                .sign(&image_services.person_photo, &s3_client.external)
+                .await
+                .unwrap();
+            Some(person)
+        }
+        None => None,
+    };
+    Json(ProfileResp {
+        user: user_profile,
+        person,
+    })
 }
 
 #[get("/account/profile", rank = 2)]
@@ -231,7 +917,7 @@ async fn update_profile(
     sqlx::query(r#"UPDATE "user" SET username=$1, avatar_id=$2 WHERE id=$3"#)
         .bind(&data.username)
         .bind(&new_key)
-        .bind(&user.0.id)
+        .bind(user.0.id)
         .execute(pool.inner())
         .await
         .unwrap();
@@ -285,9 +971,9 @@ async fn partial_update_profile(
     }
     let now = Utc::now();
     query.push("updated_at=");
-    query.push_bind(&now);
+    query.push_bind(now);
     query.push(" WHERE id=");
-    query.push_bind(&user.id);
+    query.push_bind(user.id);
     query.build().execute(pool.inner()).await.unwrap();
     let mut profile = UserProfile::from_user(user);
     profile.updated_at = now; // 这里其实是不准确的
@@ -302,8 +988,19 @@ pub fn routes() -> Vec<rocket::Route> {
     routes![
         register,
         login,
+        registration,
+        jwks,
+        refresh,
+        logout,
+        logout_all,
+        forgot_password,
+        reset_password,
+        change_password,
+        change_email,
+        confirm_email_change,
         verification,
         verification_get,
+        resend_verification,
         profile,
         profile_unauthorized,
         update_profile,
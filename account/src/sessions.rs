@@ -0,0 +1,59 @@
+//! 登录会话管理接口
+
+use crate::guards::{NoImpersonation, UserClaims};
+use entity::session::UserSession;
+use rocket::http::Status;
+use rocket::serde::json::Json;
+use rocket::{delete, get, routes, State};
+use serde::Serialize;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+#[derive(Serialize)]
+struct SessionResp {
+    #[serde(flatten)]
+    session: UserSession,
+    /// 是否为当前请求所用的会话
+    current: bool,
+}
+
+#[get("/account/sessions")]
+async fn sessions(
+    pool: &State<PgPool>,
+    claims: NoImpersonation<UserClaims>,
+) -> Json<Vec<SessionResp>> {
+    let NoImpersonation(UserClaims(claims)) = claims;
+    Json(
+        UserSession::active_of_user(pool.inner(), claims.sub)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|session| SessionResp {
+                current: claims.sid == Some(session.id),
+                session,
+            })
+            .collect(),
+    )
+}
+
+#[delete("/account/sessions/<id>")]
+async fn revoke_session(
+    pool: &State<PgPool>,
+    claims: NoImpersonation<UserClaims>,
+    id: Uuid,
+) -> (Status, &'static str) {
+    match UserSession::find(pool.inner(), claims.0 .0.sub, &id)
+        .await
+        .unwrap()
+    {
+        Some(session) => {
+            session.revoke(pool.inner()).await.unwrap();
+            (Status::Ok, "Success")
+        }
+        None => (Status::NotFound, "Session not found"),
+    }
+}
+
+pub fn routes() -> Vec<rocket::Route> {
+    routes![sessions, revoke_session]
+}
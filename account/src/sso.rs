@@ -0,0 +1,277 @@
+//! 单点登录接口
+
+use crate::auth::jwt::JWTConfig;
+use crate::auth::lockout;
+use crate::auth::oidc::{OidcConfig, OidcUserInfo};
+use crate::auth::registration::RegistrationConfig;
+use crate::guards::{ClientInfo, NoImpersonation, User};
+use crate::rbac::{role, RoleType};
+use crate::routes::{complete_login, send_verification_email, LoginResult};
+use email::EmailBackend;
+use entity::identity::{OidcAuthState, UserIdentity};
+use entity::role::Role;
+use entity::user::{AccountStatus, AuthUser};
+use rocket::form::Form;
+use rocket::http::Status;
+use rocket::serde::json::Json;
+use rocket::{delete, get, post, routes, FromForm, State};
+use serde::Serialize;
+use sqlx::PgPool;
+
+/// 授权请求有效期（秒）
+const AUTH_STATE_EXPIRATION: i64 = 600;
+
+#[get("/auth/oidc")]
+fn providers(oidc_config: &State<OidcConfig>) -> Json<Vec<String>> {
+    Json(
+        oidc_config
+            .providers
+            .iter()
+            .map(|provider| provider.name.clone())
+            .collect(),
+    )
+}
+
+#[derive(Serialize)]
+struct AuthorizeResp {
+    authorization_url: String,
+}
+
+/// 获取身份提供方的授权地址，由前端跳转
+#[get("/auth/oidc/<provider>")]
+async fn authorize(
+    pool: &State<PgPool>,
+    oidc_config: &State<OidcConfig>,
+    provider: &str,
+) -> Result<Json<AuthorizeResp>, (Status, &'static str)> {
+    let provider = oidc_config
+        .provider(provider)
+        .ok_or((Status::NotFound, "Unknown provider"))?;
+    let request = provider
+        .authorize(&oidc_config.http_client)
+        .await
+        .map_err(|e| {
+            eprintln!("{}", e);
+            (Status::BadGateway, "Identity provider unavailable")
+        })?;
+    OidcAuthState::save(
+        pool.inner(),
+        &request.state,
+        &provider.name,
+        &request.pkce_verifier,
+        &request.nonce,
+        chrono::Duration::seconds(AUTH_STATE_EXPIRATION),
+    )
+    .await
+    .unwrap();
+    Ok(Json(AuthorizeResp {
+        authorization_url: request.url,
+    }))
+}
+
+#[derive(Debug, FromForm)]
+struct CallbackReq {
+    code: String,
+    state: String,
+}
+
+/// 唯一约束冲突时返回 409
+fn conflict(e: sqlx::Error) -> (Status, &'static str) {
+    match e {
+        sqlx::Error::Database(e) if e.is_unique_violation() => {
+            (Status::Conflict, "Account or identity already exists")
+        }
+        e => panic!("{}", e),
+    }
+}
+
+/// 查找外部身份对应的用户，不存在时关联或创建账户
+///
+/// 通过单点登录创建的账户没有密码，创建账户时遵循注册方式的限制。
+/// 仅当信任提供方且邮箱已验证时直接激活，否则创建未激活的账户并发送激活邮件
+async fn find_or_create_user(
+    pool: &PgPool,
+    registration_config: &RegistrationConfig,
+    email_backend: &EmailBackend,
+    provider: &str,
+    trust_email: bool,
+    info: &OidcUserInfo,
+) -> Result<i32, (Status, &'static str)> {
+    if let Some(identity) = UserIdentity::find(pool, provider, &info.subject)
+        .await
+        .unwrap()
+    {
+        return Ok(identity.user_id);
+    }
+    let email = info.email.as_deref().ok_or((
+        Status::BadRequest,
+        "Email not provided by identity provider",
+    ))?;
+    // 并发的回调可能同时创建同一账户或关联同一身份，冲突时整体回滚
+    let email_trusted = trust_email && info.email_verified;
+    let mut tx = pool.begin().await.unwrap();
+    let existing: Option<i32> = sqlx::query_scalar(r#"SELECT id FROM "user" WHERE email=$1"#)
+        .bind(email)
+        .fetch_optional(&mut *tx)
+        .await
+        .unwrap();
+    let user_id = match existing {
+        Some(user_id) if email_trusted => {
+            // 提供方已验证邮箱，视为完成邮箱验证
+            sqlx::query(
+                r#"UPDATE "user" SET status = CASE WHEN status=$3 THEN $1 ELSE status END,
+                   email_verified_at = COALESCE(email_verified_at, CURRENT_TIMESTAMP) WHERE id=$2"#,
+            )
+            .bind(AccountStatus::Active)
+            .bind(user_id)
+            .bind(AccountStatus::Inactive)
+            .execute(&mut *tx)
+            .await
+            .unwrap();
+            user_id
+        }
+        Some(_) => return Err((Status::Conflict, "Email already registered")),
+        None => {
+            registration_config
+                .check_email(email)
+                .map_err(|e| (Status::Forbidden, e))?;
+            let username: String = info
+                .name
+                .as_deref()
+                .unwrap_or_else(|| email.split('@').next().unwrap())
+                .chars()
+                .take(32)
+                .collect();
+            let user_id: i32 = sqlx::query_scalar(
+                r#"INSERT INTO "user" (email, username, password, status, email_verified_at)
+                   VALUES ($1, $2, NULL, $3, CASE WHEN $4 THEN CURRENT_TIMESTAMP END) RETURNING id"#,
+            )
+            .bind(email)
+            .bind(username)
+            .bind(if email_trusted {
+                AccountStatus::Active
+            } else {
+                AccountStatus::Inactive
+            })
+            .bind(email_trusted)
+            .fetch_one(&mut *tx)
+            .await
+            .map_err(conflict)?;
+            Role::grant(&mut *tx, user_id, role::Member::NAME)
+                .await
+                .unwrap();
+            user_id
+        }
+    };
+    UserIdentity::link(&mut *tx, user_id, provider, &info.subject, Some(email))
+        .await
+        .map_err(conflict)?;
+    tx.commit().await.unwrap();
+    if existing.is_none() && !email_trusted {
+        send_verification_email(email_backend, pool, user_id, email).await;
+    }
+    Ok(user_id)
+}
+
+/// 前端收到身份提供方的回调后，携带 `code` 与 `state` 请求该接口完成登录
+#[post("/auth/oidc/<provider>/callback", data = "<data>")]
+#[allow(clippy::too_many_arguments)]
+async fn callback(
+    pool: &State<PgPool>,
+    jwt_config: &State<JWTConfig>,
+    oidc_config: &State<OidcConfig>,
+    registration_config: &State<RegistrationConfig>,
+    email_backend: &State<EmailBackend>,
+    client: ClientInfo,
+    provider: &str,
+    data: Form<CallbackReq>,
+) -> Result<Json<LoginResult>, (Status, &'static str)> {
+    let provider = oidc_config
+        .provider(provider)
+        .ok_or((Status::NotFound, "Unknown provider"))?;
+    let state = OidcAuthState::take(pool.inner(), &data.state, &provider.name)
+        .await
+        .unwrap()
+        .ok_or((Status::BadRequest, "Invalid state"))?;
+    let info = provider
+        .exchange(
+            &oidc_config.http_client,
+            &data.code,
+            &state.pkce_verifier,
+            &state.nonce,
+        )
+        .await
+        .map_err(|e| {
+            eprintln!("{}", e);
+            (Status::Unauthorized, "Single sign-on failed")
+        })?;
+    let user_id = find_or_create_user(
+        pool.inner(),
+        registration_config.inner(),
+        email_backend.inner(),
+        &provider.name,
+        provider.trust_email,
+        &info,
+    )
+    .await?;
+    let user = sqlx::query_as::<_, AuthUser>(
+        r#"SELECT id, email, password, status, failed_login_count, locked_until, totp_enabled FROM "user" WHERE id=$1"#,
+    )
+    .bind(user_id)
+    .fetch_one(pool.inner())
+    .await
+    .unwrap();
+    if !user.totp_enabled {
+        lockout::record_success(pool.inner(), &user, client.ip.as_deref())
+            .await
+            .unwrap();
+    }
+    complete_login(
+        pool.inner(),
+        jwt_config.inner(),
+        email_backend.inner(),
+        &user,
+        &client,
+    )
+    .await
+}
+
+#[get("/account/identities")]
+async fn identities(pool: &State<PgPool>, user: User) -> Json<Vec<UserIdentity>> {
+    Json(
+        UserIdentity::of_user(pool.inner(), user.0.id)
+            .await
+            .unwrap(),
+    )
+}
+
+#[delete("/account/identities/<id>")]
+async fn unlink_identity(
+    pool: &State<PgPool>,
+    user: NoImpersonation<User>,
+    id: i32,
+) -> (Status, &'static str) {
+    let NoImpersonation(User(user)) = user;
+    // 没有密码时至少保留一种登录方式
+    if user.password.is_none()
+        && UserIdentity::of_user(pool.inner(), user.id)
+            .await
+            .unwrap()
+            .len()
+            <= 1
+    {
+        return (Status::BadRequest, "Cannot remove the only login method");
+    }
+    if UserIdentity::delete(pool.inner(), user.id, id)
+        .await
+        .unwrap()
+    {
+        (Status::Ok, "Success")
+    } else {
+        (Status::NotFound, "Identity not found")
+    }
+}
+
+pub fn routes() -> Vec<rocket::Route> {
+    routes![providers, authorize, callback, identities, unlink_identity]
+}
@@ -0,0 +1,106 @@
+//! 账户相关的定时任务
+
+use crate::data::purge_deleted_accounts;
+use image_service::{ImageServices, S3Client};
+use rocket::fairing::AdHoc;
+use rocket::tokio;
+use sqlx::PgPool;
+use std::time::Duration;
+
+/// 清理任务配置
+pub struct CleanupConfig {
+    /// 执行间隔
+    pub interval: Duration,
+    /// 未激活账户的最长保留时间, `None` 表示不清理未激活账户
+    pub unverified_account_max_age: Option<chrono::Duration>,
+}
+
+impl CleanupConfig {
+    /// * `APP_CLEANUP_INTERVAL_SECS` - 执行间隔（秒），默认 3600
+    /// * `APP_UNVERIFIED_ACCOUNT_MAX_AGE_DAYS` - 未激活账户保留天数，不设置则不清理
+    pub fn from_env() -> Self {
+        Self {
+            interval: Duration::from_secs(match std::env::var("APP_CLEANUP_INTERVAL_SECS") {
+                Ok(secs) => secs.parse().unwrap(),
+                _ => 3600,
+            }),
+            unverified_account_max_age: std::env::var("APP_UNVERIFIED_ACCOUNT_MAX_AGE_DAYS")
+                .ok()
+                .map(|days| chrono::Duration::days(days.parse().unwrap())),
+        }
+    }
+}
+
+/// 清理过期 token、过旧的登录记录与长期未激活的账户
+pub async fn cleanup(pool: &PgPool, config: &CleanupConfig) -> Result<(), sqlx::Error> {
+    for query in [
+        r#"DELETE FROM user_verification_token WHERE expire < CURRENT_TIMESTAMP"#,
+        r#"DELETE FROM password_reset_token WHERE expire < CURRENT_TIMESTAMP"#,
+        r#"DELETE FROM email_change_token WHERE expire < CURRENT_TIMESTAMP"#,
+        r#"DELETE FROM account_deletion_token WHERE expire < CURRENT_TIMESTAMP"#,
+        r#"DELETE FROM refresh_token WHERE expire < CURRENT_TIMESTAMP"#,
+        r#"DELETE FROM revoked_token WHERE expire < CURRENT_TIMESTAMP"#,
+        r#"DELETE FROM mfa_challenge WHERE expire < CURRENT_TIMESTAMP"#,
+        r#"DELETE FROM oidc_auth_state WHERE expire < CURRENT_TIMESTAMP"#,
+        r#"DELETE FROM personal_access_token WHERE expire < CURRENT_TIMESTAMP"#,
+        r#"DELETE FROM login_attempt WHERE created_at < CURRENT_TIMESTAMP - INTERVAL '30 days'"#,
+        r#"DELETE FROM user_session WHERE last_seen_at < CURRENT_TIMESTAMP - INTERVAL '90 days'"#,
+    ] {
+        sqlx::query(query).execute(pool).await?;
+    }
+    if let Some(max_age) = config.unverified_account_max_age {
+        let before = chrono::Utc::now() - max_age;
+        let mut tx = pool.begin().await?;
+        sqlx::query(
+            r#"DELETE FROM user_verification_token USING "user"
+               WHERE user_verification_token.user_id = "user".id AND "user".status = 0 AND "user".created_at < $1"#,
+        )
+        .bind(before)
+        .execute(&mut *tx)
+        .await?;
+        sqlx::query(r#"DELETE FROM "user" WHERE status = 0 AND created_at < $1"#)
+            .bind(before)
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
+    }
+    Ok(())
+}
+
+/// 定时执行 [`cleanup`] 与 [`purge_deleted_accounts`]
+///
+/// Examples:
+/// ```ignore
+/// rocket::build()
+///     .manage(db)
+///     .manage(s3_client)
+///     .manage(image_services)
+///     .attach(account::tasks::fairing())
+/// ```
+pub fn fairing() -> AdHoc {
+    AdHoc::on_liftoff("Account cleanup", |rocket| {
+        Box::pin(async move {
+            let pool = rocket.state::<PgPool>().unwrap().clone();
+            let image_services = rocket.state::<ImageServices>().unwrap().clone();
+            let s3_client = rocket.state::<S3Client>().unwrap().clone();
+            let config = CleanupConfig::from_env();
+            let mut shutdown = rocket.shutdown();
+            tokio::spawn(async move {
+                let mut interval = tokio::time::interval(config.interval);
+                loop {
+                    tokio::select! {
+                        _ = interval.tick() => {
+                            if let Err(e) = cleanup(&pool, &config).await {
+                                eprintln!("{}", e);
+                            }
+                            if let Err(e) = purge_deleted_accounts(&pool, &image_services, &s3_client).await {
+                                eprintln!("{}", e);
+                            }
+                        }
+                        _ = &mut shutdown => break,
+                    }
+                }
+            });
+        })
+    })
+}
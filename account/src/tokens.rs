@@ -0,0 +1,99 @@
+//! 个人访问令牌接口
+//!
+//! 修改密码、退出所有设备、停用账户等使全部 token 失效的操作会删除用户的所有个人访问令牌
+
+use crate::guards::{NoImpersonation, User};
+use chrono::{DateTime, Utc};
+use entity::role::Permission;
+use entity::token::PersonalAccessToken;
+use rocket::form::Form;
+use rocket::http::Status;
+use rocket::serde::json::Json;
+use rocket::{delete, get, post, routes, FromForm, State};
+use serde::Serialize;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+#[get("/account/tokens")]
+async fn list_tokens(
+    pool: &State<PgPool>,
+    user: NoImpersonation<User>,
+) -> Json<Vec<PersonalAccessToken>> {
+    Json(
+        PersonalAccessToken::of_user(pool.inner(), user.0 .0.id)
+            .await
+            .unwrap(),
+    )
+}
+
+#[derive(Debug, FromForm)]
+struct CreateTokenReq {
+    #[field(validate = len(1..64).or_else(msg!("Name length must be between 1 and 64 characters")))]
+    name: String,
+    /// 权限 code, 必须是用户当前拥有的权限
+    scopes: Vec<String>,
+    /// 有效天数 (1 ~ 3650)，不设置则永不过期
+    expires_in_days: Option<i64>,
+}
+
+#[derive(Serialize)]
+struct CreateTokenResp {
+    #[serde(flatten)]
+    info: PersonalAccessToken,
+    /// token 原文，仅在创建时返回一次
+    token: String,
+}
+
+#[post("/account/tokens", data = "<data>")]
+async fn create_token(
+    pool: &State<PgPool>,
+    user: NoImpersonation<User>,
+    data: Form<CreateTokenReq>,
+) -> Result<Json<CreateTokenResp>, (Status, &'static str)> {
+    let NoImpersonation(User(user)) = user;
+    if data
+        .expires_in_days
+        .is_some_and(|days| !(1..=3650).contains(&days))
+    {
+        return Err((Status::BadRequest, "Invalid expiration"));
+    }
+    let permissions = Permission::of_user(pool.inner(), user.id).await.unwrap();
+    if !data
+        .scopes
+        .iter()
+        .all(|scope| permissions.iter().any(|p| &p.code == scope))
+    {
+        return Err((Status::BadRequest, "Invalid scope"));
+    }
+    let mut scopes = data.scopes.clone();
+    scopes.sort();
+    scopes.dedup();
+    let expire: Option<DateTime<Utc>> = data
+        .expires_in_days
+        .map(|days| Utc::now() + chrono::Duration::days(days));
+    let (info, token) =
+        PersonalAccessToken::create(pool.inner(), user.id, &data.name, &scopes, expire)
+            .await
+            .unwrap();
+    Ok(Json(CreateTokenResp { info, token }))
+}
+
+#[delete("/account/tokens/<id>")]
+async fn delete_token(
+    pool: &State<PgPool>,
+    user: NoImpersonation<User>,
+    id: Uuid,
+) -> (Status, &'static str) {
+    if PersonalAccessToken::delete(pool.inner(), user.0 .0.id, &id)
+        .await
+        .unwrap()
+    {
+        (Status::Ok, "Success")
+    } else {
+        (Status::NotFound, "Token not found")
+    }
+}
+
+pub fn routes() -> Vec<rocket::Route> {
+    routes![list_tokens, create_token, delete_token]
+}
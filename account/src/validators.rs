@@ -2,6 +2,7 @@ use rocket::form;
 use zxcvbn::zxcvbn;
 
 /// 密码等级校验
+#[allow(clippy::ptr_arg)]
 pub fn validate_password_level<'v>(password: &String) -> form::Result<'v, ()> {
     let result = zxcvbn(password, &[]);
     if result.score() <= zxcvbn::Score::Two {
@@ -1,10 +1,12 @@
+use lettre::message::header::ContentType;
 use lettre::message::Mailbox;
 use lettre::transport::smtp::authentication::Credentials;
 use lettre::transport::smtp::AsyncSmtpTransport;
-use lettre::Tokio1Executor;
+use lettre::{AsyncTransport, Message, Tokio1Executor};
 use std::ops::Deref;
 use tera::Tera;
 
+#[derive(Clone)]
 pub struct EmailBackend {
     pub transport: AsyncSmtpTransportTokio,
     pub host: String,
@@ -26,6 +28,27 @@ impl EmailBackend {
             from: from.parse().unwrap(),
         }
     }
+
+    /// 渲染模板并发送 html 邮件
+    ///
+    /// * `template` - `templates/email` 下的模板名称
+    pub async fn send_template(
+        &self,
+        to: Mailbox,
+        subject: &str,
+        template: &str,
+        context: &tera::Context,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let body = templates().render(template, context)?;
+        let email = Message::builder()
+            .from(self.from.clone())
+            .to(to)
+            .subject(subject)
+            .header(ContentType::TEXT_HTML)
+            .body(body)?;
+        self.send(email).await?;
+        Ok(())
+    }
 }
 
 impl Deref for EmailBackend {
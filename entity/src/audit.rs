@@ -0,0 +1,71 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use sqlx::types::JsonValue;
+use sqlx::{FromRow, PgPool};
+
+/// 管理操作审计日志
+#[derive(Clone, Debug, PartialEq, FromRow, Serialize)]
+pub struct AuditLog {
+    pub id: i32,
+    /// 操作者
+    pub admin_id: Option<i32>,
+    /// VARCHAR(64), 如 `user.status`
+    pub action: String,
+    /// 被操作的用户
+    pub target_user_id: Option<i32>,
+    pub detail: JsonValue,
+    /// VARCHAR(45)
+    pub ip: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl AuditLog {
+    /// 记录一次管理操作
+    pub async fn record(
+        pool: &PgPool,
+        admin_id: i32,
+        action: &str,
+        target_user_id: Option<i32>,
+        detail: JsonValue,
+        ip: Option<&str>,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            r#"INSERT INTO admin_audit_log (admin_id, action, target_user_id, detail, ip) VALUES ($1, $2, $3, $4, $5)"#,
+        )
+        .bind(admin_id)
+        .bind(action)
+        .bind(target_user_id)
+        .bind(detail)
+        .bind(ip)
+        .execute(pool)
+        .await?;
+        Ok(())
+    }
+
+    /// 按时间倒序分页查询
+    ///
+    /// * `target_user_id` - 为 `Some` 时仅查询该用户相关的记录
+    pub async fn list(
+        pool: &PgPool,
+        target_user_id: Option<i32>,
+        limit: i64,
+        offset: i64,
+    ) -> Result<(Vec<Self>, i64), sqlx::Error> {
+        let items = sqlx::query_as::<_, Self>(
+            r#"SELECT * FROM admin_audit_log WHERE $1::INTEGER IS NULL OR target_user_id = $1
+               ORDER BY created_at DESC, id DESC LIMIT $2 OFFSET $3"#,
+        )
+        .bind(target_user_id)
+        .bind(limit)
+        .bind(offset)
+        .fetch_all(pool)
+        .await?;
+        let total = sqlx::query_scalar(
+            r#"SELECT COUNT(*) FROM admin_audit_log WHERE $1::INTEGER IS NULL OR target_user_id = $1"#,
+        )
+        .bind(target_user_id)
+        .fetch_one(pool)
+        .await?;
+        Ok((items, total))
+    }
+}
@@ -0,0 +1,194 @@
+//! 人员的教育和工作经历
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::Serialize;
+use sqlx::{FromRow, PgPool};
+
+/// 教育经历
+#[derive(Clone, Debug, PartialEq, Eq, FromRow, Serialize)]
+pub struct Education {
+    pub id: i32,
+    pub person_id: i32,
+    /// VARCHAR(128), 学校 / 机构
+    pub institution: String,
+    /// VARCHAR(64), 学位
+    pub degree: Option<String>,
+    /// VARCHAR(128), 专业
+    pub major: Option<String>,
+    pub start_date: Option<NaiveDate>,
+    /// 为空表示在读
+    pub end_date: Option<NaiveDate>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// 新建或修改教育经历
+#[derive(Clone, Debug)]
+pub struct EducationData<'a> {
+    pub institution: &'a str,
+    pub degree: Option<&'a str>,
+    pub major: Option<&'a str>,
+    pub start_date: Option<NaiveDate>,
+    pub end_date: Option<NaiveDate>,
+}
+
+/// 工作经历
+#[derive(Clone, Debug, PartialEq, Eq, FromRow, Serialize)]
+pub struct Employment {
+    pub id: i32,
+    pub person_id: i32,
+    /// VARCHAR(128), 单位
+    pub organization: String,
+    /// VARCHAR(128), 职位
+    pub title: Option<String>,
+    /// VARCHAR(64)
+    pub city: Option<String>,
+    pub start_date: Option<NaiveDate>,
+    /// 为空表示在职
+    pub end_date: Option<NaiveDate>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// 新建或修改工作经历
+#[derive(Clone, Debug)]
+pub struct EmploymentData<'a> {
+    pub organization: &'a str,
+    pub title: Option<&'a str>,
+    pub city: Option<&'a str>,
+    pub start_date: Option<NaiveDate>,
+    pub end_date: Option<NaiveDate>,
+}
+
+impl Education {
+    /// 人员的全部教育经历，在读的在前，其余按开始时间倒序
+    pub async fn of_person(pool: &PgPool, person_id: i32) -> Result<Vec<Self>, sqlx::Error> {
+        sqlx::query_as::<_, Self>(
+            r#"SELECT * FROM person_education WHERE person_id = $1
+               ORDER BY end_date DESC NULLS FIRST, start_date DESC NULLS LAST, id DESC"#,
+        )
+        .bind(person_id)
+        .fetch_all(pool)
+        .await
+    }
+
+    pub async fn create(
+        pool: &PgPool,
+        person_id: i32,
+        data: &EducationData<'_>,
+    ) -> Result<Self, sqlx::Error> {
+        sqlx::query_as::<_, Self>(
+            r#"INSERT INTO person_education (person_id, institution, degree, major, start_date, end_date)
+               VALUES ($1, $2, $3, $4, $5, $6) RETURNING *"#,
+        )
+        .bind(person_id)
+        .bind(data.institution)
+        .bind(data.degree)
+        .bind(data.major)
+        .bind(data.start_date)
+        .bind(data.end_date)
+        .fetch_one(pool)
+        .await
+    }
+
+    /// 修改人员的一条教育经历，不属于该人员时返回 `None`
+    pub async fn update(
+        pool: &PgPool,
+        id: i32,
+        person_id: i32,
+        data: &EducationData<'_>,
+    ) -> Result<Option<Self>, sqlx::Error> {
+        sqlx::query_as::<_, Self>(
+            r#"UPDATE person_education
+               SET institution = $1, degree = $2, major = $3, start_date = $4, end_date = $5
+               WHERE id = $6 AND person_id = $7 RETURNING *"#,
+        )
+        .bind(data.institution)
+        .bind(data.degree)
+        .bind(data.major)
+        .bind(data.start_date)
+        .bind(data.end_date)
+        .bind(id)
+        .bind(person_id)
+        .fetch_optional(pool)
+        .await
+    }
+
+    pub async fn delete(pool: &PgPool, id: i32, person_id: i32) -> Result<bool, sqlx::Error> {
+        Ok(
+            sqlx::query(r#"DELETE FROM person_education WHERE id = $1 AND person_id = $2"#)
+                .bind(id)
+                .bind(person_id)
+                .execute(pool)
+                .await?
+                .rows_affected()
+                > 0,
+        )
+    }
+}
+
+impl Employment {
+    /// 人员的全部工作经历，在职的在前，其余按开始时间倒序
+    pub async fn of_person(pool: &PgPool, person_id: i32) -> Result<Vec<Self>, sqlx::Error> {
+        sqlx::query_as::<_, Self>(
+            r#"SELECT * FROM person_employment WHERE person_id = $1
+               ORDER BY end_date DESC NULLS FIRST, start_date DESC NULLS LAST, id DESC"#,
+        )
+        .bind(person_id)
+        .fetch_all(pool)
+        .await
+    }
+
+    pub async fn create(
+        pool: &PgPool,
+        person_id: i32,
+        data: &EmploymentData<'_>,
+    ) -> Result<Self, sqlx::Error> {
+        sqlx::query_as::<_, Self>(
+            r#"INSERT INTO person_employment (person_id, organization, title, city, start_date, end_date)
+               VALUES ($1, $2, $3, $4, $5, $6) RETURNING *"#,
+        )
+        .bind(person_id)
+        .bind(data.organization)
+        .bind(data.title)
+        .bind(data.city)
+        .bind(data.start_date)
+        .bind(data.end_date)
+        .fetch_one(pool)
+        .await
+    }
+
+    /// 修改人员的一条工作经历，不属于该人员时返回 `None`
+    pub async fn update(
+        pool: &PgPool,
+        id: i32,
+        person_id: i32,
+        data: &EmploymentData<'_>,
+    ) -> Result<Option<Self>, sqlx::Error> {
+        sqlx::query_as::<_, Self>(
+            r#"UPDATE person_employment
+               SET organization = $1, title = $2, city = $3, start_date = $4, end_date = $5
+               WHERE id = $6 AND person_id = $7 RETURNING *"#,
+        )
+        .bind(data.organization)
+        .bind(data.title)
+        .bind(data.city)
+        .bind(data.start_date)
+        .bind(data.end_date)
+        .bind(id)
+        .bind(person_id)
+        .fetch_optional(pool)
+        .await
+    }
+
+    pub async fn delete(pool: &PgPool, id: i32, person_id: i32) -> Result<bool, sqlx::Error> {
+        Ok(
+            sqlx::query(r#"DELETE FROM person_employment WHERE id = $1 AND person_id = $2"#)
+                .bind(id)
+                .bind(person_id)
+                .execute(pool)
+                .await?
+                .rows_affected()
+                > 0,
+        )
+    }
+}
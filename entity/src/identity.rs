@@ -0,0 +1,134 @@
+use crate::token::hash_token;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use sqlx::{FromRow, PgExecutor, PgPool};
+
+/// 外部身份 (OIDC)
+#[derive(Clone, Debug, PartialEq, Eq, FromRow, Serialize)]
+pub struct UserIdentity {
+    pub id: i32,
+    pub user_id: i32,
+    /// VARCHAR(32), 身份提供方名称
+    pub provider: String,
+    /// VARCHAR(255), id token 中的 sub
+    pub subject: String,
+    /// VARCHAR(255)
+    pub email: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl UserIdentity {
+    /// 按身份提供方与 sub 查找
+    pub async fn find(
+        pool: &PgPool,
+        provider: &str,
+        subject: &str,
+    ) -> Result<Option<Self>, sqlx::Error> {
+        sqlx::query_as::<_, Self>(
+            r#"SELECT * FROM user_identity WHERE provider = $1 AND subject = $2"#,
+        )
+        .bind(provider)
+        .bind(subject)
+        .fetch_optional(pool)
+        .await
+    }
+
+    /// 关联外部身份与用户
+    pub async fn link<'e>(
+        executor: impl PgExecutor<'e>,
+        user_id: i32,
+        provider: &str,
+        subject: &str,
+        email: Option<&str>,
+    ) -> Result<Self, sqlx::Error> {
+        sqlx::query_as::<_, Self>(
+            r#"INSERT INTO user_identity (user_id, provider, subject, email) VALUES ($1, $2, $3, $4) RETURNING *"#,
+        )
+        .bind(user_id)
+        .bind(provider)
+        .bind(subject)
+        .bind(email)
+        .fetch_one(executor)
+        .await
+    }
+
+    /// 用户关联的全部外部身份
+    pub async fn of_user(pool: &PgPool, user_id: i32) -> Result<Vec<Self>, sqlx::Error> {
+        sqlx::query_as::<_, Self>(
+            r#"SELECT * FROM user_identity WHERE user_id = $1 ORDER BY created_at"#,
+        )
+        .bind(user_id)
+        .fetch_all(pool)
+        .await
+    }
+
+    /// 解除关联
+    ///
+    /// 返回 `false` 表示身份不存在或不属于该用户
+    pub async fn delete(pool: &PgPool, user_id: i32, id: i32) -> Result<bool, sqlx::Error> {
+        let result = sqlx::query(r#"DELETE FROM user_identity WHERE id = $1 AND user_id = $2"#)
+            .bind(id)
+            .bind(user_id)
+            .execute(pool)
+            .await?;
+        Ok(result.rows_affected() > 0)
+    }
+}
+
+/// 进行中的 OIDC 授权请求
+///
+/// 保存 PKCE verifier 与 nonce, 回调时按 state 取回
+#[derive(Clone, Debug, PartialEq, Eq, FromRow)]
+pub struct OidcAuthState {
+    /// VARCHAR(64), sha256(state)
+    pub state_hash: String,
+    /// VARCHAR(32)
+    pub provider: String,
+    /// VARCHAR(128)
+    pub pkce_verifier: String,
+    /// VARCHAR(128)
+    pub nonce: String,
+    pub expire: DateTime<Utc>,
+}
+
+impl OidcAuthState {
+    pub async fn save(
+        pool: &PgPool,
+        state: &str,
+        provider: &str,
+        pkce_verifier: &str,
+        nonce: &str,
+        ttl: chrono::Duration,
+    ) -> Result<(), sqlx::Error> {
+        let expire = Utc::now()
+            .checked_add_signed(ttl)
+            .expect("Invalid timestamp");
+        sqlx::query(
+            r#"INSERT INTO oidc_auth_state (state_hash, provider, pkce_verifier, nonce, expire) VALUES ($1, $2, $3, $4, $5)"#,
+        )
+        .bind(hash_token(state))
+        .bind(provider)
+        .bind(pkce_verifier)
+        .bind(nonce)
+        .bind(expire)
+        .execute(pool)
+        .await?;
+        Ok(())
+    }
+
+    /// 取出并删除未过期的授权请求，每个 state 只能使用一次
+    pub async fn take(
+        pool: &PgPool,
+        state: &str,
+        provider: &str,
+    ) -> Result<Option<Self>, sqlx::Error> {
+        sqlx::query_as::<_, Self>(
+            r#"DELETE FROM oidc_auth_state
+               WHERE state_hash = $1 AND provider = $2 AND expire >= CURRENT_TIMESTAMP RETURNING *"#,
+        )
+        .bind(hash_token(state))
+        .bind(provider)
+        .fetch_optional(pool)
+        .await
+    }
+}
@@ -0,0 +1,97 @@
+use argon2::password_hash::rand_core::{OsRng, RngCore};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use sqlx::{FromRow, PgExecutor, PgPool};
+
+/// 注册邀请码
+#[derive(Clone, Debug, PartialEq, Eq, FromRow, Serialize)]
+pub struct InvitationCode {
+    pub id: i32,
+    /// VARCHAR(32), 唯一
+    pub code: String,
+    /// 邀请注册的届，迁移前创建且未能匹配到届的邀请码为 `None`
+    pub cohort_id: Option<i32>,
+    /// 可使用次数
+    pub max_uses: i32,
+    /// 已使用次数
+    pub use_count: i32,
+    pub expire: DateTime<Utc>,
+    pub created_by: Option<i32>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// 邀请码字符集，去除了易混淆的 0/O/1/I
+const CODE_CHARSET: &[u8] = b"ABCDEFGHJKLMNPQRSTUVWXYZ23456789";
+/// 邀请码长度
+const CODE_LENGTH: usize = 12;
+
+/// 生成随机邀请码
+fn generate_code() -> String {
+    let mut bytes = [0u8; CODE_LENGTH];
+    OsRng.fill_bytes(&mut bytes);
+    bytes
+        .iter()
+        .map(|b| CODE_CHARSET[*b as usize % CODE_CHARSET.len()] as char)
+        .collect()
+}
+
+impl InvitationCode {
+    pub async fn create(
+        pool: &PgPool,
+        cohort_id: i32,
+        max_uses: i32,
+        expire: DateTime<Utc>,
+        created_by: i32,
+    ) -> Result<Self, sqlx::Error> {
+        sqlx::query_as::<_, Self>(
+            r#"INSERT INTO invitation_code (code, cohort_id, max_uses, expire, created_by)
+               VALUES ($1, $2, $3, $4, $5) RETURNING *"#,
+        )
+        .bind(generate_code())
+        .bind(cohort_id)
+        .bind(max_uses)
+        .bind(expire)
+        .bind(created_by)
+        .fetch_one(pool)
+        .await
+    }
+
+    pub async fn find(pool: &PgPool, id: i32) -> Result<Option<Self>, sqlx::Error> {
+        sqlx::query_as::<_, Self>(r#"SELECT * FROM invitation_code WHERE id = $1"#)
+            .bind(id)
+            .fetch_optional(pool)
+            .await
+    }
+
+    /// 全部邀请码，按创建时间倒序
+    pub async fn all(pool: &PgPool) -> Result<Vec<Self>, sqlx::Error> {
+        sqlx::query_as::<_, Self>(r#"SELECT * FROM invitation_code ORDER BY created_at DESC"#)
+            .fetch_all(pool)
+            .await
+    }
+
+    /// 使用一次邀请码
+    ///
+    /// 邀请码不存在、已过期或次数已用完时返回 `None`
+    pub async fn redeem<'e>(
+        executor: impl PgExecutor<'e>,
+        code: &str,
+    ) -> Result<Option<Self>, sqlx::Error> {
+        sqlx::query_as::<_, Self>(
+            r#"UPDATE invitation_code SET use_count = use_count + 1
+               WHERE code = $1 AND use_count < max_uses AND expire >= CURRENT_TIMESTAMP RETURNING *"#,
+        )
+        .bind(code.trim().to_uppercase())
+        .fetch_optional(executor)
+        .await
+    }
+
+    /// 删除邀请码，已注册用户不受影响
+    pub async fn delete(pool: &PgPool, id: i32) -> Result<bool, sqlx::Error> {
+        let result = sqlx::query(r#"DELETE FROM invitation_code WHERE id = $1"#)
+            .bind(id)
+            .execute(pool)
+            .await?;
+        Ok(result.rows_affected() > 0)
+    }
+}
@@ -1,2 +1,11 @@
+pub mod audit;
+pub mod history;
+pub mod identity;
+pub mod invitation;
+pub mod password;
 pub mod person;
+pub mod role;
+pub mod school;
+pub mod session;
+pub mod token;
 pub mod user;
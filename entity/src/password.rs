@@ -0,0 +1,265 @@
+//! 密码加密
+//!
+//! - 新密码使用 argon2id 加密，参数和 pepper 可配置
+//! - 兼容 Django 的 `pbkdf2_sha256` 和 `argon2` hash，用于导入旧系统的用户
+//! - 登录成功时若 hash 使用的算法或参数已过时，应调用 [`PasswordConfig::hash`] 重新加密
+use argon2::password_hash::rand_core::OsRng;
+use argon2::password_hash::SaltString;
+use argon2::{
+    Algorithm, Argon2, KeyId, Params, ParamsBuilder, PasswordHash, PasswordHasher,
+    PasswordVerifier, Version,
+};
+use base64::prelude::*;
+use sha2::{Digest, Sha256};
+use subtle::ConstantTimeEq;
+
+/// Django `PBKDF2PasswordHasher` 的前缀
+const DJANGO_PBKDF2_SHA256: &str = "pbkdf2_sha256$";
+/// Django `Argon2PasswordHasher` 的前缀，去掉后为标准 PHC 格式
+const DJANGO_ARGON2: &str = "argon2";
+
+/// 无法与任何密码匹配的 hash
+///
+/// 用于强制重置密码，与没有密码的单点登录账户 (`NULL`) 区分
+pub const UNUSABLE_PASSWORD: &str = "!";
+
+pub struct PasswordConfig {
+    /// 设置了 pepper 时 `keyid` 为 pepper 的 SHA-256 前 4 字节，与 hash 一起保存
+    params: Params,
+    pepper: Option<Vec<u8>>,
+    /// 轮换前使用过的 pepper，仅用于校验
+    old_peppers: Vec<Vec<u8>>,
+}
+
+/// pepper 对应的 argon2 `keyid`
+fn pepper_key_id(pepper: &[u8]) -> KeyId {
+    KeyId::new(&Sha256::digest(pepper)[..4]).unwrap()
+}
+
+impl PasswordConfig {
+    /// * `APP_ARGON2_MEMORY_KIB` - 默认 19456
+    /// * `APP_ARGON2_ITERATIONS` - 默认 2
+    /// * `APP_ARGON2_PARALLELISM` - 默认 1
+    /// * `APP_PASSWORD_PEPPER` - 可选，设置后新的 hash 都会使用该 pepper
+    /// * `APP_PASSWORD_OLD_PEPPERS` - 可选，以 `,` 分隔。轮换 pepper 时保留旧值，
+    ///   使用旧 pepper 的 hash 仍可校验，并在登录成功时以当前 pepper 重新加密
+    pub fn from_env() -> Self {
+        fn var(key: &str, default: u32) -> u32 {
+            match std::env::var(key) {
+                Ok(value) => value
+                    .parse()
+                    .unwrap_or_else(|_| panic!("{} must be a number", key)),
+                _ => default,
+            }
+        }
+        let params = Params::new(
+            var("APP_ARGON2_MEMORY_KIB", Params::DEFAULT_M_COST),
+            var("APP_ARGON2_ITERATIONS", Params::DEFAULT_T_COST),
+            var("APP_ARGON2_PARALLELISM", Params::DEFAULT_P_COST),
+            None,
+        )
+        .expect("Invalid argon2 parameters");
+        let pepper = std::env::var("APP_PASSWORD_PEPPER")
+            .ok()
+            .filter(|pepper| !pepper.is_empty())
+            .map(String::into_bytes);
+        let old_peppers = std::env::var("APP_PASSWORD_OLD_PEPPERS")
+            .unwrap_or_default()
+            .split(',')
+            .filter(|pepper| !pepper.is_empty())
+            .map(|pepper| pepper.as_bytes().to_vec())
+            .collect();
+        Self::new(params, pepper).with_old_peppers(old_peppers)
+    }
+
+    pub fn new(params: Params, pepper: Option<Vec<u8>>) -> Self {
+        let params = match &pepper {
+            Some(pepper) => ParamsBuilder::new()
+                .m_cost(params.m_cost())
+                .t_cost(params.t_cost())
+                .p_cost(params.p_cost())
+                .keyid(pepper_key_id(pepper))
+                .build()
+                .unwrap(),
+            None => params,
+        };
+        Self {
+            params,
+            pepper,
+            old_peppers: Vec::new(),
+        }
+    }
+
+    /// 设置仅用于校验的旧 pepper
+    pub fn with_old_peppers(mut self, old_peppers: Vec<Vec<u8>>) -> Self {
+        self.old_peppers = old_peppers;
+        self
+    }
+
+    /// `pepper` 为 `None` 时不使用 pepper
+    fn argon2<'a>(&'a self, pepper: Option<&'a [u8]>) -> Result<Argon2<'a>, argon2::Error> {
+        match pepper {
+            Some(pepper) => Argon2::new_with_secret(
+                pepper,
+                Algorithm::Argon2id,
+                Version::V0x13,
+                self.params.clone(),
+            ),
+            None => Ok(Argon2::new(
+                Algorithm::Argon2id,
+                Version::V0x13,
+                self.params.clone(),
+            )),
+        }
+    }
+
+    /// 按 `keyid` 查找当前或旧的 pepper
+    fn find_pepper(&self, key_id: &[u8]) -> Option<&[u8]> {
+        self.pepper
+            .iter()
+            .chain(self.old_peppers.iter())
+            .find(|pepper| pepper_key_id(pepper).as_bytes() == key_id)
+            .map(Vec::as_slice)
+    }
+
+    /// 加密密码原文
+    pub fn hash(&self, password: &str) -> Result<String, argon2::password_hash::Error> {
+        let salt = SaltString::generate(&mut OsRng);
+        Ok(self
+            .argon2(self.pepper.as_deref())?
+            .hash_password(password.as_bytes(), &salt)?
+            .to_string())
+    }
+
+    /// 校验密码原文与 hash 是否匹配
+    ///
+    /// hash 使用的 pepper 既不是当前 pepper 也不在旧 pepper 中时返回错误
+    pub fn verify(&self, hash: &str, password: &str) -> Result<bool, &'static str> {
+        if let Some(hash) = hash.strip_prefix(DJANGO_PBKDF2_SHA256) {
+            return verify_django_pbkdf2(hash, password);
+        }
+        let hash = hash.strip_prefix(DJANGO_ARGON2).unwrap_or(hash);
+        let parsed_hash = PasswordHash::new(hash).map_err(|_| "Invalid password hash")?;
+        let params = Params::try_from(&parsed_hash).map_err(|_| "Invalid password hash")?;
+        let key_id = params.keyid();
+        let pepper = if key_id.is_empty() {
+            None
+        } else {
+            Some(self.find_pepper(key_id).ok_or("Unknown password pepper")?)
+        };
+        Ok(self
+            .argon2(pepper)
+            .map_err(|_| "Invalid password pepper")?
+            .verify_password(password.as_bytes(), &parsed_hash)
+            .is_ok())
+    }
+
+    /// hash 的算法、参数或 pepper 是否与当前配置不同
+    pub fn needs_rehash(&self, hash: &str) -> bool {
+        let Ok(parsed_hash) = PasswordHash::new(hash) else {
+            return true;
+        };
+        let Ok(params) = Params::try_from(&parsed_hash) else {
+            return true;
+        };
+        parsed_hash.algorithm != Algorithm::Argon2id.ident()
+            || parsed_hash.version != Some(Version::V0x13.into())
+            || params.m_cost() != self.params.m_cost()
+            || params.t_cost() != self.params.t_cost()
+            || params.p_cost() != self.params.p_cost()
+            || parsed_hash.hash.map(|output| output.len()) != Some(Params::DEFAULT_OUTPUT_LEN)
+            || params.keyid() != self.params.keyid()
+    }
+}
+
+/// 校验 Django 的 `pbkdf2_sha256$<iterations>$<salt>$<hash>`
+fn verify_django_pbkdf2(hash: &str, password: &str) -> Result<bool, &'static str> {
+    let mut parts = hash.splitn(3, '$');
+    let (Some(iterations), Some(salt), Some(expected)) = (parts.next(), parts.next(), parts.next())
+    else {
+        return Err("Invalid password hash");
+    };
+    let iterations: u32 = iterations.parse().map_err(|_| "Invalid password hash")?;
+    let expected = BASE64_STANDARD
+        .decode(expected)
+        .map_err(|_| "Invalid password hash")?;
+    if iterations == 0 || expected.is_empty() {
+        return Err("Invalid password hash");
+    }
+    let mut output = vec![0u8; expected.len()];
+    pbkdf2::pbkdf2_hmac::<Sha256>(
+        password.as_bytes(),
+        salt.as_bytes(),
+        iterations,
+        &mut output,
+    );
+    Ok(output.ct_eq(&expected).into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 测试用的低成本参数
+    fn config(pepper: Option<&str>) -> PasswordConfig {
+        PasswordConfig::new(
+            Params::new(1024, 1, 1, None).unwrap(),
+            pepper.map(|pepper| pepper.as_bytes().to_vec()),
+        )
+    }
+
+    #[test]
+    fn verify_django_pbkdf2_sha256() {
+        // hashlib.pbkdf2_hmac("sha256", b"blossom", b"c2FsdHNhbHQ", 1000)
+        let hash = "pbkdf2_sha256$1000$c2FsdHNhbHQ$8XzcD5ecUui27acwdSj1c+D9KePL8S+nkjGl9aXlX9c=";
+        let config = config(None);
+        assert_eq!(config.verify(hash, "blossom"), Ok(true));
+        assert_eq!(config.verify(hash, "Blossom"), Ok(false));
+        assert!(config.needs_rehash(hash));
+    }
+
+    #[test]
+    fn verify_django_pbkdf2_rejects_malformed_hash() {
+        let config = config(None);
+        assert!(config.verify("pbkdf2_sha256$1000$salt", "blossom").is_err());
+        assert!(config
+            .verify("pbkdf2_sha256$0$salt$AAAA", "blossom")
+            .is_err());
+        assert!(config
+            .verify("pbkdf2_sha256$x$salt$AAAA", "blossom")
+            .is_err());
+    }
+
+    #[test]
+    fn verify_argon2_with_pepper() {
+        let config = config(Some("pepper"));
+        let hash = config.hash("blossom").unwrap();
+        assert_eq!(config.verify(&hash, "blossom"), Ok(true));
+        assert_eq!(config.verify(&hash, "other"), Ok(false));
+        assert!(!config.needs_rehash(&hash));
+    }
+
+    #[test]
+    fn verify_old_pepper_after_rotation() {
+        let hash = config(Some("old")).hash("blossom").unwrap();
+
+        let rotated = config(Some("new"));
+        assert_eq!(
+            rotated.verify(&hash, "blossom"),
+            Err("Unknown password pepper")
+        );
+
+        let rotated = rotated.with_old_peppers(vec![b"old".to_vec()]);
+        assert_eq!(rotated.verify(&hash, "blossom"), Ok(true));
+        assert_eq!(rotated.verify(&hash, "other"), Ok(false));
+        assert!(rotated.needs_rehash(&hash));
+        let rehashed = rotated.hash("blossom").unwrap();
+        assert_eq!(rotated.verify(&rehashed, "blossom"), Ok(true));
+        assert!(!rotated.needs_rehash(&rehashed));
+    }
+
+    #[test]
+    fn unusable_password_never_matches() {
+        assert_ne!(config(None).verify(UNUSABLE_PASSWORD, "!"), Ok(true));
+    }
+}
@@ -2,9 +2,11 @@ use aws_sdk_s3::Client;
 use chrono::{DateTime, NaiveDate, Utc};
 use image_service::errors::ImageError;
 use image_service::service::ImageService;
+use pinyin::ToPinyin;
 use rocket::form::{FromFormField, ValueField};
 use serde::{Deserialize, Serialize};
-use sqlx::FromRow;
+use sqlx::{FromRow, PgPool};
+use std::collections::HashSet;
 
 #[derive(Clone, Debug, PartialEq, Eq, sqlx::Type, Serialize, Deserialize)]
 #[sqlx(type_name = "gender")]
@@ -44,10 +46,106 @@ pub struct Person {
     pub qq: Option<String>,
     pub wechat: Option<String>,
 
+    /// 认领该人员的账户
+    pub user_id: Option<i32>,
+
+    #[sqlx(flatten)]
+    #[serde(flatten)]
+    pub visibility: PersonVisibility,
+
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
 }
 
+/// 生成姓名的全拼和首字母，用于搜索
+///
+/// 首字母同时包含只取第一个字母和取完整声母 (zh/ch/sh) 两种写法，以空格分隔
+///
+/// Examples:
+/// ```
+/// # use entity::person::name_pinyin;
+/// assert_eq!(name_pinyin("张三"), ("zhangsan".to_string(), "zs zhs".to_string()));
+/// assert_eq!(name_pinyin("李四"), ("lisi".to_string(), "ls".to_string()));
+/// ```
+pub fn name_pinyin(name: &str) -> (String, String) {
+    let mut full = String::new();
+    let mut initials = String::new();
+    let mut shengmu = String::new();
+    let mut in_word = false;
+    for c in name.chars() {
+        if let Some(pinyin) = c.to_pinyin() {
+            let plain = pinyin.plain();
+            full.push_str(plain);
+            initials.push_str(pinyin.first_letter());
+            shengmu.push_str(
+                ["zh", "ch", "sh"]
+                    .into_iter()
+                    .find(|prefix| plain.starts_with(prefix))
+                    .unwrap_or(pinyin.first_letter()),
+            );
+            in_word = false;
+        } else if c.is_ascii_alphanumeric() {
+            let c = c.to_ascii_lowercase();
+            full.push(c);
+            if !in_word {
+                initials.push(c);
+                shengmu.push(c);
+            }
+            in_word = true;
+        } else {
+            in_word = false;
+        }
+    }
+    if shengmu != initials {
+        initials = format!("{} {}", initials, shengmu);
+    }
+    (full, initials)
+}
+
+impl Person {
+    pub async fn find(pool: &PgPool, id: i32) -> Result<Option<Self>, sqlx::Error> {
+        sqlx::query_as::<_, Self>(r#"SELECT * FROM person WHERE id = $1"#)
+            .bind(id)
+            .fetch_optional(pool)
+            .await
+    }
+
+    /// 账户认领的人员
+    pub async fn of_user(pool: &PgPool, user_id: i32) -> Result<Option<Self>, sqlx::Error> {
+        sqlx::query_as::<_, Self>(r#"SELECT * FROM person WHERE user_id = $1"#)
+            .bind(user_id)
+            .fetch_optional(pool)
+            .await
+    }
+
+    /// 为拼音为空的记录生成拼音
+    pub async fn backfill_pinyin(pool: &PgPool) -> Result<u64, sqlx::Error> {
+        let rows: Vec<(i32, String)> = sqlx::query_as(
+            r#"SELECT id, name FROM person WHERE name_pinyin = '' AND COALESCE(name, '') <> ''"#,
+        )
+        .fetch_all(pool)
+        .await?;
+        for (id, name) in &rows {
+            let (full, initials) = name_pinyin(name);
+            sqlx::query(r#"UPDATE person SET name_pinyin = $1, name_initials = $2 WHERE id = $3"#)
+                .bind(full)
+                .bind(initials)
+                .bind(id)
+                .execute(pool)
+                .await?;
+        }
+        Ok(rows.len() as u64)
+    }
+
+    /// 删除并返回被删除的记录，用于清理照片
+    pub async fn delete(pool: &PgPool, id: i32) -> Result<Option<Self>, sqlx::Error> {
+        sqlx::query_as::<_, Self>(r#"DELETE FROM person WHERE id = $1 RETURNING *"#)
+            .bind(id)
+            .fetch_optional(pool)
+            .await
+    }
+}
+
 #[derive(Clone, Debug, PartialEq, Eq, FromRow, Serialize, Deserialize)]
 pub struct PersonProfile {
     pub id: i32,
@@ -68,6 +166,16 @@ pub struct PersonProfile {
     pub qq: Option<String>,
     pub wechat: Option<String>,
 
+    /// 认领该人员的账户
+    pub user_id: Option<i32>,
+
+    #[sqlx(flatten)]
+    #[serde(flatten)]
+    pub visibility: PersonVisibility,
+    /// 生日的月日 MM-DD, 生日可见 (包括只显示月日) 时由 [`PersonProfile::mask`] 填写
+    #[sqlx(skip)]
+    pub birth_month_day: Option<String>,
+
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
 }
@@ -85,6 +193,9 @@ impl PersonProfile {
             email: person.email,
             qq: person.qq,
             wechat: person.wechat,
+            user_id: person.user_id,
+            visibility: person.visibility,
+            birth_month_day: None,
             created_at: person.created_at,
             updated_at: person.updated_at,
         }
@@ -100,4 +211,370 @@ impl PersonProfile {
         };
         Ok(())
     }
+
+    /// 按查看者与人员的关系隐藏不可见的字段
+    pub fn mask(&mut self, viewer: &Viewer) {
+        let relation = viewer.relation(self);
+        let visibility = &self.visibility;
+        for (field, level) in [
+            (&mut self.phone, &visibility.phone_visibility),
+            (&mut self.email, &visibility.email_visibility),
+            (&mut self.qq, &visibility.qq_visibility),
+            (&mut self.wechat, &visibility.wechat_visibility),
+        ] {
+            if !level.visible_to(relation) {
+                *field = None;
+            }
+        }
+        if visibility.birthday_visibility.visible_to(relation) {
+            self.birth_month_day = self.birthday.map(|date| date.format("%m-%d").to_string());
+            if visibility.birthday_month_day_only && relation < Relation::Admin {
+                self.birthday = None;
+            }
+        } else {
+            self.birthday = None;
+        }
+    }
+
+    /// 批量签名照片
+    pub async fn sign_all(
+        persons: impl IntoIterator<Item = &mut PersonProfile>,
+        image_service: &ImageService,
+        s3_client: &Client,
+    ) -> Result<(), ImageError> {
+        let mut persons: Vec<&mut PersonProfile> = persons
+            .into_iter()
+            .filter(|person| person.photo_id.is_some())
+            .collect();
+        let urls = image_service
+            .get_presigned_urls(
+                s3_client,
+                persons.iter().filter_map(|person| person.photo_id.clone()),
+            )
+            .await?;
+        for (person, url) in persons.iter_mut().zip(urls) {
+            person.photo = url;
+        }
+        Ok(())
+    }
+}
+
+/// 字段可见性
+#[derive(Clone, Copy, Debug, PartialEq, Eq, sqlx::Type, Serialize, Deserialize)]
+#[sqlx(type_name = "field_visibility")]
+#[sqlx(rename_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+pub enum Visibility {
+    /// 所有人
+    Public,
+    /// 登录用户
+    LoggedIn,
+    /// 同班同学
+    Classmates,
+    /// 管理员
+    Admins,
+    /// 仅本人
+    Hidden,
+}
+
+#[rocket::async_trait]
+impl<'v> FromFormField<'v> for Visibility {
+    fn from_value(field: ValueField<'v>) -> rocket::form::Result<'v, Self> {
+        match field.value.to_lowercase().as_str() {
+            "public" => Ok(Visibility::Public),
+            "logged_in" => Ok(Visibility::LoggedIn),
+            "classmates" => Ok(Visibility::Classmates),
+            "admins" => Ok(Visibility::Admins),
+            "hidden" => Ok(Visibility::Hidden),
+            other => Err(
+                rocket::form::Error::validation(format!("invalid visibility: {}", other)).into(),
+            ),
+        }
+    }
+}
+
+impl Visibility {
+    /// 至少需要的关系
+    fn required(&self) -> Relation {
+        match self {
+            Visibility::Public => Relation::Anonymous,
+            Visibility::LoggedIn => Relation::User,
+            Visibility::Classmates => Relation::Classmate,
+            Visibility::Admins => Relation::Admin,
+            Visibility::Hidden => Relation::Owner,
+        }
+    }
+
+    pub fn visible_to(&self, relation: Relation) -> bool {
+        relation >= self.required()
+    }
+}
+
+/// 人员联系方式和生日的可见性
+#[derive(Clone, Debug, PartialEq, Eq, FromRow, Serialize, Deserialize)]
+pub struct PersonVisibility {
+    pub phone_visibility: Visibility,
+    pub email_visibility: Visibility,
+    pub qq_visibility: Visibility,
+    pub wechat_visibility: Visibility,
+    pub birthday_visibility: Visibility,
+    /// 生日只显示月日，不影响管理员和本人
+    pub birthday_month_day_only: bool,
+}
+
+impl PersonVisibility {
+    pub async fn update(
+        pool: &PgPool,
+        person_id: i32,
+        visibility: &Self,
+    ) -> Result<bool, sqlx::Error> {
+        Ok(sqlx::query(
+            r#"UPDATE person
+               SET phone_visibility = $1, email_visibility = $2, qq_visibility = $3,
+                   wechat_visibility = $4, birthday_visibility = $5, birthday_month_day_only = $6
+               WHERE id = $7"#,
+        )
+        .bind(visibility.phone_visibility)
+        .bind(visibility.email_visibility)
+        .bind(visibility.qq_visibility)
+        .bind(visibility.wechat_visibility)
+        .bind(visibility.birthday_visibility)
+        .bind(visibility.birthday_month_day_only)
+        .bind(person_id)
+        .execute(pool)
+        .await?
+        .rows_affected()
+            > 0)
+    }
+}
+
+/// 查看者与人员的关系，越靠后能看到的字段越多
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Relation {
+    /// 未登录
+    Anonymous,
+    /// 登录用户
+    User,
+    /// 与人员在同一班级
+    Classmate,
+    /// 管理员
+    Admin,
+    /// 认领该人员的账户
+    Owner,
+}
+
+/// 查看人员的账户
+#[derive(Clone, Debug, Default)]
+pub struct Viewer {
+    user_id: Option<i32>,
+    admin: bool,
+    /// 与查看者认领的人员同班的人员
+    classmates: HashSet<i32>,
+}
+
+impl Viewer {
+    pub fn anonymous() -> Self {
+        Self::default()
+    }
+
+    /// * `admin` - 是否为管理员，由调用者根据权限判断
+    pub async fn load(pool: &PgPool, user_id: i32, admin: bool) -> Result<Self, sqlx::Error> {
+        let classmates: Vec<i32> = sqlx::query_scalar(
+            r#"SELECT DISTINCT other.person_id
+               FROM person
+               JOIN class_member own ON own.person_id = person.id
+               JOIN class_member other ON other.class_id = own.class_id
+               WHERE person.user_id = $1"#,
+        )
+        .bind(user_id)
+        .fetch_all(pool)
+        .await?;
+        Ok(Self {
+            user_id: Some(user_id),
+            admin,
+            classmates: classmates.into_iter().collect(),
+        })
+    }
+
+    pub fn is_admin(&self) -> bool {
+        self.admin
+    }
+
+    pub fn user_id(&self) -> Option<i32> {
+        self.user_id
+    }
+
+    pub fn relation(&self, person: &PersonProfile) -> Relation {
+        match self.user_id {
+            None => Relation::Anonymous,
+            Some(user_id) if person.user_id == Some(user_id) => Relation::Owner,
+            Some(_) if self.admin => Relation::Admin,
+            Some(_) if self.classmates.contains(&person.id) => Relation::Classmate,
+            Some(_) => Relation::User,
+        }
+    }
+}
+
+/// 认领状态
+#[derive(Clone, Debug, PartialEq, Eq, sqlx::Type, Serialize, Deserialize)]
+#[repr(i16)]
+pub enum ClaimStatus {
+    /// 待管理员审核
+    Pending = 0,
+    Approved = 1,
+    Rejected = 2,
+}
+
+/// 账户认领人员的申请
+#[derive(Clone, Debug, PartialEq, Eq, FromRow, Serialize)]
+pub struct PersonClaim {
+    pub id: i32,
+    pub person_id: i32,
+    pub user_id: i32,
+    pub status: ClaimStatus,
+    /// VARCHAR(16), 通过方式 `email` / `admin`
+    pub method: Option<String>,
+    pub reviewed_by: Option<i32>,
+    pub reviewed_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl PersonClaim {
+    /// 提交待审核的认领
+    ///
+    /// 已有待审核的认领时返回该认领
+    pub async fn create(pool: &PgPool, person_id: i32, user_id: i32) -> Result<Self, sqlx::Error> {
+        sqlx::query_as::<_, Self>(
+            r#"INSERT INTO person_claim (person_id, user_id) VALUES ($1, $2)
+               ON CONFLICT (person_id, user_id) WHERE status = 0 DO UPDATE SET person_id = EXCLUDED.person_id
+               RETURNING *"#,
+        )
+        .bind(person_id)
+        .bind(user_id)
+        .fetch_one(pool)
+        .await
+    }
+
+    /// 关联人员与账户并记录为已通过的认领
+    ///
+    /// 人员已被认领或账户已关联其他人员时返回 `None`
+    pub async fn link(
+        pool: &PgPool,
+        person_id: i32,
+        user_id: i32,
+        method: &str,
+        reviewed_by: Option<i32>,
+    ) -> Result<Option<Self>, sqlx::Error> {
+        let mut tx = pool.begin().await?;
+        let linked = match sqlx::query(
+            r#"UPDATE person SET user_id = $1 WHERE id = $2 AND user_id IS NULL
+               AND NOT EXISTS (SELECT 1 FROM person WHERE user_id = $1)"#,
+        )
+        .bind(user_id)
+        .bind(person_id)
+        .execute(&mut *tx)
+        .await
+        {
+            Ok(result) => result.rows_affected() > 0,
+            // 并发认领时 NOT EXISTS 可能同时通过，由 person.user_id 的唯一约束兜底
+            Err(sqlx::Error::Database(e)) if e.is_unique_violation() => false,
+            Err(e) => return Err(e),
+        };
+        if !linked {
+            return Ok(None);
+        }
+        // 通过待审核的认领，没有时新建一条记录
+        let claim = sqlx::query_as::<_, Self>(
+            r#"UPDATE person_claim SET status = 1, method = $3, reviewed_by = $4, reviewed_at = CURRENT_TIMESTAMP
+               WHERE person_id = $1 AND user_id = $2 AND status = 0 RETURNING *"#,
+        )
+        .bind(person_id)
+        .bind(user_id)
+        .bind(method)
+        .bind(reviewed_by)
+        .fetch_optional(&mut *tx)
+        .await?;
+        let claim = match claim {
+            Some(claim) => claim,
+            None => {
+                sqlx::query_as::<_, Self>(
+                    r#"INSERT INTO person_claim (person_id, user_id, status, method, reviewed_by, reviewed_at)
+                       VALUES ($1, $2, 1, $3, $4, CURRENT_TIMESTAMP) RETURNING *"#,
+                )
+                .bind(person_id)
+                .bind(user_id)
+                .bind(method)
+                .bind(reviewed_by)
+                .fetch_one(&mut *tx)
+                .await?
+            }
+        };
+        // 人员已被认领，其他待审核的认领一并拒绝
+        sqlx::query(
+            r#"UPDATE person_claim SET status = 2, reviewed_by = $2, reviewed_at = CURRENT_TIMESTAMP
+               WHERE person_id = $1 AND status = 0"#,
+        )
+        .bind(person_id)
+        .bind(reviewed_by)
+        .execute(&mut *tx)
+        .await?;
+        tx.commit().await?;
+        Ok(Some(claim))
+    }
+
+    pub async fn find(pool: &PgPool, id: i32) -> Result<Option<Self>, sqlx::Error> {
+        sqlx::query_as::<_, Self>(r#"SELECT * FROM person_claim WHERE id = $1"#)
+            .bind(id)
+            .fetch_optional(pool)
+            .await
+    }
+
+    /// 拒绝待审核的认领
+    ///
+    /// 认领不存在或已审核时返回 `false`
+    pub async fn reject(pool: &PgPool, id: i32, reviewed_by: i32) -> Result<bool, sqlx::Error> {
+        Ok(sqlx::query(
+            r#"UPDATE person_claim SET status = 2, method = 'admin', reviewed_by = $2, reviewed_at = CURRENT_TIMESTAMP
+               WHERE id = $1 AND status = 0"#,
+        )
+        .bind(id)
+        .bind(reviewed_by)
+        .execute(pool)
+        .await?
+        .rows_affected()
+            > 0)
+    }
+
+    /// 按状态分页查询，按提交时间排序
+    pub async fn list(
+        pool: &PgPool,
+        status: &ClaimStatus,
+        limit: i64,
+        offset: i64,
+    ) -> Result<(Vec<Self>, i64), sqlx::Error> {
+        let items = sqlx::query_as::<_, Self>(
+            r#"SELECT * FROM person_claim WHERE status = $1 ORDER BY created_at, id LIMIT $2 OFFSET $3"#,
+        )
+        .bind(status)
+        .bind(limit)
+        .bind(offset)
+        .fetch_all(pool)
+        .await?;
+        let total: i64 =
+            sqlx::query_scalar(r#"SELECT COUNT(*) FROM person_claim WHERE status = $1"#)
+                .bind(status)
+                .fetch_one(pool)
+                .await?;
+        Ok((items, total))
+    }
+
+    /// 账户提交的全部认领，新的在前
+    pub async fn of_user(pool: &PgPool, user_id: i32) -> Result<Vec<Self>, sqlx::Error> {
+        sqlx::query_as::<_, Self>(
+            r#"SELECT * FROM person_claim WHERE user_id = $1 ORDER BY created_at DESC, id DESC"#,
+        )
+        .bind(user_id)
+        .fetch_all(pool)
+        .await
+    }
 }
@@ -0,0 +1,118 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{FromRow, PgExecutor, PgPool};
+
+/// 角色
+#[derive(Clone, Debug, PartialEq, Eq, FromRow, Serialize, Deserialize)]
+pub struct Role {
+    pub id: i32,
+    /// VARCHAR(64), 唯一
+    pub name: String,
+    /// VARCHAR(255)
+    pub description: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// 权限
+#[derive(Clone, Debug, PartialEq, Eq, FromRow, Serialize, Deserialize)]
+pub struct Permission {
+    pub id: i32,
+    /// VARCHAR(64), 唯一, 形如 `person.write`
+    pub code: String,
+    /// VARCHAR(255)
+    pub description: Option<String>,
+}
+
+impl Role {
+    /// 全部角色
+    pub async fn all(pool: &PgPool) -> Result<Vec<Self>, sqlx::Error> {
+        sqlx::query_as::<_, Self>(r#"SELECT * FROM role ORDER BY id"#)
+            .fetch_all(pool)
+            .await
+    }
+
+    /// 查询用户拥有的全部角色
+    pub async fn of_user(pool: &PgPool, user_id: i32) -> Result<Vec<Self>, sqlx::Error> {
+        sqlx::query_as::<_, Self>(
+            r#"SELECT role.* FROM role JOIN user_role ON role.id = user_role.role_id WHERE user_role.user_id = $1"#,
+        )
+        .bind(user_id)
+        .fetch_all(pool)
+        .await
+    }
+
+    /// 判断用户是否拥有指定角色
+    pub async fn user_has(pool: &PgPool, user_id: i32, name: &str) -> Result<bool, sqlx::Error> {
+        sqlx::query_scalar(
+            r#"SELECT EXISTS(SELECT 1 FROM user_role JOIN role ON role.id = user_role.role_id WHERE user_role.user_id = $1 AND role.name = $2)"#,
+        )
+        .bind(user_id)
+        .bind(name)
+        .fetch_one(pool)
+        .await
+    }
+
+    /// 为用户授予角色
+    ///
+    /// 角色不存在时不做任何操作
+    pub async fn grant<'e>(
+        executor: impl PgExecutor<'e>,
+        user_id: i32,
+        name: &str,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            r#"INSERT INTO user_role (user_id, role_id) SELECT $1, id FROM role WHERE name = $2 ON CONFLICT DO NOTHING"#,
+        )
+        .bind(user_id)
+        .bind(name)
+        .execute(executor)
+        .await?;
+        Ok(())
+    }
+
+    /// 撤销用户的角色
+    pub async fn revoke<'e>(
+        executor: impl PgExecutor<'e>,
+        user_id: i32,
+        name: &str,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            r#"DELETE FROM user_role USING role WHERE user_role.role_id = role.id AND user_role.user_id = $1 AND role.name = $2"#,
+        )
+        .bind(user_id)
+        .bind(name)
+        .execute(executor)
+        .await?;
+        Ok(())
+    }
+}
+
+impl Permission {
+    /// 查询用户通过角色获得的全部权限
+    pub async fn of_user(pool: &PgPool, user_id: i32) -> Result<Vec<Self>, sqlx::Error> {
+        sqlx::query_as::<_, Self>(
+            r#"SELECT DISTINCT permission.* FROM permission
+               JOIN role_permission ON permission.id = role_permission.permission_id
+               JOIN user_role ON role_permission.role_id = user_role.role_id
+               WHERE user_role.user_id = $1"#,
+        )
+        .bind(user_id)
+        .fetch_all(pool)
+        .await
+    }
+
+    /// 判断用户是否拥有指定权限
+    pub async fn user_has(pool: &PgPool, user_id: i32, code: &str) -> Result<bool, sqlx::Error> {
+        sqlx::query_scalar(
+            r#"SELECT EXISTS(SELECT 1 FROM permission
+               JOIN role_permission ON permission.id = role_permission.permission_id
+               JOIN user_role ON role_permission.role_id = user_role.role_id
+               WHERE user_role.user_id = $1 AND permission.code = $2)"#,
+        )
+        .bind(user_id)
+        .bind(code)
+        .fetch_one(pool)
+        .await
+    }
+}
@@ -0,0 +1,313 @@
+use crate::person::Gender;
+use chrono::{DateTime, Utc};
+use rocket::form::{FromFormField, ValueField};
+use serde::{Deserialize, Serialize};
+use sqlx::{FromRow, PgPool};
+
+/// 学校
+#[derive(Clone, Debug, PartialEq, Eq, FromRow, Serialize)]
+pub struct School {
+    pub id: i32,
+    /// VARCHAR(128), 唯一
+    pub name: String,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// 届
+#[derive(Clone, Debug, PartialEq, Eq, FromRow, Serialize)]
+pub struct Cohort {
+    pub id: i32,
+    pub school_id: i32,
+    /// 毕业年份，同一学校内唯一
+    pub graduation_year: i16,
+    /// VARCHAR(64), 如 "2015届"
+    pub name: String,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// 班级
+#[derive(Clone, Debug, PartialEq, Eq, FromRow, Serialize)]
+pub struct SchoolClass {
+    pub id: i32,
+    pub cohort_id: i32,
+    /// VARCHAR(64), 同一届内唯一
+    pub name: String,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// 班级成员身份
+#[derive(Clone, Debug, PartialEq, Eq, sqlx::Type, Serialize, Deserialize)]
+#[sqlx(type_name = "class_role")]
+#[sqlx(rename_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+pub enum ClassRole {
+    /// 学生
+    Student,
+    /// 班主任
+    HeadTeacher,
+    /// 班长
+    Monitor,
+}
+
+#[rocket::async_trait]
+impl<'v> FromFormField<'v> for ClassRole {
+    fn from_value(field: ValueField<'v>) -> rocket::form::Result<'v, Self> {
+        match field.value.to_lowercase().as_str() {
+            "student" => Ok(ClassRole::Student),
+            "head_teacher" => Ok(ClassRole::HeadTeacher),
+            "monitor" => Ok(ClassRole::Monitor),
+            other => Err(
+                rocket::form::Error::validation(format!("invalid class role: {}", other)).into(),
+            ),
+        }
+    }
+}
+
+/// 班级成员
+#[derive(Clone, Debug, PartialEq, Eq, FromRow, Serialize)]
+pub struct ClassMember {
+    pub class_id: i32,
+    pub person_id: i32,
+    pub role: ClassRole,
+    pub created_at: DateTime<Utc>,
+}
+
+/// 班级成员及其基本信息
+#[derive(Clone, Debug, PartialEq, Eq, FromRow, Serialize)]
+pub struct ClassMemberInfo {
+    pub person_id: i32,
+    pub name: String,
+    pub gender: Gender,
+    pub role: ClassRole,
+}
+
+/// 人员所在的班级
+#[derive(Clone, Debug, PartialEq, Eq, FromRow, Serialize)]
+pub struct PersonClass {
+    pub school_id: i32,
+    pub school_name: String,
+    pub cohort_id: i32,
+    pub graduation_year: i16,
+    pub cohort_name: String,
+    pub class_id: i32,
+    pub class_name: String,
+    pub role: ClassRole,
+}
+
+impl School {
+    /// 全部学校，按名称排序
+    pub async fn all(pool: &PgPool) -> Result<Vec<Self>, sqlx::Error> {
+        sqlx::query_as::<_, Self>(r#"SELECT * FROM school ORDER BY name"#)
+            .fetch_all(pool)
+            .await
+    }
+
+    pub async fn find(pool: &PgPool, id: i32) -> Result<Option<Self>, sqlx::Error> {
+        sqlx::query_as::<_, Self>(r#"SELECT * FROM school WHERE id = $1"#)
+            .bind(id)
+            .fetch_optional(pool)
+            .await
+    }
+
+    pub async fn create(pool: &PgPool, name: &str) -> Result<Self, sqlx::Error> {
+        sqlx::query_as::<_, Self>(r#"INSERT INTO school (name) VALUES ($1) RETURNING *"#)
+            .bind(name)
+            .fetch_one(pool)
+            .await
+    }
+
+    pub async fn update(pool: &PgPool, id: i32, name: &str) -> Result<Option<Self>, sqlx::Error> {
+        sqlx::query_as::<_, Self>(r#"UPDATE school SET name = $1 WHERE id = $2 RETURNING *"#)
+            .bind(name)
+            .bind(id)
+            .fetch_optional(pool)
+            .await
+    }
+
+    /// 删除学校及其下的届、班级
+    pub async fn delete(pool: &PgPool, id: i32) -> Result<bool, sqlx::Error> {
+        Ok(sqlx::query(r#"DELETE FROM school WHERE id = $1"#)
+            .bind(id)
+            .execute(pool)
+            .await?
+            .rows_affected()
+            > 0)
+    }
+}
+
+impl Cohort {
+    /// 学校的全部届，按毕业年份排序
+    pub async fn of_school(pool: &PgPool, school_id: i32) -> Result<Vec<Self>, sqlx::Error> {
+        sqlx::query_as::<_, Self>(
+            r#"SELECT * FROM cohort WHERE school_id = $1 ORDER BY graduation_year"#,
+        )
+        .bind(school_id)
+        .fetch_all(pool)
+        .await
+    }
+
+    pub async fn find(pool: &PgPool, id: i32) -> Result<Option<Self>, sqlx::Error> {
+        sqlx::query_as::<_, Self>(r#"SELECT * FROM cohort WHERE id = $1"#)
+            .bind(id)
+            .fetch_optional(pool)
+            .await
+    }
+
+    pub async fn create(
+        pool: &PgPool,
+        school_id: i32,
+        graduation_year: i16,
+        name: &str,
+    ) -> Result<Self, sqlx::Error> {
+        sqlx::query_as::<_, Self>(
+            r#"INSERT INTO cohort (school_id, graduation_year, name) VALUES ($1, $2, $3) RETURNING *"#,
+        )
+        .bind(school_id)
+        .bind(graduation_year)
+        .bind(name)
+        .fetch_one(pool)
+        .await
+    }
+
+    pub async fn update(
+        pool: &PgPool,
+        id: i32,
+        graduation_year: i16,
+        name: &str,
+    ) -> Result<Option<Self>, sqlx::Error> {
+        sqlx::query_as::<_, Self>(
+            r#"UPDATE cohort SET graduation_year = $1, name = $2 WHERE id = $3 RETURNING *"#,
+        )
+        .bind(graduation_year)
+        .bind(name)
+        .bind(id)
+        .fetch_optional(pool)
+        .await
+    }
+
+    /// 删除届及其下的班级
+    pub async fn delete(pool: &PgPool, id: i32) -> Result<bool, sqlx::Error> {
+        Ok(sqlx::query(r#"DELETE FROM cohort WHERE id = $1"#)
+            .bind(id)
+            .execute(pool)
+            .await?
+            .rows_affected()
+            > 0)
+    }
+}
+
+impl SchoolClass {
+    /// 届的全部班级，按名称排序
+    pub async fn of_cohort(pool: &PgPool, cohort_id: i32) -> Result<Vec<Self>, sqlx::Error> {
+        sqlx::query_as::<_, Self>(
+            r#"SELECT * FROM school_class WHERE cohort_id = $1 ORDER BY name"#,
+        )
+        .bind(cohort_id)
+        .fetch_all(pool)
+        .await
+    }
+
+    pub async fn find(pool: &PgPool, id: i32) -> Result<Option<Self>, sqlx::Error> {
+        sqlx::query_as::<_, Self>(r#"SELECT * FROM school_class WHERE id = $1"#)
+            .bind(id)
+            .fetch_optional(pool)
+            .await
+    }
+
+    pub async fn create(pool: &PgPool, cohort_id: i32, name: &str) -> Result<Self, sqlx::Error> {
+        sqlx::query_as::<_, Self>(
+            r#"INSERT INTO school_class (cohort_id, name) VALUES ($1, $2) RETURNING *"#,
+        )
+        .bind(cohort_id)
+        .bind(name)
+        .fetch_one(pool)
+        .await
+    }
+
+    pub async fn update(pool: &PgPool, id: i32, name: &str) -> Result<Option<Self>, sqlx::Error> {
+        sqlx::query_as::<_, Self>(r#"UPDATE school_class SET name = $1 WHERE id = $2 RETURNING *"#)
+            .bind(name)
+            .bind(id)
+            .fetch_optional(pool)
+            .await
+    }
+
+    pub async fn delete(pool: &PgPool, id: i32) -> Result<bool, sqlx::Error> {
+        Ok(sqlx::query(r#"DELETE FROM school_class WHERE id = $1"#)
+            .bind(id)
+            .execute(pool)
+            .await?
+            .rows_affected()
+            > 0)
+    }
+}
+
+impl ClassMember {
+    /// 加入班级，已在班级中时修改身份
+    pub async fn set(
+        pool: &PgPool,
+        class_id: i32,
+        person_id: i32,
+        role: &ClassRole,
+    ) -> Result<Self, sqlx::Error> {
+        sqlx::query_as::<_, Self>(
+            r#"INSERT INTO class_member (class_id, person_id, role) VALUES ($1, $2, $3)
+               ON CONFLICT (class_id, person_id) DO UPDATE SET role = EXCLUDED.role RETURNING *"#,
+        )
+        .bind(class_id)
+        .bind(person_id)
+        .bind(role)
+        .fetch_one(pool)
+        .await
+    }
+
+    pub async fn remove(pool: &PgPool, class_id: i32, person_id: i32) -> Result<bool, sqlx::Error> {
+        Ok(
+            sqlx::query(r#"DELETE FROM class_member WHERE class_id = $1 AND person_id = $2"#)
+                .bind(class_id)
+                .bind(person_id)
+                .execute(pool)
+                .await?
+                .rows_affected()
+                > 0,
+        )
+    }
+
+    /// 班级的全部成员，班主任、班长在前
+    pub async fn of_class(
+        pool: &PgPool,
+        class_id: i32,
+    ) -> Result<Vec<ClassMemberInfo>, sqlx::Error> {
+        sqlx::query_as::<_, ClassMemberInfo>(
+            r#"SELECT person.id AS person_id, person.name, person.gender, class_member.role
+               FROM class_member JOIN person ON person.id = class_member.person_id
+               WHERE class_member.class_id = $1
+               ORDER BY CASE class_member.role WHEN 'head_teacher' THEN 0 WHEN 'monitor' THEN 1 ELSE 2 END,
+                        person.name_pinyin, person.id"#,
+        )
+        .bind(class_id)
+        .fetch_all(pool)
+        .await
+    }
+
+    /// 人员所在的全部班级，按毕业年份排序
+    pub async fn of_person(pool: &PgPool, person_id: i32) -> Result<Vec<PersonClass>, sqlx::Error> {
+        sqlx::query_as::<_, PersonClass>(
+            r#"SELECT school.id AS school_id, school.name AS school_name,
+                      cohort.id AS cohort_id, cohort.graduation_year, cohort.name AS cohort_name,
+                      school_class.id AS class_id, school_class.name AS class_name, class_member.role
+               FROM class_member
+               JOIN school_class ON school_class.id = class_member.class_id
+               JOIN cohort ON cohort.id = school_class.cohort_id
+               JOIN school ON school.id = cohort.school_id
+               WHERE class_member.person_id = $1
+               ORDER BY cohort.graduation_year, school.name, school_class.name"#,
+        )
+        .bind(person_id)
+        .fetch_all(pool)
+        .await
+    }
+}
@@ -0,0 +1,144 @@
+use crate::token::{RefreshToken, RevokedToken};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use sqlx::types::Uuid;
+use sqlx::{FromRow, PgPool};
+
+/// 登录会话
+///
+/// 一次登录对应一个 refresh token family, `id` 即 family
+#[derive(Clone, Debug, PartialEq, Eq, FromRow, Serialize)]
+pub struct UserSession {
+    pub id: Uuid,
+    pub user_id: i32,
+    /// VARCHAR(45)
+    pub ip: Option<String>,
+    /// VARCHAR(512)
+    pub user_agent: Option<String>,
+    /// 最近签发的 access token
+    #[serde(skip_serializing)]
+    pub access_jti: Option<Uuid>,
+    #[serde(skip_serializing)]
+    pub access_expire: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+    /// 最近一次签发 token 的时间
+    pub last_seen_at: DateTime<Utc>,
+    pub revoked_at: Option<DateTime<Utc>>,
+}
+
+impl UserSession {
+    pub async fn create(
+        pool: &PgPool,
+        user_id: i32,
+        ip: Option<&str>,
+        user_agent: Option<&str>,
+    ) -> Result<Self, sqlx::Error> {
+        sqlx::query_as::<_, Self>(
+            r#"INSERT INTO user_session (id, user_id, ip, user_agent) VALUES ($1, $2, $3, $4) RETURNING *"#,
+        )
+        .bind(Uuid::new_v4())
+        .bind(user_id)
+        .bind(ip)
+        .bind(user_agent)
+        .fetch_one(pool)
+        .await
+    }
+
+    /// 是否为新设备
+    ///
+    /// 用户有过登录记录，且没有任何会话使用相同的 user agent。首次登录不视为新设备
+    pub async fn is_new_device(
+        pool: &PgPool,
+        user_id: i32,
+        user_agent: Option<&str>,
+    ) -> Result<bool, sqlx::Error> {
+        sqlx::query_scalar(
+            r#"SELECT EXISTS(SELECT 1 FROM user_session WHERE user_id = $1)
+                  AND NOT EXISTS(SELECT 1 FROM user_session WHERE user_id = $1 AND user_agent IS NOT DISTINCT FROM $2)"#,
+        )
+        .bind(user_id)
+        .bind(user_agent)
+        .fetch_one(pool)
+        .await
+    }
+
+    /// 记录新签发的 access token
+    pub async fn touch(
+        pool: &PgPool,
+        id: &Uuid,
+        access_jti: &Uuid,
+        access_expire: DateTime<Utc>,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            r#"UPDATE user_session SET access_jti = $1, access_expire = $2, last_seen_at = CURRENT_TIMESTAMP WHERE id = $3"#,
+        )
+        .bind(access_jti)
+        .bind(access_expire)
+        .bind(id)
+        .execute(pool)
+        .await?;
+        Ok(())
+    }
+
+    /// 用户仍然有效的会话
+    ///
+    /// 会话未被吊销，且 refresh token 仍可使用
+    pub async fn active_of_user(pool: &PgPool, user_id: i32) -> Result<Vec<Self>, sqlx::Error> {
+        sqlx::query_as::<_, Self>(
+            r#"SELECT * FROM user_session s
+               WHERE s.user_id = $1 AND s.revoked_at IS NULL AND EXISTS(
+                   SELECT 1 FROM refresh_token r
+                   WHERE r.family = s.id AND r.used_at IS NULL AND r.revoked_at IS NULL AND r.expire >= CURRENT_TIMESTAMP
+               )
+               ORDER BY s.last_seen_at DESC"#,
+        )
+        .bind(user_id)
+        .fetch_all(pool)
+        .await
+    }
+
+    /// 用户的全部会话，包括已失效的
+    pub async fn of_user(pool: &PgPool, user_id: i32) -> Result<Vec<Self>, sqlx::Error> {
+        sqlx::query_as::<_, Self>(
+            r#"SELECT * FROM user_session WHERE user_id = $1 ORDER BY created_at DESC"#,
+        )
+        .bind(user_id)
+        .fetch_all(pool)
+        .await
+    }
+
+    pub async fn find(pool: &PgPool, user_id: i32, id: &Uuid) -> Result<Option<Self>, sqlx::Error> {
+        sqlx::query_as::<_, Self>(r#"SELECT * FROM user_session WHERE id = $1 AND user_id = $2"#)
+            .bind(id)
+            .bind(user_id)
+            .fetch_optional(pool)
+            .await
+    }
+
+    /// 会话是否已被吊销
+    pub async fn is_revoked(pool: &PgPool, id: &Uuid) -> Result<bool, sqlx::Error> {
+        sqlx::query_scalar(
+            r#"SELECT EXISTS(SELECT 1 FROM user_session WHERE id = $1 AND revoked_at IS NOT NULL)"#,
+        )
+        .bind(id)
+        .fetch_one(pool)
+        .await
+    }
+
+    /// 吊销会话
+    ///
+    /// 同时吊销 refresh token family 与最近签发的 access token
+    pub async fn revoke(&self, pool: &PgPool) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            r#"UPDATE user_session SET revoked_at = CURRENT_TIMESTAMP WHERE id = $1 AND revoked_at IS NULL"#,
+        )
+        .bind(self.id)
+        .execute(pool)
+        .await?;
+        RefreshToken::revoke_family(pool, &self.id).await?;
+        if let (Some(jti), Some(expire)) = (&self.access_jti, self.access_expire) {
+            RevokedToken::revoke(pool, jti, self.user_id, expire).await?;
+        }
+        Ok(())
+    }
+}
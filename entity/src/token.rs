@@ -0,0 +1,400 @@
+use argon2::password_hash::rand_core::{OsRng, RngCore};
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use sqlx::types::Uuid;
+use sqlx::{FromRow, PgPool};
+
+/// 生成不透明 token
+///
+/// 32 字节随机数, base64url 编码
+pub fn generate_opaque_token() -> String {
+    let mut bytes = [0u8; 32];
+    OsRng.fill_bytes(&mut bytes);
+    URL_SAFE_NO_PAD.encode(bytes)
+}
+
+/// 计算 token 摘要
+///
+/// 数据库中只保存 sha256 摘要的十六进制
+pub fn hash_token(token: &str) -> String {
+    Sha256::digest(token.as_bytes())
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+/// refresh token
+///
+/// 每次刷新都会消耗旧 token 并在同一 family 下签发新 token，
+/// 已消耗的 token 再次出现时视为泄露，整个 family 将被吊销
+#[derive(Clone, Debug, PartialEq, Eq, FromRow)]
+pub struct RefreshToken {
+    pub id: i32,
+    pub user_id: i32,
+    pub family: Uuid,
+    /// VARCHAR(64), sha256 摘要
+    pub token_hash: String,
+    pub expire: DateTime<Utc>,
+    pub used_at: Option<DateTime<Utc>>,
+    pub revoked_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl RefreshToken {
+    /// 签发 refresh token
+    ///
+    /// * `family` - 为 `None` 时开启新的 family（即一次新的登录）
+    /// * `ttl` - 有效期
+    ///
+    /// 返回 token 原文，原文不会被保存
+    pub async fn issue(
+        pool: &PgPool,
+        user_id: i32,
+        family: Option<Uuid>,
+        ttl: chrono::Duration,
+    ) -> Result<String, sqlx::Error> {
+        let token = generate_opaque_token();
+        let expire = Utc::now()
+            .checked_add_signed(ttl)
+            .expect("Invalid timestamp");
+        sqlx::query(
+            r#"INSERT INTO refresh_token (user_id, family, token_hash, expire) VALUES ($1, $2, $3, $4)"#,
+        )
+        .bind(user_id)
+        .bind(family.unwrap_or_else(Uuid::new_v4))
+        .bind(hash_token(&token))
+        .bind(expire)
+        .execute(pool)
+        .await?;
+        Ok(token)
+    }
+
+    /// 通过 token 原文查找
+    pub async fn find(pool: &PgPool, token: &str) -> Result<Option<Self>, sqlx::Error> {
+        sqlx::query_as::<_, Self>(r#"SELECT * FROM refresh_token WHERE token_hash = $1"#)
+            .bind(hash_token(token))
+            .fetch_optional(pool)
+            .await
+    }
+
+    /// 是否已被使用或吊销
+    pub fn is_spent(&self) -> bool {
+        self.used_at.is_some() || self.revoked_at.is_some()
+    }
+
+    /// 是否已过期
+    pub fn is_expired(&self) -> bool {
+        self.expire < Utc::now()
+    }
+
+    /// 标记为已使用
+    ///
+    /// 返回 `false` 表示已被并发请求抢先使用
+    pub async fn consume(&self, pool: &PgPool) -> Result<bool, sqlx::Error> {
+        let result = sqlx::query(
+            r#"UPDATE refresh_token SET used_at = CURRENT_TIMESTAMP WHERE id = $1 AND used_at IS NULL AND revoked_at IS NULL"#,
+        )
+        .bind(self.id)
+        .execute(pool)
+        .await?;
+        Ok(result.rows_affected() == 1)
+    }
+
+    /// 吊销整个 family
+    pub async fn revoke_family(pool: &PgPool, family: &Uuid) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            r#"UPDATE refresh_token SET revoked_at = CURRENT_TIMESTAMP WHERE family = $1 AND revoked_at IS NULL"#,
+        )
+        .bind(family)
+        .execute(pool)
+        .await?;
+        Ok(())
+    }
+}
+
+/// 已吊销的 access token
+#[derive(Clone, Debug, PartialEq, Eq, FromRow)]
+pub struct RevokedToken {
+    pub jti: Uuid,
+    pub user_id: i32,
+    /// 原 token 的过期时间
+    pub expire: DateTime<Utc>,
+}
+
+impl RevokedToken {
+    /// 吊销 access token
+    pub async fn revoke(
+        pool: &PgPool,
+        jti: &Uuid,
+        user_id: i32,
+        expire: DateTime<Utc>,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            r#"INSERT INTO revoked_token (jti, user_id, expire) VALUES ($1, $2, $3) ON CONFLICT DO NOTHING"#,
+        )
+        .bind(jti)
+        .bind(user_id)
+        .bind(expire)
+        .execute(pool)
+        .await?;
+        Ok(())
+    }
+
+    /// 判断 token 是否已失效
+    ///
+    /// token 被单独吊销，或签发时间早于用户的 `tokens_valid_after` 时均视为失效
+    ///
+    /// * `issued_at` - 为 `None` 时不检查 `tokens_valid_after`，用于个人访问令牌
+    pub async fn is_revoked(
+        pool: &PgPool,
+        jti: &Uuid,
+        user_id: i32,
+        issued_at: Option<DateTime<Utc>>,
+    ) -> Result<bool, sqlx::Error> {
+        sqlx::query_scalar(
+            r#"SELECT EXISTS(SELECT 1 FROM revoked_token WHERE jti = $1)
+                   OR EXISTS(SELECT 1 FROM "user" WHERE id = $2 AND tokens_valid_after > $3)"#,
+        )
+        .bind(jti)
+        .bind(user_id)
+        .bind(issued_at)
+        .fetch_one(pool)
+        .await
+    }
+}
+
+/// 两步验证临时凭证
+///
+/// 密码校验通过后签发，与 TOTP 验证码一同换取正式 token
+#[derive(Clone, Debug, PartialEq, Eq, FromRow)]
+pub struct MfaChallenge {
+    pub id: i32,
+    pub user_id: i32,
+    /// VARCHAR(64), sha256 摘要
+    pub token_hash: String,
+    /// 已尝试次数
+    pub attempts: i32,
+    pub expire: DateTime<Utc>,
+}
+
+impl MfaChallenge {
+    /// 签发临时凭证，返回 token 原文
+    pub async fn issue(
+        pool: &PgPool,
+        user_id: i32,
+        ttl: chrono::Duration,
+    ) -> Result<String, sqlx::Error> {
+        let token = generate_opaque_token();
+        let expire = Utc::now()
+            .checked_add_signed(ttl)
+            .expect("Invalid timestamp");
+        sqlx::query(
+            r#"INSERT INTO mfa_challenge (user_id, token_hash, expire) VALUES ($1, $2, $3)"#,
+        )
+        .bind(user_id)
+        .bind(hash_token(&token))
+        .bind(expire)
+        .execute(pool)
+        .await?;
+        Ok(token)
+    }
+
+    /// 查找未过期的凭证并记录一次尝试
+    pub async fn attempt(pool: &PgPool, token: &str) -> Result<Option<Self>, sqlx::Error> {
+        sqlx::query_as::<_, Self>(
+            r#"UPDATE mfa_challenge SET attempts = attempts + 1
+               WHERE token_hash = $1 AND expire >= CURRENT_TIMESTAMP RETURNING *"#,
+        )
+        .bind(hash_token(token))
+        .fetch_optional(pool)
+        .await
+    }
+
+    pub async fn delete(self, pool: &PgPool) -> Result<(), sqlx::Error> {
+        sqlx::query(r#"DELETE FROM mfa_challenge WHERE id = $1"#)
+            .bind(self.id)
+            .execute(pool)
+            .await?;
+        Ok(())
+    }
+}
+
+/// 两步验证恢复码
+#[derive(Clone, Debug, PartialEq, Eq, FromRow)]
+pub struct TotpRecoveryCode {
+    pub id: i32,
+    pub user_id: i32,
+    /// VARCHAR(64), sha256 摘要
+    pub code_hash: String,
+    pub used_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl TotpRecoveryCode {
+    /// 每个用户的恢复码数量
+    pub const COUNT: usize = 10;
+
+    /// 统一大小写并去除分隔符
+    fn normalize(code: &str) -> String {
+        code.chars()
+            .filter(|c| c.is_ascii_alphanumeric())
+            .map(|c| c.to_ascii_lowercase())
+            .collect()
+    }
+
+    /// 重新生成用户的恢复码，旧的恢复码全部失效
+    ///
+    /// 返回恢复码原文, 形如 `abcde-fghij`
+    pub async fn regenerate(pool: &PgPool, user_id: i32) -> Result<Vec<String>, sqlx::Error> {
+        const ALPHABET: &[u8] = b"abcdefghjkmnpqrstuvwxyz23456789";
+        let codes: Vec<String> = (0..Self::COUNT)
+            .map(|_| {
+                let mut bytes = [0u8; 10];
+                OsRng.fill_bytes(&mut bytes);
+                let chars: String = bytes
+                    .iter()
+                    .map(|b| ALPHABET[*b as usize % ALPHABET.len()] as char)
+                    .collect();
+                format!("{}-{}", &chars[..5], &chars[5..])
+            })
+            .collect();
+        let mut tx = pool.begin().await?;
+        sqlx::query(r#"DELETE FROM totp_recovery_code WHERE user_id = $1"#)
+            .bind(user_id)
+            .execute(&mut *tx)
+            .await?;
+        for code in &codes {
+            sqlx::query(r#"INSERT INTO totp_recovery_code (user_id, code_hash) VALUES ($1, $2)"#)
+                .bind(user_id)
+                .bind(hash_token(&Self::normalize(code)))
+                .execute(&mut *tx)
+                .await?;
+        }
+        tx.commit().await?;
+        Ok(codes)
+    }
+
+    /// 使用恢复码
+    ///
+    /// 返回 `false` 表示恢复码无效或已被使用
+    pub async fn consume(pool: &PgPool, user_id: i32, code: &str) -> Result<bool, sqlx::Error> {
+        let result = sqlx::query(
+            r#"UPDATE totp_recovery_code SET used_at = CURRENT_TIMESTAMP
+               WHERE user_id = $1 AND code_hash = $2 AND used_at IS NULL"#,
+        )
+        .bind(user_id)
+        .bind(hash_token(&Self::normalize(code)))
+        .execute(pool)
+        .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// 删除用户的全部恢复码
+    pub async fn delete_all(pool: &PgPool, user_id: i32) -> Result<(), sqlx::Error> {
+        sqlx::query(r#"DELETE FROM totp_recovery_code WHERE user_id = $1"#)
+            .bind(user_id)
+            .execute(pool)
+            .await?;
+        Ok(())
+    }
+}
+
+/// 个人访问令牌
+///
+/// 供脚本与第三方集成使用, 通过 `Authorization: Bearer bpat_...` 认证
+#[derive(Clone, Debug, PartialEq, Eq, FromRow, Serialize)]
+pub struct PersonalAccessToken {
+    pub id: Uuid,
+    pub user_id: i32,
+    /// VARCHAR(64)
+    pub name: String,
+    #[serde(skip_serializing)]
+    pub token_hash: String,
+    /// token 原文前缀
+    pub token_prefix: String,
+    /// 权限 code 列表
+    pub scopes: Vec<String>,
+    pub expire: Option<DateTime<Utc>>,
+    pub last_used_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl PersonalAccessToken {
+    /// token 原文前缀
+    pub const PREFIX: &'static str = "bpat_";
+
+    /// 创建令牌，返回令牌与 token 原文
+    pub async fn create(
+        pool: &PgPool,
+        user_id: i32,
+        name: &str,
+        scopes: &[String],
+        expire: Option<DateTime<Utc>>,
+    ) -> Result<(Self, String), sqlx::Error> {
+        let token = format!("{}{}", Self::PREFIX, generate_opaque_token());
+        let row = sqlx::query_as::<_, Self>(
+            r#"INSERT INTO personal_access_token (id, user_id, name, token_hash, token_prefix, scopes, expire)
+               VALUES ($1, $2, $3, $4, $5, $6, $7) RETURNING *"#,
+        )
+        .bind(Uuid::new_v4())
+        .bind(user_id)
+        .bind(name)
+        .bind(hash_token(&token))
+        .bind(&token[..Self::PREFIX.len() + 6])
+        .bind(scopes)
+        .bind(expire)
+        .fetch_one(pool)
+        .await?;
+        Ok((row, token))
+    }
+
+    /// 通过 token 原文认证
+    ///
+    /// 不更新最近使用时间，令牌通过全部校验后应调用 [`Self::touch`]
+    pub async fn authenticate(pool: &PgPool, token: &str) -> Result<Option<Self>, sqlx::Error> {
+        sqlx::query_as::<_, Self>(
+            r#"SELECT * FROM personal_access_token
+               WHERE token_hash = $1 AND (expire IS NULL OR expire >= CURRENT_TIMESTAMP)"#,
+        )
+        .bind(hash_token(token))
+        .fetch_optional(pool)
+        .await
+    }
+
+    /// 更新最近使用时间
+    pub async fn touch(pool: &PgPool, id: &Uuid) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            r#"UPDATE personal_access_token SET last_used_at = CURRENT_TIMESTAMP WHERE id = $1"#,
+        )
+        .bind(id)
+        .execute(pool)
+        .await?;
+        Ok(())
+    }
+
+    /// 用户的全部令牌
+    pub async fn of_user(pool: &PgPool, user_id: i32) -> Result<Vec<Self>, sqlx::Error> {
+        sqlx::query_as::<_, Self>(
+            r#"SELECT * FROM personal_access_token WHERE user_id = $1 ORDER BY created_at DESC"#,
+        )
+        .bind(user_id)
+        .fetch_all(pool)
+        .await
+    }
+
+    /// 删除令牌
+    ///
+    /// 返回 `false` 表示令牌不存在或不属于该用户
+    pub async fn delete(pool: &PgPool, user_id: i32, id: &Uuid) -> Result<bool, sqlx::Error> {
+        let result =
+            sqlx::query(r#"DELETE FROM personal_access_token WHERE id = $1 AND user_id = $2"#)
+                .bind(id)
+                .bind(user_id)
+                .execute(pool)
+                .await?;
+        Ok(result.rows_affected() > 0)
+    }
+}
@@ -1,6 +1,4 @@
-use argon2::password_hash::rand_core::OsRng;
-use argon2::password_hash::SaltString;
-use argon2::{Argon2, PasswordHash, PasswordHasher, PasswordVerifier};
+use crate::password::PasswordConfig;
 use aws_sdk_s3::Client;
 use chrono::{DateTime, Utc};
 use image_service::errors::ImageError;
@@ -17,6 +15,8 @@ pub enum AccountStatus {
     Inactive = 0,
     /// 已激活
     Active = 1,
+    /// 被管理员停用，不能登录
+    Suspended = 2,
 }
 
 /// 数据库完整表单
@@ -25,15 +25,33 @@ pub struct User {
     pub id: i32,
     /// VARCHAR(255)
     pub email: String,
-    /// VARCHAR(255), argon2 加密
-    pub password: String,
-    /// SMALLINT
-    pub admin_level: i16,
+    /// VARCHAR(255), argon2 加密 (从旧系统导入的可能是 Django hash), 仅通过外部身份登录的账户为 `None`
+    #[serde(skip_serializing)]
+    pub password: Option<String>,
     /// VARCHAR(255)
     pub username: String,
     /// 头像 s3_key, VARCHAR(36), uuid 转字符串
     pub avatar_id: Option<String>,
     pub status: AccountStatus,
+    /// 邮箱通过验证的时间，未经验证 (如管理员直接启用) 时为 `None`
+    pub email_verified_at: Option<DateTime<Utc>>,
+    /// 早于该时间签发的 token 全部失效
+    pub tokens_valid_after: Option<DateTime<Utc>>,
+    /// 连续登录失败次数
+    pub failed_login_count: i32,
+    /// 锁定截止时间
+    pub locked_until: Option<DateTime<Utc>>,
+    /// TOTP 密钥, base32 编码
+    #[serde(skip_serializing)]
+    pub totp_secret: Option<String>,
+    /// 是否已启用两步验证
+    pub totp_enabled: bool,
+    /// 最近一次使用的 TOTP 时间步
+    pub totp_last_step: Option<i64>,
+    /// 注册时使用的邀请码
+    pub invitation_id: Option<i32>,
+    /// 计划删除的时间
+    pub deletion_scheduled_at: Option<DateTime<Utc>>,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
 }
@@ -43,8 +61,22 @@ pub struct User {
 pub struct AuthUser {
     pub id: i32,
     pub email: String,
-    pub password: String,
+    pub password: Option<String>,
     pub status: AccountStatus,
+    pub failed_login_count: i32,
+    pub locked_until: Option<DateTime<Utc>>,
+    pub totp_enabled: bool,
+}
+
+/// 登录尝试记录
+#[derive(Clone, Debug, PartialEq, Eq, FromRow, Serialize)]
+pub struct LoginAttempt {
+    pub id: i32,
+    pub user_id: Option<i32>,
+    /// VARCHAR(45)
+    pub ip: Option<String>,
+    pub success: bool,
+    pub created_at: DateTime<Utc>,
 }
 
 /// 用户资料
@@ -54,13 +86,13 @@ pub struct AuthUser {
 pub struct UserProfile {
     pub id: i32,
     pub email: String,
-    pub admin_level: i16,
     pub username: String,
     #[sqlx(skip)]
     pub avatar: String,
     #[serde(skip_serializing)]
     pub avatar_id: Option<String>,
     pub status: AccountStatus,
+    pub totp_enabled: bool,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
 }
@@ -71,25 +103,201 @@ pub struct UserVerificationToken {
     pub user_id: i32,
     pub token: Uuid,
     pub expire: DateTime<Utc>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// 密码重置 token
+///
+/// 一次性使用
+#[derive(Clone, Debug, PartialEq, Eq, FromRow, Serialize)]
+pub struct PasswordResetToken {
+    pub user_id: i32,
+    pub token: Uuid,
+    pub expire: DateTime<Utc>,
+}
+
+/// 修改邮箱 token
+///
+/// 新邮箱确认后才会替换 `email`
+#[derive(Clone, Debug, PartialEq, Eq, FromRow, Serialize)]
+pub struct EmailChangeToken {
+    pub user_id: i32,
+    pub new_email: String,
+    pub token: Uuid,
+    pub expire: DateTime<Utc>,
+}
+
+/// 删除账户确认 token
+///
+/// 没有密码的账户通过邮件确认删除，一次性使用
+#[derive(Clone, Debug, PartialEq, Eq, FromRow, Serialize)]
+pub struct AccountDeletionToken {
+    pub user_id: i32,
+    pub token: Uuid,
+    pub expire: DateTime<Utc>,
 }
 
 impl User {
     /// 加密并修改密码
     ///
     /// attention: 不会保存到数据库
-    pub fn set_password(&mut self, password: &str) -> Result<(), &'static str> {
-        self.password = hash_password(password).map_err(|_| "Unable to hash password")?;
+    pub fn set_password(
+        &mut self,
+        config: &PasswordConfig,
+        password: &str,
+    ) -> Result<(), &'static str> {
+        self.password = Some(
+            config
+                .hash(password)
+                .map_err(|_| "Unable to hash password")?,
+        );
+        Ok(())
+    }
+
+    /// 校验密码
+    ///
+    /// 没有密码的账户总是返回 `Ok(false)`
+    pub fn verify_password(
+        &self,
+        config: &PasswordConfig,
+        password: &str,
+    ) -> Result<bool, &'static str> {
+        match &self.password {
+            Some(hash) => config.verify(hash, password),
+            None => Ok(false),
+        }
+    }
+
+    /// 计划在 `at` 删除账户
+    pub async fn schedule_deletion(
+        pool: &PgPool,
+        user_id: i32,
+        at: DateTime<Utc>,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query(r#"UPDATE "user" SET deletion_scheduled_at = $1 WHERE id = $2"#)
+            .bind(at)
+            .bind(user_id)
+            .execute(pool)
+            .await?;
+        Ok(())
+    }
+
+    /// 取消计划中的删除
+    ///
+    /// 返回 `true` 表示确实取消了删除
+    pub async fn cancel_deletion(pool: &PgPool, user_id: i32) -> Result<bool, sqlx::Error> {
+        let result = sqlx::query(
+            r#"UPDATE "user" SET deletion_scheduled_at = NULL WHERE id = $1 AND deletion_scheduled_at IS NOT NULL"#,
+        )
+        .bind(user_id)
+        .execute(pool)
+        .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// 从数据库删除用户及其全部关联数据
+    ///
+    /// attention: 不会删除头像图片
+    pub async fn delete(pool: &PgPool, user_id: i32) -> Result<(), sqlx::Error> {
+        let mut tx = pool.begin().await?;
+        sqlx::query(r#"DELETE FROM user_verification_token WHERE user_id = $1"#)
+            .bind(user_id)
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query(r#"DELETE FROM "user" WHERE id = $1"#)
+            .bind(user_id)
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await
+    }
+
+    /// 使用户此前签发的所有 token 失效
+    ///
+    /// 包括 access token、refresh token 与个人访问令牌，个人访问令牌会被删除
+    ///
+    /// attention: `tokens_valid_after` 精确到秒，同一秒内签发的 token 不受影响
+    pub async fn invalidate_tokens(pool: &PgPool, user_id: i32) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            r#"UPDATE "user" SET tokens_valid_after = date_trunc('second', CURRENT_TIMESTAMP) WHERE id = $1"#,
+        )
+        .bind(user_id)
+        .execute(pool)
+        .await?;
+        sqlx::query(
+            r#"UPDATE refresh_token SET revoked_at = CURRENT_TIMESTAMP WHERE user_id = $1 AND revoked_at IS NULL"#,
+        )
+        .bind(user_id)
+        .execute(pool)
+        .await?;
+        sqlx::query(r#"DELETE FROM personal_access_token WHERE user_id = $1"#)
+            .bind(user_id)
+            .execute(pool)
+            .await?;
         Ok(())
     }
 }
 
 impl AuthUser {
-    pub fn verify_password(&self, password: &str) -> Result<bool, &'static str> {
-        let parsed_hash =
-            PasswordHash::new(self.password.as_str()).map_err(|_| "Invalid password hash")?;
-        Ok(Argon2::default()
-            .verify_password(password.as_bytes(), &parsed_hash)
-            .is_ok())
+    /// 校验密码
+    ///
+    /// 没有密码的账户总是返回 `Ok(false)`
+    pub fn verify_password(
+        &self,
+        config: &PasswordConfig,
+        password: &str,
+    ) -> Result<bool, &'static str> {
+        match &self.password {
+            Some(hash) => config.verify(hash, password),
+            None => Ok(false),
+        }
+    }
+
+    /// 是否处于锁定状态
+    pub fn is_locked(&self) -> bool {
+        self.locked_until.is_some_and(|until| until > Utc::now())
+    }
+}
+
+impl LoginAttempt {
+    /// 记录一次登录尝试
+    pub async fn record(
+        pool: &PgPool,
+        user_id: Option<i32>,
+        ip: Option<&str>,
+        success: bool,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query(r#"INSERT INTO login_attempt (user_id, ip, success) VALUES ($1, $2, $3)"#)
+            .bind(user_id)
+            .bind(ip)
+            .bind(success)
+            .execute(pool)
+            .await?;
+        Ok(())
+    }
+
+    /// 用户的全部登录记录
+    pub async fn of_user(pool: &PgPool, user_id: i32) -> Result<Vec<Self>, sqlx::Error> {
+        sqlx::query_as::<_, Self>(
+            r#"SELECT * FROM login_attempt WHERE user_id = $1 ORDER BY created_at DESC"#,
+        )
+        .bind(user_id)
+        .fetch_all(pool)
+        .await
+    }
+
+    /// 统计 ip 在指定时间之后的失败次数
+    pub async fn failures_from_ip(
+        pool: &PgPool,
+        ip: &str,
+        since: DateTime<Utc>,
+    ) -> Result<i64, sqlx::Error> {
+        sqlx::query_scalar(
+            r#"SELECT COUNT(*) FROM login_attempt WHERE ip = $1 AND success = FALSE AND created_at > $2"#,
+        )
+        .bind(ip)
+        .bind(since)
+        .fetch_one(pool)
+        .await
     }
 }
 
@@ -98,11 +306,11 @@ impl UserProfile {
         Self {
             id: user.id,
             email: user.email,
-            admin_level: user.admin_level,
             username: user.username,
             avatar: String::new(),
             avatar_id: user.avatar_id,
             status: user.status,
+            totp_enabled: user.totp_enabled,
             created_at: user.created_at,
             updated_at: user.updated_at,
         }
@@ -127,27 +335,47 @@ impl UserProfile {
 
 impl UserVerificationToken {
     pub fn new(user_id: i32) -> UserVerificationToken {
+        let now = Utc::now();
         Self {
             user_id,
             token: Uuid::new_v4(),
-            expire: Utc::now()
+            expire: now
                 .checked_add_signed(chrono::Duration::days(3))
                 .expect("Invalid timestamp"),
+            created_at: now,
         }
     }
 
+    /// 保存 token
+    ///
+    /// 同一用户仅保留最新的一个 token
     pub async fn save(&self, pool: &PgPool) -> Result<(), sqlx::Error> {
+        sqlx::query(r#"DELETE FROM user_verification_token WHERE user_id = $1"#)
+            .bind(self.user_id)
+            .execute(pool)
+            .await?;
         sqlx::query(
-            r#"INSERT INTO "user_verification_token" (user_id, token, expire) VALUES ($1, $2, $3)"#,
+            r#"INSERT INTO "user_verification_token" (user_id, token, expire, created_at) VALUES ($1, $2, $3, $4)"#,
         )
         .bind(self.user_id)
-        .bind(&self.token)
-        .bind(&self.expire)
+        .bind(self.token)
+        .bind(self.expire)
+        .bind(self.created_at)
         .execute(pool)
         .await?;
         Ok(())
     }
 
+    /// 查询用户最新的 token
+    pub async fn latest_of_user(pool: &PgPool, user_id: i32) -> Result<Option<Self>, sqlx::Error> {
+        sqlx::query_as::<_, Self>(
+            r#"SELECT * FROM user_verification_token WHERE user_id = $1 ORDER BY created_at DESC LIMIT 1"#,
+        )
+        .bind(user_id)
+        .fetch_optional(pool)
+        .await
+    }
+
     pub async fn verify(pool: &PgPool, token: &Uuid) -> Result<Option<Self>, sqlx::Error> {
         let row =
             sqlx::query_as::<_, Self>(r#"SELECT * FROM user_verification_token WHERE token = $1"#)
@@ -175,12 +403,155 @@ impl UserVerificationToken {
     }
 }
 
-/// 加密密码原文
-///
-/// 使用 argon2 加密
-pub fn hash_password(password: &str) -> Result<String, argon2::password_hash::Error> {
-    let salt = SaltString::generate(&mut OsRng);
-    Ok(Argon2::default()
-        .hash_password(password.as_bytes(), &salt)?
-        .to_string())
+impl PasswordResetToken {
+    /// 有效期（分钟）
+    pub const EXPIRE_MINUTES: i64 = 60;
+
+    pub fn new(user_id: i32) -> PasswordResetToken {
+        Self {
+            user_id,
+            token: Uuid::new_v4(),
+            expire: Utc::now()
+                .checked_add_signed(chrono::Duration::minutes(Self::EXPIRE_MINUTES))
+                .expect("Invalid timestamp"),
+        }
+    }
+
+    /// 保存 token
+    ///
+    /// 同一用户仅保留最新的一个 token
+    pub async fn save(&self, pool: &PgPool) -> Result<(), sqlx::Error> {
+        sqlx::query(r#"DELETE FROM password_reset_token WHERE user_id = $1"#)
+            .bind(self.user_id)
+            .execute(pool)
+            .await?;
+        sqlx::query(
+            r#"INSERT INTO "password_reset_token" (user_id, token, expire) VALUES ($1, $2, $3)"#,
+        )
+        .bind(self.user_id)
+        .bind(self.token)
+        .bind(self.expire)
+        .execute(pool)
+        .await?;
+        Ok(())
+    }
+
+    /// 查询用户当前的 token
+    pub async fn of_user(pool: &PgPool, user_id: i32) -> Result<Option<Self>, sqlx::Error> {
+        sqlx::query_as::<_, Self>(r#"SELECT * FROM password_reset_token WHERE user_id = $1"#)
+            .bind(user_id)
+            .fetch_optional(pool)
+            .await
+    }
+
+    /// 创建时间，由过期时间推算
+    pub fn created_at(&self) -> DateTime<Utc> {
+        self.expire - chrono::Duration::minutes(Self::EXPIRE_MINUTES)
+    }
+
+    /// 校验并消耗 token
+    ///
+    /// 无论是否过期，token 都会被删除
+    pub async fn consume(pool: &PgPool, token: &Uuid) -> Result<Option<Self>, sqlx::Error> {
+        let row = sqlx::query_as::<_, Self>(
+            r#"DELETE FROM password_reset_token WHERE token = $1 RETURNING *"#,
+        )
+        .bind(token)
+        .fetch_optional(pool)
+        .await?;
+        Ok(row.filter(|token| token.expire >= Utc::now()))
+    }
+}
+
+impl EmailChangeToken {
+    pub fn new(user_id: i32, new_email: String) -> EmailChangeToken {
+        Self {
+            user_id,
+            new_email,
+            token: Uuid::new_v4(),
+            expire: Utc::now()
+                .checked_add_signed(chrono::Duration::days(1))
+                .expect("Invalid timestamp"),
+        }
+    }
+
+    /// 保存 token
+    ///
+    /// 同一用户仅保留最新的一个 token
+    pub async fn save(&self, pool: &PgPool) -> Result<(), sqlx::Error> {
+        sqlx::query(r#"DELETE FROM email_change_token WHERE user_id = $1"#)
+            .bind(self.user_id)
+            .execute(pool)
+            .await?;
+        sqlx::query(
+            r#"INSERT INTO "email_change_token" (user_id, new_email, token, expire) VALUES ($1, $2, $3, $4)"#,
+        )
+        .bind(self.user_id)
+        .bind(&self.new_email)
+        .bind(self.token)
+        .bind(self.expire)
+        .execute(pool)
+        .await?;
+        Ok(())
+    }
+
+    /// 校验并消耗 token
+    ///
+    /// 无论是否过期，token 都会被删除
+    pub async fn consume(pool: &PgPool, token: &Uuid) -> Result<Option<Self>, sqlx::Error> {
+        let row = sqlx::query_as::<_, Self>(
+            r#"DELETE FROM email_change_token WHERE token = $1 RETURNING *"#,
+        )
+        .bind(token)
+        .fetch_optional(pool)
+        .await?;
+        Ok(row.filter(|token| token.expire >= Utc::now()))
+    }
+}
+
+impl AccountDeletionToken {
+    /// 有效期（分钟）
+    pub const EXPIRE_MINUTES: i64 = 60;
+
+    pub fn new(user_id: i32) -> AccountDeletionToken {
+        Self {
+            user_id,
+            token: Uuid::new_v4(),
+            expire: Utc::now()
+                .checked_add_signed(chrono::Duration::minutes(Self::EXPIRE_MINUTES))
+                .expect("Invalid timestamp"),
+        }
+    }
+
+    /// 保存 token
+    ///
+    /// 同一用户仅保留最新的一个 token
+    pub async fn save(&self, pool: &PgPool) -> Result<(), sqlx::Error> {
+        sqlx::query(r#"DELETE FROM account_deletion_token WHERE user_id = $1"#)
+            .bind(self.user_id)
+            .execute(pool)
+            .await?;
+        sqlx::query(
+            r#"INSERT INTO "account_deletion_token" (user_id, token, expire) VALUES ($1, $2, $3)"#,
+        )
+        .bind(self.user_id)
+        .bind(self.token)
+        .bind(self.expire)
+        .execute(pool)
+        .await?;
+        Ok(())
+    }
+
+    /// 校验并消耗 token
+    ///
+    /// 无论是否过期，token 都会被删除
+    pub async fn consume(pool: &PgPool, token: &Uuid) -> Result<Option<Self>, sqlx::Error> {
+        let row = sqlx::query_as::<_, Self>(
+            r#"DELETE FROM account_deletion_token WHERE token = $1 RETURNING *"#,
+        )
+        .bind(token)
+        .fetch_optional(pool)
+        .await?;
+        Ok(row.filter(|token| token.expire >= Utc::now()))
+    }
 }
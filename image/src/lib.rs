@@ -18,7 +18,7 @@ pub mod utils;
 /// ```
 /// # use image_service::S3Client;
 /// # use image_service::storage::create_client;
-/// # async fn main () {
+/// # async fn run() {
 /// let internal_endpoint = std::env::var("MINIO_ENDPOINT").expect("MINIO_ENDPOINT must be set");
 /// let external_endpoint =
 ///     std::env::var("MINIO_EXTERNAL_ENDPOINT").unwrap_or(internal_endpoint.clone());
@@ -41,6 +41,7 @@ pub mod utils;
 /// };
 /// # }
 /// ```
+#[derive(Clone)]
 pub struct S3Client {
     /// 服务器内部连接
     pub internal: Client,
@@ -72,6 +72,15 @@ impl ImageService {
         }
     }
 
+    #[doc(hidden)]
+    pub fn image_extension(&self) -> &'static str {
+        self.image_format
+            .unwrap_or(ImageFormat::Jpeg)
+            .extensions_str()
+            .first()
+            .unwrap_or(&"bin")
+    }
+
     /// 处理图片
     ///
     /// 将传入的图片按照要求处理
@@ -81,16 +90,13 @@ impl ImageService {
                 width,
                 height,
                 self.image_filter
-                    .unwrap_or_else(|| image::imageops::FilterType::Lanczos3),
+                    .unwrap_or(image::imageops::FilterType::Lanczos3),
             ),
             None => image,
         };
         let mut bytes = Cursor::new(Vec::new());
         resized
-            .write_to(
-                &mut bytes,
-                self.image_format.unwrap_or_else(|| ImageFormat::Jpeg),
-            )
+            .write_to(&mut bytes, self.image_format.unwrap_or(ImageFormat::Jpeg))
             .map_err(|_| ProcessError("Cannot write image to bytes"))?;
         Ok(bytes.into_inner())
     }
@@ -103,15 +109,16 @@ impl ImageService {
     /// # use image_service::service::ImageService;
     /// # use image_service::storage::create_client;
     /// use image_service::utils::open_image;
-    /// # async fn main() {
+    /// # async fn run() -> Result<(), &'static str> {
     ///
-    /// # let s3_client = create_client().await;
+    /// # let s3_client = create_client("", "", "", "").await;
     /// let service = ImageService::new("test", Some(Jpeg), Some((128, 128)), None);
     /// let image = open_image("/path/to/image".as_ref());
     ///
     /// service.upload_image(&s3_client, image)
     ///     .await
     ///     .map_err(|_| "fail to upload image")?;
+    /// # Ok(())
     /// # }
     /// ```
     pub async fn upload_image(
@@ -157,6 +164,50 @@ impl ImageService {
         Ok(presigned_url.uri().to_string())
     }
 
+    /// 批量生成预签名 url
+    ///
+    /// 所有 url 使用同一个签名配置并发生成，返回顺序与 `s3_keys` 相同
+    pub async fn get_presigned_urls(
+        &self,
+        s3_client: &Client,
+        s3_keys: impl IntoIterator<Item = impl Into<String>>,
+    ) -> Result<Vec<String>, ImageError> {
+        let config =
+            aws_sdk_s3::presigning::PresigningConfig::expires_in(Duration::from_secs(3600))
+                .map_err(|_| S3Error("Cannot build PresigningConfig"))?;
+        futures::future::try_join_all(s3_keys.into_iter().map(|s3_key| {
+            let request = s3_client
+                .get_object()
+                .bucket(&self.bucket_name)
+                .key(s3_key)
+                .presigned(config.clone());
+            async move {
+                request
+                    .await
+                    .map(|presigned| presigned.uri().to_string())
+                    .map_err(|_| S3Error("Cannot get image url from s3"))
+            }
+        }))
+        .await
+    }
+
+    /// 下载图片原始内容
+    pub async fn get_image(&self, s3_client: &Client, key: &String) -> Result<Vec<u8>, ImageError> {
+        let object = s3_client
+            .get_object()
+            .bucket(&self.bucket_name)
+            .key(key)
+            .send()
+            .await
+            .map_err(|_| S3Error("Cannot get image from s3"))?;
+        let bytes = object
+            .body
+            .collect()
+            .await
+            .map_err(|_| S3Error("Cannot read image from s3"))?;
+        Ok(bytes.into_bytes().to_vec())
+    }
+
     /// 从 s3 储存桶删除图片
     pub async fn delete_image(&self, s3_client: &Client, key: &String) -> Result<(), ImageError> {
         s3_client
@@ -9,23 +9,24 @@ use aws_sdk_s3::{Client, Config};
 ///
 /// ```
 /// # use image_service::storage::create_client;
-/// #
+/// # async fn run() {
 /// let endpoint = "http://endpoint:9000".to_string();
 /// let region = "your-region".to_string();
 /// let access_key = "your-access-key".to_string();
 /// let secret_key = "your-secret-key".to_string();
 ///
-/// let client = create_client(&endpoint, &region, &access_key, &secret_key)
+/// let client = create_client(&endpoint, &region, &access_key, &secret_key).await;
+/// # }
 /// ```
 pub async fn create_client(
-    endpoint: &String,
-    region: &String,
-    access_key: &String,
-    secret_key: &String,
+    endpoint: &str,
+    region: &str,
+    access_key: &str,
+    secret_key: &str,
 ) -> Client {
     let config = Config::builder()
         .endpoint_url(endpoint)
-        .region(Region::new(region.clone()))
+        .region(Region::new(region.to_string()))
         .credentials_provider(Credentials::new(
             access_key, secret_key, None, None, "Static",
         ))
@@ -50,7 +51,13 @@ pub async fn ensure_bucket_exists(
     client: &Client,
     bucket_name: &String,
 ) -> Result<(), SdkError<CreateBucketError, HttpResponse>> {
-    if let Err(_) = client.head_bucket().bucket(bucket_name).send().await {
+    if client
+        .head_bucket()
+        .bucket(bucket_name)
+        .send()
+        .await
+        .is_err()
+    {
         // println!("Bucket {} not found", bucket_name);
         // println!("{}", e);
         client
@@ -0,0 +1,7 @@
+DROP TABLE user_role;
+
+DROP TABLE role_permission;
+
+DROP TABLE permission;
+
+DROP TABLE role;
@@ -0,0 +1,76 @@
+CREATE TABLE role
+(
+    id          SERIAL PRIMARY KEY,
+    name        VARCHAR(64) UNIQUE NOT NULL,
+    description VARCHAR(255)             DEFAULT NULL,
+    created_at  TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP,
+    updated_at  TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE TRIGGER role_upd_trigger
+    BEFORE UPDATE
+    ON role
+    FOR EACH ROW
+EXECUTE PROCEDURE upd_timestamp();
+
+CREATE TABLE permission
+(
+    id          SERIAL PRIMARY KEY,
+    code        VARCHAR(64) UNIQUE NOT NULL,
+    description VARCHAR(255) DEFAULT NULL
+);
+
+CREATE TABLE role_permission
+(
+    role_id       INTEGER NOT NULL REFERENCES role (id) ON DELETE CASCADE,
+    permission_id INTEGER NOT NULL REFERENCES permission (id) ON DELETE CASCADE,
+    PRIMARY KEY (role_id, permission_id)
+);
+
+CREATE TABLE user_role
+(
+    user_id    INTEGER NOT NULL REFERENCES "user" (id) ON DELETE CASCADE,
+    role_id    INTEGER NOT NULL REFERENCES role (id) ON DELETE CASCADE,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (user_id, role_id)
+);
+
+-- 内置角色与权限
+INSERT INTO role (name, description)
+VALUES ('admin', '管理员'),
+       ('member', '普通成员');
+
+INSERT INTO permission (code, description)
+VALUES ('person.read', '查看同学录'),
+       ('person.write', '新增、修改同学录'),
+       ('person.delete', '删除同学录'),
+       ('user.manage', '管理用户'),
+       ('role.manage', '管理角色与权限');
+
+INSERT INTO role_permission (role_id, permission_id)
+SELECT role.id, permission.id
+FROM role,
+     permission
+WHERE role.name = 'admin';
+
+INSERT INTO role_permission (role_id, permission_id)
+SELECT role.id, permission.id
+FROM role,
+     permission
+WHERE role.name = 'member'
+  AND permission.code = 'person.read';
+
+-- 已存在的用户默认为普通成员
+INSERT INTO user_role (user_id, role_id)
+SELECT "user".id, role.id
+FROM "user",
+     role
+WHERE role.name = 'member';
+
+-- 已有的管理员同时授予 admin 角色，避免迁移后失去管理权限
+INSERT INTO user_role (user_id, role_id)
+SELECT "user".id, role.id
+FROM "user",
+     role
+WHERE role.name = 'admin'
+  AND "user".admin_level > 0;
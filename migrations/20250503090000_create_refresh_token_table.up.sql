@@ -0,0 +1,15 @@
+CREATE TABLE refresh_token
+(
+    id         SERIAL PRIMARY KEY,
+    user_id    INTEGER     NOT NULL REFERENCES "user" (id) ON DELETE CASCADE,
+    -- 同一次登录轮换出的所有 refresh token 属于同一个 family
+    family     UUID        NOT NULL,
+    -- sha256(token) 的十六进制
+    token_hash VARCHAR(64) NOT NULL UNIQUE,
+    expire     TIMESTAMP WITH TIME ZONE NOT NULL,
+    used_at    TIMESTAMP WITH TIME ZONE DEFAULT NULL,
+    revoked_at TIMESTAMP WITH TIME ZONE DEFAULT NULL,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX refresh_token_family_idx ON refresh_token (family);
@@ -0,0 +1,4 @@
+ALTER TABLE "user"
+    DROP COLUMN tokens_valid_after;
+
+DROP TABLE revoked_token;
@@ -0,0 +1,11 @@
+CREATE TABLE revoked_token
+(
+    jti     UUID PRIMARY KEY,
+    user_id INTEGER                  NOT NULL REFERENCES "user" (id) ON DELETE CASCADE,
+    -- 原 token 的过期时间，过期后即可清理
+    expire  TIMESTAMP WITH TIME ZONE NOT NULL
+);
+
+-- 早于该时间签发的 token 全部失效
+ALTER TABLE "user"
+    ADD COLUMN tokens_valid_after TIMESTAMP WITH TIME ZONE DEFAULT NULL;
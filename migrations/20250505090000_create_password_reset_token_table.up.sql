@@ -0,0 +1,6 @@
+CREATE TABLE "password_reset_token"
+(
+    user_id INTEGER REFERENCES "user" (id) ON DELETE CASCADE,
+    token   UUID UNIQUE,
+    expire  TIMESTAMP WITH TIME ZONE
+);
@@ -0,0 +1,7 @@
+CREATE TABLE "email_change_token"
+(
+    user_id   INTEGER REFERENCES "user" (id) ON DELETE CASCADE,
+    new_email VARCHAR(255) NOT NULL,
+    token     UUID UNIQUE,
+    expire    TIMESTAMP WITH TIME ZONE
+);
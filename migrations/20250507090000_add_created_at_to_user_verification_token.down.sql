@@ -0,0 +1,2 @@
+ALTER TABLE "user_verification_token"
+    DROP COLUMN created_at;
@@ -0,0 +1,2 @@
+ALTER TABLE "user_verification_token"
+    ADD COLUMN created_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP;
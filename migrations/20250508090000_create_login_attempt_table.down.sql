@@ -0,0 +1,5 @@
+DROP TABLE login_attempt;
+
+ALTER TABLE "user"
+    DROP COLUMN failed_login_count,
+    DROP COLUMN locked_until;
@@ -0,0 +1,14 @@
+ALTER TABLE "user"
+    ADD COLUMN failed_login_count INTEGER                  NOT NULL DEFAULT 0,
+    ADD COLUMN locked_until       TIMESTAMP WITH TIME ZONE DEFAULT NULL;
+
+CREATE TABLE login_attempt
+(
+    id         SERIAL PRIMARY KEY,
+    user_id    INTEGER     DEFAULT NULL REFERENCES "user" (id) ON DELETE CASCADE,
+    ip         VARCHAR(45) DEFAULT NULL,
+    success    BOOLEAN NOT NULL,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX login_attempt_ip_idx ON login_attempt (ip, created_at);
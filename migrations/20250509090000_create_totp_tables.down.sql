@@ -0,0 +1,8 @@
+DROP TABLE mfa_challenge;
+
+DROP TABLE totp_recovery_code;
+
+ALTER TABLE "user"
+    DROP COLUMN totp_secret,
+    DROP COLUMN totp_enabled,
+    DROP COLUMN totp_last_step;
@@ -0,0 +1,25 @@
+ALTER TABLE "user"
+    ADD COLUMN totp_secret    VARCHAR(64) DEFAULT NULL,
+    ADD COLUMN totp_enabled   BOOLEAN     NOT NULL DEFAULT FALSE,
+    -- 最近一次使用的 TOTP 时间步，防止同一验证码被重复使用
+    ADD COLUMN totp_last_step BIGINT      DEFAULT NULL;
+
+CREATE TABLE totp_recovery_code
+(
+    id         SERIAL PRIMARY KEY,
+    user_id    INTEGER     NOT NULL REFERENCES "user" (id) ON DELETE CASCADE,
+    -- sha256(code) 的十六进制
+    code_hash  VARCHAR(64) NOT NULL,
+    used_at    TIMESTAMP WITH TIME ZONE DEFAULT NULL,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP
+);
+
+-- 登录第二步的临时凭证
+CREATE TABLE mfa_challenge
+(
+    id         SERIAL PRIMARY KEY,
+    user_id    INTEGER     NOT NULL REFERENCES "user" (id) ON DELETE CASCADE,
+    token_hash VARCHAR(64) NOT NULL UNIQUE,
+    attempts   INTEGER     NOT NULL     DEFAULT 0,
+    expire     TIMESTAMP WITH TIME ZONE NOT NULL
+);
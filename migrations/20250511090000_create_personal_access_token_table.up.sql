@@ -0,0 +1,18 @@
+CREATE TABLE personal_access_token
+(
+    -- 同时作为 jti 参与吊销校验
+    id           UUID PRIMARY KEY,
+    user_id      INTEGER      NOT NULL REFERENCES "user" (id) ON DELETE CASCADE,
+    name         VARCHAR(64)  NOT NULL,
+    -- sha256(token) 的十六进制
+    token_hash   VARCHAR(64)  NOT NULL UNIQUE,
+    -- token 原文前缀，便于用户辨认
+    token_prefix VARCHAR(16)  NOT NULL,
+    -- 权限 code 列表，与 permission.code 对应
+    scopes       VARCHAR(64)[] NOT NULL     DEFAULT '{}',
+    expire       TIMESTAMP WITH TIME ZONE DEFAULT NULL,
+    last_used_at TIMESTAMP WITH TIME ZONE DEFAULT NULL,
+    created_at   TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX personal_access_token_user_idx ON personal_access_token (user_id);
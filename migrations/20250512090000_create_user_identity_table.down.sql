@@ -0,0 +1,2 @@
+DROP TABLE oidc_auth_state;
+DROP TABLE user_identity;
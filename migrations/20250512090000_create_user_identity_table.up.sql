@@ -0,0 +1,26 @@
+-- 外部身份 (OIDC) 与用户的关联
+CREATE TABLE user_identity
+(
+    id         SERIAL PRIMARY KEY,
+    user_id    INTEGER      NOT NULL REFERENCES "user" (id) ON DELETE CASCADE,
+    -- 身份提供方名称，与配置中的名称对应
+    provider   VARCHAR(32)  NOT NULL,
+    -- id token 中的 sub
+    subject    VARCHAR(255) NOT NULL,
+    email      VARCHAR(255) DEFAULT NULL,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP,
+    UNIQUE (provider, subject)
+);
+
+CREATE INDEX user_identity_user_idx ON user_identity (user_id);
+
+-- 进行中的 OIDC 授权请求
+CREATE TABLE oidc_auth_state
+(
+    -- sha256(state) 的十六进制
+    state_hash    VARCHAR(64) PRIMARY KEY,
+    provider      VARCHAR(32)  NOT NULL,
+    pkce_verifier VARCHAR(128) NOT NULL,
+    nonce         VARCHAR(128) NOT NULL,
+    expire        TIMESTAMP WITH TIME ZONE NOT NULL
+);
@@ -0,0 +1,4 @@
+ALTER TABLE "user"
+    DROP COLUMN invitation_id;
+
+DROP TABLE invitation_code;
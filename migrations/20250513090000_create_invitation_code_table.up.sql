@@ -0,0 +1,17 @@
+CREATE TABLE invitation_code
+(
+    id         SERIAL PRIMARY KEY,
+    code       VARCHAR(32) UNIQUE       NOT NULL,
+    -- 届别，如 "2015届"
+    cohort     VARCHAR(64)              NOT NULL,
+    -- 可使用次数，1 为一次性邀请码
+    max_uses   INTEGER                  NOT NULL DEFAULT 1,
+    use_count  INTEGER                  NOT NULL DEFAULT 0,
+    expire     TIMESTAMP WITH TIME ZONE NOT NULL,
+    created_by INTEGER                  REFERENCES "user" (id) ON DELETE SET NULL,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP
+);
+
+-- 注册时使用的邀请码
+ALTER TABLE "user"
+    ADD COLUMN invitation_id INTEGER DEFAULT NULL REFERENCES invitation_code (id) ON DELETE SET NULL;
@@ -0,0 +1,17 @@
+-- 登录会话，一次登录对应一个 refresh token family
+CREATE TABLE user_session
+(
+    -- 与 refresh_token.family 相同
+    id            UUID PRIMARY KEY,
+    user_id       INTEGER NOT NULL REFERENCES "user" (id) ON DELETE CASCADE,
+    ip            VARCHAR(45)              DEFAULT NULL,
+    user_agent    VARCHAR(512)             DEFAULT NULL,
+    -- 最近签发的 access token
+    access_jti    UUID                     DEFAULT NULL,
+    access_expire TIMESTAMP WITH TIME ZONE DEFAULT NULL,
+    created_at    TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP,
+    last_seen_at  TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP,
+    revoked_at    TIMESTAMP WITH TIME ZONE DEFAULT NULL
+);
+
+CREATE INDEX user_session_user_idx ON user_session (user_id);
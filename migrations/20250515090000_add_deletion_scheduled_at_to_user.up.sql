@@ -0,0 +1,3 @@
+-- 账户计划删除的时间，宽限期内重新登录即取消
+ALTER TABLE "user"
+    ADD COLUMN deletion_scheduled_at TIMESTAMP WITH TIME ZONE DEFAULT NULL;
@@ -0,0 +1,16 @@
+-- 管理操作审计日志
+CREATE TABLE admin_audit_log
+(
+    id             SERIAL PRIMARY KEY,
+    -- 操作者
+    admin_id       INTEGER     DEFAULT NULL REFERENCES "user" (id) ON DELETE SET NULL,
+    -- 操作，如 user.status
+    action         VARCHAR(64) NOT NULL,
+    -- 被操作的用户
+    target_user_id INTEGER     DEFAULT NULL REFERENCES "user" (id) ON DELETE SET NULL,
+    detail         JSONB       NOT NULL     DEFAULT '{}',
+    ip             VARCHAR(45)              DEFAULT NULL,
+    created_at     TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX admin_audit_log_target_idx ON admin_audit_log (target_user_id);
@@ -0,0 +1,7 @@
+DROP INDEX IF EXISTS person_name_initials_trgm_idx;
+DROP INDEX IF EXISTS person_name_pinyin_trgm_idx;
+DROP INDEX IF EXISTS person_name_trgm_idx;
+
+ALTER TABLE person
+    DROP COLUMN name_initials,
+    DROP COLUMN name_pinyin;
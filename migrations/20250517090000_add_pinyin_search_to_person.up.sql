@@ -0,0 +1,10 @@
+CREATE EXTENSION IF NOT EXISTS pg_trgm;
+
+-- 由应用在写入 name 时生成
+ALTER TABLE person
+    ADD COLUMN name_pinyin   VARCHAR(255) NOT NULL DEFAULT '',
+    ADD COLUMN name_initials VARCHAR(64)  NOT NULL DEFAULT '';
+
+CREATE INDEX person_name_trgm_idx ON person USING GIN (name gin_trgm_ops);
+CREATE INDEX person_name_pinyin_trgm_idx ON person USING GIN (name_pinyin gin_trgm_ops);
+CREATE INDEX person_name_initials_trgm_idx ON person USING GIN (name_initials gin_trgm_ops);
@@ -0,0 +1,4 @@
+DROP TABLE person_claim;
+
+ALTER TABLE person
+    DROP COLUMN user_id;
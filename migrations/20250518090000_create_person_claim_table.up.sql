@@ -0,0 +1,20 @@
+-- 认领该人员的账户，一个账户只能关联一个人员
+ALTER TABLE person
+    ADD COLUMN user_id INTEGER UNIQUE DEFAULT NULL REFERENCES "user" (id) ON DELETE SET NULL;
+
+CREATE TABLE person_claim
+(
+    id          SERIAL PRIMARY KEY,
+    person_id   INTEGER                  NOT NULL REFERENCES person (id) ON DELETE CASCADE,
+    user_id     INTEGER                  NOT NULL REFERENCES "user" (id) ON DELETE CASCADE,
+    -- 0 待审核, 1 已通过, 2 已拒绝
+    status      SMALLINT                 NOT NULL DEFAULT 0,
+    -- 通过方式: email (邮箱匹配) / admin (管理员审核)
+    method      VARCHAR(16)              DEFAULT NULL,
+    reviewed_by INTEGER                  REFERENCES "user" (id) ON DELETE SET NULL,
+    reviewed_at TIMESTAMP WITH TIME ZONE DEFAULT NULL,
+    created_at  TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP
+);
+
+-- 同一账户对同一人员只能有一个待审核的认领
+CREATE UNIQUE INDEX person_claim_pending_idx ON person_claim (person_id, user_id) WHERE status = 0;
@@ -0,0 +1,5 @@
+DROP TABLE class_member;
+DROP TYPE class_role;
+DROP TABLE school_class;
+DROP TABLE cohort;
+DROP TABLE school;
@@ -0,0 +1,61 @@
+CREATE TABLE school
+(
+    id         SERIAL PRIMARY KEY,
+    name       VARCHAR(128) UNIQUE      NOT NULL,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE TRIGGER school_upd_trigger
+    BEFORE UPDATE
+    ON school
+    FOR EACH ROW
+EXECUTE PROCEDURE upd_timestamp();
+
+-- 届，按毕业年份区分
+CREATE TABLE cohort
+(
+    id              SERIAL PRIMARY KEY,
+    school_id       INTEGER                  NOT NULL REFERENCES school (id) ON DELETE CASCADE,
+    graduation_year SMALLINT                 NOT NULL,
+    -- 展示名称，如 "2015届"
+    name            VARCHAR(64)              NOT NULL,
+    created_at      TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP,
+    updated_at      TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP,
+    UNIQUE (school_id, graduation_year)
+);
+
+CREATE TRIGGER cohort_upd_trigger
+    BEFORE UPDATE
+    ON cohort
+    FOR EACH ROW
+EXECUTE PROCEDURE upd_timestamp();
+
+CREATE TABLE school_class
+(
+    id         SERIAL PRIMARY KEY,
+    cohort_id  INTEGER                  NOT NULL REFERENCES cohort (id) ON DELETE CASCADE,
+    name       VARCHAR(64)              NOT NULL,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP,
+    UNIQUE (cohort_id, name)
+);
+
+CREATE TRIGGER school_class_upd_trigger
+    BEFORE UPDATE
+    ON school_class
+    FOR EACH ROW
+EXECUTE PROCEDURE upd_timestamp();
+
+CREATE TYPE class_role AS ENUM ('student', 'head_teacher', 'monitor');
+
+CREATE TABLE class_member
+(
+    class_id   INTEGER                  NOT NULL REFERENCES school_class (id) ON DELETE CASCADE,
+    person_id  INTEGER                  NOT NULL REFERENCES person (id) ON DELETE CASCADE,
+    role       class_role               NOT NULL DEFAULT 'student',
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (class_id, person_id)
+);
+
+CREATE INDEX class_member_person_idx ON class_member (person_id);
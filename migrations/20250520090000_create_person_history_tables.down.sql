@@ -0,0 +1,2 @@
+DROP TABLE person_employment;
+DROP TABLE person_education;
@@ -0,0 +1,55 @@
+-- 教育经历
+CREATE TABLE person_education
+(
+    id          SERIAL PRIMARY KEY,
+    person_id   INTEGER                  NOT NULL REFERENCES person (id) ON DELETE CASCADE,
+    -- 学校 / 机构
+    institution VARCHAR(128)             NOT NULL,
+    -- 学位，如 "学士"
+    degree      VARCHAR(64),
+    -- 专业
+    major       VARCHAR(128),
+    start_date  DATE,
+    -- 为空表示在读
+    end_date    DATE,
+    created_at  TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP,
+    updated_at  TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP,
+    CHECK (end_date IS NULL OR start_date IS NULL OR start_date <= end_date)
+);
+
+CREATE TRIGGER person_education_upd_trigger
+    BEFORE UPDATE
+    ON person_education
+    FOR EACH ROW
+EXECUTE PROCEDURE upd_timestamp();
+
+CREATE INDEX person_education_person_idx ON person_education (person_id);
+CREATE INDEX person_education_institution_trgm_idx ON person_education USING GIN (institution gin_trgm_ops);
+
+-- 工作经历
+CREATE TABLE person_employment
+(
+    id           SERIAL PRIMARY KEY,
+    person_id    INTEGER                  NOT NULL REFERENCES person (id) ON DELETE CASCADE,
+    -- 单位
+    organization VARCHAR(128)             NOT NULL,
+    -- 职位
+    title        VARCHAR(128),
+    city         VARCHAR(64),
+    start_date   DATE,
+    -- 为空表示在职
+    end_date     DATE,
+    created_at   TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP,
+    updated_at   TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP,
+    CHECK (end_date IS NULL OR start_date IS NULL OR start_date <= end_date)
+);
+
+CREATE TRIGGER person_employment_upd_trigger
+    BEFORE UPDATE
+    ON person_employment
+    FOR EACH ROW
+EXECUTE PROCEDURE upd_timestamp();
+
+CREATE INDEX person_employment_person_idx ON person_employment (person_id);
+CREATE INDEX person_employment_organization_trgm_idx ON person_employment USING GIN (organization gin_trgm_ops);
+CREATE INDEX person_employment_city_trgm_idx ON person_employment USING GIN (city gin_trgm_ops);
@@ -0,0 +1,9 @@
+ALTER TABLE person
+    DROP COLUMN phone_visibility,
+    DROP COLUMN email_visibility,
+    DROP COLUMN qq_visibility,
+    DROP COLUMN wechat_visibility,
+    DROP COLUMN birthday_visibility,
+    DROP COLUMN birthday_month_day_only;
+
+DROP TYPE field_visibility;
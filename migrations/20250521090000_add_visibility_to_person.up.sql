@@ -0,0 +1,12 @@
+-- 字段可见性，依次放宽：hidden < admins < classmates < logged_in < public
+CREATE TYPE field_visibility AS ENUM ('public', 'logged_in', 'classmates', 'admins', 'hidden');
+
+-- 默认所有登录用户可见，与之前的行为一致
+ALTER TABLE person
+    ADD COLUMN phone_visibility        field_visibility NOT NULL DEFAULT 'logged_in',
+    ADD COLUMN email_visibility        field_visibility NOT NULL DEFAULT 'logged_in',
+    ADD COLUMN qq_visibility           field_visibility NOT NULL DEFAULT 'logged_in',
+    ADD COLUMN wechat_visibility       field_visibility NOT NULL DEFAULT 'logged_in',
+    ADD COLUMN birthday_visibility     field_visibility NOT NULL DEFAULT 'logged_in',
+    -- 生日只显示月日
+    ADD COLUMN birthday_month_day_only BOOLEAN          NOT NULL DEFAULT FALSE;
@@ -0,0 +1,11 @@
+ALTER TABLE invitation_code
+    ADD COLUMN cohort VARCHAR(64) NOT NULL DEFAULT '';
+
+UPDATE invitation_code
+SET cohort = cohort.name
+FROM cohort
+WHERE cohort.id = invitation_code.cohort_id;
+
+ALTER TABLE invitation_code
+    ALTER COLUMN cohort DROP DEFAULT,
+    DROP COLUMN cohort_id;
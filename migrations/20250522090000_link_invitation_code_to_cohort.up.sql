@@ -0,0 +1,13 @@
+-- 邀请码关联到届 (cohort 表)，取代原来的自由文本
+ALTER TABLE invitation_code
+    ADD COLUMN cohort_id INTEGER DEFAULT NULL REFERENCES cohort (id) ON DELETE CASCADE;
+
+-- 已有邀请码按名称匹配唯一的届，匹配不到的保留为 NULL
+UPDATE invitation_code
+SET cohort_id = cohort.id
+FROM cohort
+WHERE cohort.name = invitation_code.cohort
+  AND (SELECT COUNT(*) FROM cohort c WHERE c.name = invitation_code.cohort) = 1;
+
+ALTER TABLE invitation_code
+    DROP COLUMN cohort;
@@ -0,0 +1,4 @@
+-- 邮箱通过激活链接、邮箱变更确认或受信任的身份提供方验证的时间
+-- 已有账户无法区分是否由管理员直接启用，保留为 NULL
+ALTER TABLE "user"
+    ADD COLUMN email_verified_at TIMESTAMP WITH TIME ZONE DEFAULT NULL;
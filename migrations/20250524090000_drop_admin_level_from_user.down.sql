@@ -0,0 +1,10 @@
+ALTER TABLE "user"
+    ADD COLUMN admin_level SMALLINT DEFAULT 0;
+
+-- 拥有 admin 角色的用户恢复为管理员
+UPDATE "user"
+SET admin_level = 1
+FROM user_role
+         JOIN role ON role.id = user_role.role_id
+WHERE user_role.user_id = "user".id
+  AND role.name = 'admin';
@@ -0,0 +1,3 @@
+-- 权限以 user_role 为准，admin_level 已在 RBAC 迁移时转换为 admin 角色
+ALTER TABLE "user"
+    DROP COLUMN admin_level;
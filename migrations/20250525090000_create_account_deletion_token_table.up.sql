@@ -0,0 +1,7 @@
+-- 没有密码的账户通过邮件确认删除
+CREATE TABLE "account_deletion_token"
+(
+    user_id INTEGER REFERENCES "user" (id) ON DELETE CASCADE,
+    token   UUID UNIQUE,
+    expire  TIMESTAMP WITH TIME ZONE
+);
@@ -0,0 +1,137 @@
+//! 从 CSV / XLSX 批量导入人员，规则同 `POST /person/import`
+//!
+//! ```shell
+//! cargo run -p person --bin import_persons -- persons.xlsx [--photos photos.zip] [--dry-run]
+//! ```
+//!
+//! 数据库和 MinIO 的环境变量与服务端相同
+use image_service::storage::create_client;
+use image_service::{ImageServices, S3Client};
+use person::import::{import_persons, read_table, PhotoArchive};
+use sqlx::postgres::{PgConnectOptions, PgPoolOptions};
+use std::process::ExitCode;
+
+const USAGE: &str =
+    "Usage: import_persons <file.csv|file.xlsx> [--photos <photos.zip>] [--dry-run]";
+
+fn env(key: &str) -> String {
+    std::env::var(key).unwrap_or_else(|_| panic!("{} must be set", key))
+}
+
+#[rocket::main]
+async fn main() -> ExitCode {
+    dotenvy::dotenv().ok();
+    let mut file = None;
+    let mut photos = None;
+    let mut dry_run = false;
+    let mut args = std::env::args().skip(1).collect::<Vec<_>>().into_iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--dry-run" => dry_run = true,
+            "--photos" => photos = args.next(),
+            "-h" | "--help" => {
+                println!("{}", USAGE);
+                return ExitCode::SUCCESS;
+            }
+            _ if file.is_none() => file = Some(arg),
+            _ => {
+                eprintln!("{}", USAGE);
+                return ExitCode::FAILURE;
+            }
+        }
+    }
+    let Some(file) = file else {
+        eprintln!("{}", USAGE);
+        return ExitCode::FAILURE;
+    };
+
+    let table = match std::fs::read(&file)
+        .map_err(|e| format!("Cannot read {}: {}", file, e))
+        .and_then(|data| read_table(&data))
+    {
+        Ok(table) => table,
+        Err(e) => {
+            eprintln!("{}", e);
+            return ExitCode::FAILURE;
+        }
+    };
+    let photos = match photos.map(|path| {
+        std::fs::read(&path)
+            .map_err(|e| format!("Cannot read {}: {}", path, e))
+            .and_then(|data| PhotoArchive::read(&data))
+    }) {
+        Some(Ok(photos)) => Some(photos),
+        Some(Err(e)) => {
+            eprintln!("{}", e);
+            return ExitCode::FAILURE;
+        }
+        None => None,
+    };
+
+    let pool = PgPoolOptions::new()
+        .max_connections(1)
+        .connect_with(
+            PgConnectOptions::new()
+                .host(
+                    std::env::var("DATABASE_HOST")
+                        .unwrap_or("localhost".to_string())
+                        .as_str(),
+                )
+                .port(match std::env::var("DATABASE_PORT") {
+                    Ok(port_str) => port_str.parse::<u16>().unwrap(),
+                    _ => 5432,
+                })
+                .username(&env("APP_DB_USER"))
+                .password(&env("APP_DB_PASSWORD"))
+                .database(&env("APP_DB_NAME")),
+        )
+        .await
+        .expect("Failed to connect to database");
+    let client = create_client(
+        &env("MINIO_ENDPOINT"),
+        &env("MINIO_REGION"),
+        &env("APP_MINIO_ACCESS_KEY"),
+        &env("APP_MINIO_SECRET_KEY"),
+    )
+    .await;
+    let image_services = ImageServices::init(&client).await;
+    let s3_client = S3Client {
+        internal: client.clone(),
+        external: client,
+    };
+
+    let report = match import_persons(
+        &pool,
+        &image_services.person_photo,
+        &s3_client,
+        table,
+        photos.as_ref(),
+        dry_run,
+    )
+    .await
+    {
+        Ok(report) => report,
+        Err(e) => {
+            eprintln!("{}", e);
+            return ExitCode::FAILURE;
+        }
+    };
+    if !report.ignored_columns.is_empty() {
+        println!("Ignored columns: {}", report.ignored_columns.join(", "));
+    }
+    for error in &report.errors {
+        println!("Row {}: {}", error.row, error.errors.join("; "));
+    }
+    println!(
+        "{} rows, {} invalid, {} imported{}",
+        report.rows,
+        report.errors.len(),
+        report.imported,
+        if dry_run { " (dry run)" } else { "" }
+    );
+    if report.errors.is_empty() {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    }
+}
@@ -0,0 +1,203 @@
+//! 人员认领
+//!
+//! 账户邮箱已经过验证且与人员邮箱一致时直接关联，否则等待管理员审核。
+//! 通过绑定届的邀请码注册的账户只能认领该届班级中的人员
+//!
+//! 不按手机号自动关联：账户没有经过验证的手机号
+use account::guards::{ClientInfo, Require, User, UserId};
+use account::rbac::perm;
+use entity::audit::AuditLog;
+use entity::invitation::InvitationCode;
+use entity::person::{ClaimStatus, Person, PersonClaim};
+use entity::school::ClassMember;
+use rocket::http::Status;
+use rocket::serde::json::{json, Json};
+use rocket::{get, post, routes, FromFormField, State};
+use sqlx::PgPool;
+use utils::pagination::{Page, Pagination};
+
+/// 认领人员
+///
+/// 返回的认领状态为 `Approved` 时已关联，`Pending` 时等待管理员审核。
+/// 仅按已验证的账户邮箱自动关联，未验证邮箱的账户与手机号一致的人员同样需要管理员审核
+#[post("/person/<id>/claim")]
+async fn claim_person(
+    pool: &State<PgPool>,
+    user: User,
+    id: i32,
+) -> Result<Json<PersonClaim>, (Status, &'static str)> {
+    let User(user) = user;
+    let person = Person::find(pool.inner(), id)
+        .await
+        .unwrap()
+        .ok_or((Status::NotFound, "Person not found"))?;
+    if person.user_id.is_some() {
+        return Err((Status::Conflict, "Person already claimed"));
+    }
+    if Person::of_user(pool.inner(), user.id)
+        .await
+        .unwrap()
+        .is_some()
+    {
+        return Err((Status::Conflict, "Account already linked to a person"));
+    }
+    if let Some(invitation_id) = user.invitation_id
+        && let Some(cohort_id) = InvitationCode::find(pool.inner(), invitation_id)
+            .await
+            .unwrap()
+            .and_then(|invitation| invitation.cohort_id)
+        && !ClassMember::of_person(pool.inner(), person.id)
+            .await
+            .unwrap()
+            .iter()
+            .any(|class| class.cohort_id == cohort_id)
+    {
+        return Err((Status::Forbidden, "Person not in the invited cohort"));
+    }
+    // 未验证的邮箱可能属于他人，且不应通过认领结果推断人员的邮箱
+    if user.email_verified_at.is_some()
+        && person
+            .email
+            .as_deref()
+            .is_some_and(|email| email.trim().eq_ignore_ascii_case(&user.email))
+    {
+        return PersonClaim::link(pool.inner(), person.id, user.id, "email", None)
+            .await
+            .unwrap()
+            .map(Json)
+            .ok_or((Status::Conflict, "Person already claimed"));
+    }
+    Ok(Json(
+        PersonClaim::create(pool.inner(), person.id, user.id)
+            .await
+            .unwrap(),
+    ))
+}
+
+/// 当前账户提交的认领
+#[get("/account/person/claims")]
+async fn own_claims(pool: &State<PgPool>, user: UserId) -> Json<Vec<PersonClaim>> {
+    Json(PersonClaim::of_user(pool.inner(), user.0).await.unwrap())
+}
+
+/// 认领状态查询参数
+#[derive(Clone, Copy, Debug, FromFormField)]
+enum ClaimStatusParam {
+    Pending,
+    Approved,
+    Rejected,
+}
+
+impl From<ClaimStatusParam> for ClaimStatus {
+    fn from(status: ClaimStatusParam) -> Self {
+        match status {
+            ClaimStatusParam::Pending => ClaimStatus::Pending,
+            ClaimStatusParam::Approved => ClaimStatus::Approved,
+            ClaimStatusParam::Rejected => ClaimStatus::Rejected,
+        }
+    }
+}
+
+/// 认领列表
+///
+/// * `status` - `pending` / `approved` / `rejected`, 默认 `pending`
+#[get("/person/claims?<status>&<pagination..>")]
+async fn claims(
+    pool: &State<PgPool>,
+    _admin: Require<perm::PersonWrite>,
+    status: Option<ClaimStatusParam>,
+    pagination: Pagination,
+) -> Json<Page<PersonClaim>> {
+    let status = status.map_or(ClaimStatus::Pending, ClaimStatus::from);
+    let (items, total) = PersonClaim::list(
+        pool.inner(),
+        &status,
+        pagination.limit(),
+        pagination.offset(),
+    )
+    .await
+    .unwrap();
+    Json(Page::new(items, total, pagination))
+}
+
+async fn find_pending_claim(pool: &PgPool, id: i32) -> Result<PersonClaim, (Status, &'static str)> {
+    let claim = PersonClaim::find(pool, id)
+        .await
+        .unwrap()
+        .ok_or((Status::NotFound, "Claim not found"))?;
+    if claim.status != ClaimStatus::Pending {
+        return Err((Status::Conflict, "Claim already reviewed"));
+    }
+    Ok(claim)
+}
+
+#[post("/person/claims/<id>/approve")]
+async fn approve_claim(
+    pool: &State<PgPool>,
+    admin: Require<perm::PersonWrite>,
+    client: ClientInfo,
+    id: i32,
+) -> Result<Json<PersonClaim>, (Status, &'static str)> {
+    let claim = find_pending_claim(pool.inner(), id).await?;
+    let claim = PersonClaim::link(
+        pool.inner(),
+        claim.person_id,
+        claim.user_id,
+        "admin",
+        Some(admin.0.id),
+    )
+    .await
+    .unwrap()
+    .ok_or((
+        Status::Conflict,
+        "Person already claimed or account already linked",
+    ))?;
+    AuditLog::record(
+        pool.inner(),
+        admin.0.id,
+        "person.claim_approve",
+        Some(claim.user_id),
+        json!({ "claim_id": claim.id, "person_id": claim.person_id }),
+        client.ip.as_deref(),
+    )
+    .await
+    .unwrap();
+    Ok(Json(claim))
+}
+
+#[post("/person/claims/<id>/reject")]
+async fn reject_claim(
+    pool: &State<PgPool>,
+    admin: Require<perm::PersonWrite>,
+    client: ClientInfo,
+    id: i32,
+) -> Result<(Status, &'static str), (Status, &'static str)> {
+    let claim = find_pending_claim(pool.inner(), id).await?;
+    if !PersonClaim::reject(pool.inner(), id, admin.0.id)
+        .await
+        .unwrap()
+    {
+        return Err((Status::Conflict, "Claim already reviewed"));
+    }
+    AuditLog::record(
+        pool.inner(),
+        admin.0.id,
+        "person.claim_reject",
+        Some(claim.user_id),
+        json!({ "claim_id": claim.id, "person_id": claim.person_id }),
+        client.ip.as_deref(),
+    )
+    .await
+    .unwrap();
+    Ok((Status::Ok, "Success"))
+}
+
+pub fn routes() -> Vec<rocket::Route> {
+    routes![
+        claim_person,
+        own_claims,
+        claims,
+        approve_claim,
+        reject_claim
+    ]
+}
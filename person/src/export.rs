@@ -0,0 +1,323 @@
+//! 导出人员为 CSV / XLSX / vCard
+//!
+//! 筛选条件同 `GET /person`，字段按查看者的可见性隐藏。
+//! CSV 和 XLSX 的列与 [导入](crate::import) 相同，可以直接重新导入；
+//! 只显示月日的生日导出为 `--MM-DD`，重新导入时没有年份，生日留空
+use crate::routes::ListPersonsReq;
+use crate::visibility::viewer;
+use account::guards::Require;
+use account::rbac::perm;
+use base64::prelude::*;
+use entity::person::{Gender, PersonProfile};
+use image_service::service::ImageService;
+use image_service::{ImageServices, S3Client};
+use rocket::http::{ContentType, Header, Status};
+use rocket::{get, routes, FromFormField, Responder, State};
+use rust_xlsxwriter::{Format, Workbook};
+use sqlx::{PgPool, QueryBuilder};
+
+/// 导出格式
+#[derive(Clone, Copy, Debug, Default, FromFormField)]
+enum ExportFormat {
+    #[default]
+    Csv,
+    Xlsx,
+    Vcard,
+}
+
+/// vCard 中的照片
+#[derive(Clone, Copy, Debug, Default, FromFormField)]
+enum PhotoMode {
+    /// 以 base64 嵌入
+    #[default]
+    Embed,
+    /// 使用预签名 url，过期后失效
+    Link,
+    None,
+}
+
+#[derive(Responder)]
+struct ExportFile {
+    data: Vec<u8>,
+    content_type: ContentType,
+    disposition: Header<'static>,
+}
+
+impl ExportFile {
+    fn new(data: Vec<u8>, content_type: ContentType, extension: &str) -> Self {
+        Self {
+            data,
+            content_type,
+            disposition: Header::new(
+                "Content-Disposition",
+                format!("attachment; filename=\"persons.{}\"", extension),
+            ),
+        }
+    }
+}
+
+/// 嵌入照片时最多导出的人数，超过时需缩小筛选范围或使用 `link`
+const MAX_EMBED_PERSONS: usize = 500;
+
+/// 嵌入照片时同时下载的数量
+const EMBED_CONCURRENCY: usize = 16;
+
+const HEADERS: [&str; 7] = [
+    "name", "gender", "birthday", "phone", "email", "qq", "wechat",
+];
+
+fn gender(gender: &Gender) -> &'static str {
+    match gender {
+        Gender::Male => "male",
+        Gender::Female => "female",
+    }
+}
+
+/// 只显示月日时为 `--MM-DD`
+fn birthday(person: &PersonProfile) -> Option<String> {
+    match (&person.birthday, &person.birth_month_day) {
+        (Some(birthday), _) => Some(birthday.format("%Y-%m-%d").to_string()),
+        (None, Some(month_day)) => Some(format!("--{}", month_day)),
+        (None, None) => None,
+    }
+}
+
+fn row(person: &PersonProfile) -> [String; 7] {
+    [
+        person.name.clone(),
+        gender(&person.gender).to_string(),
+        birthday(person).unwrap_or_default(),
+        person.phone.clone().unwrap_or_default(),
+        person.email.clone().unwrap_or_default(),
+        person.qq.clone().unwrap_or_default(),
+        person.wechat.clone().unwrap_or_default(),
+    ]
+}
+
+/// 带 BOM，便于 Excel 识别 UTF-8
+fn to_csv(persons: &[PersonProfile]) -> Vec<u8> {
+    let mut writer = csv::Writer::from_writer("\u{feff}".as_bytes().to_vec());
+    writer.write_record(HEADERS).unwrap();
+    for person in persons {
+        writer.write_record(row(person)).unwrap();
+    }
+    writer.into_inner().unwrap()
+}
+
+fn to_xlsx(persons: &[PersonProfile]) -> Vec<u8> {
+    let mut workbook = Workbook::new();
+    let worksheet = workbook.add_worksheet();
+    let bold = Format::new().set_bold();
+    for (col, header) in HEADERS.iter().enumerate() {
+        worksheet
+            .write_string_with_format(0, col as u16, *header, &bold)
+            .unwrap();
+    }
+    // 全部写为文本，避免手机号、QQ 号被转换为数字
+    for (i, person) in persons.iter().enumerate() {
+        for (col, value) in row(person).iter().enumerate() {
+            if !value.is_empty() {
+                worksheet
+                    .write_string(i as u32 + 1, col as u16, value)
+                    .unwrap();
+            }
+        }
+    }
+    worksheet.autofit();
+    workbook.save_to_buffer().unwrap()
+}
+
+/// 转义 vCard 的文本值
+fn escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace(',', "\\,")
+        .replace(';', "\\;")
+        .replace('\n', "\\n")
+}
+
+/// 按 RFC 6350 每行不超过 75 字节，续行以空格开头
+fn push_line(card: &mut String, line: &str) {
+    let mut width = 0;
+    for c in line.chars() {
+        if width + c.len_utf8() > 75 {
+            card.push_str("\r\n ");
+            width = 1;
+        }
+        card.push(c);
+        width += c.len_utf8();
+    }
+    card.push_str("\r\n");
+}
+
+/// vCard 4.0
+///
+/// * `photo` - `PHOTO` 属性的值，data url 或普通 url
+fn to_vcard(person: &PersonProfile, photo: Option<&str>) -> String {
+    let mut card = String::new();
+    push_line(&mut card, "BEGIN:VCARD");
+    push_line(&mut card, "VERSION:4.0");
+    push_line(&mut card, &format!("FN:{}", escape(&person.name)));
+    push_line(&mut card, &format!("N:{};;;;", escape(&person.name)));
+    push_line(
+        &mut card,
+        match person.gender {
+            Gender::Male => "GENDER:M",
+            Gender::Female => "GENDER:F",
+        },
+    );
+    // 只有月日时为截断格式 `--MMDD`
+    if let Some(birthday) = birthday(person) {
+        let value = match birthday.strip_prefix("--") {
+            Some(month_day) => format!("--{}", month_day.replace('-', "")),
+            None => birthday.replace('-', ""),
+        };
+        push_line(&mut card, &format!("BDAY:{}", value));
+    }
+    if let Some(phone) = &person.phone {
+        push_line(&mut card, &format!("TEL;VALUE=uri;TYPE=cell:tel:{}", phone));
+    }
+    if let Some(email) = &person.email {
+        push_line(&mut card, &format!("EMAIL:{}", escape(email)));
+    }
+    if let Some(qq) = &person.qq {
+        push_line(&mut card, &format!("X-QQ:{}", escape(qq)));
+    }
+    if let Some(wechat) = &person.wechat {
+        push_line(&mut card, &format!("X-WECHAT:{}", escape(wechat)));
+    }
+    if let Some(photo) = photo {
+        push_line(&mut card, &format!("PHOTO:{}", photo));
+    }
+    push_line(
+        &mut card,
+        &format!("REV:{}", person.updated_at.format("%Y%m%dT%H%M%SZ")),
+    );
+    push_line(&mut card, "END:VCARD");
+    card
+}
+
+/// 照片的 data url，无法读取时跳过
+async fn embed_photo(
+    service: &ImageService,
+    s3_client: &S3Client,
+    person: &PersonProfile,
+) -> Option<String> {
+    let key = person.photo_id.as_ref()?;
+    match service.get_image(&s3_client.internal, key).await {
+        Ok(data) => Some(format!(
+            "data:{};base64,{}",
+            service.image_content_type(),
+            BASE64_STANDARD.encode(data)
+        )),
+        Err(e) => {
+            eprintln!("Cannot load photo {}: {:?}", key, e);
+            None
+        }
+    }
+}
+
+/// 分批并发下载照片，返回顺序与 `persons` 相同
+async fn embed_photos(
+    service: &ImageService,
+    s3_client: &S3Client,
+    persons: &[PersonProfile],
+) -> Vec<Option<String>> {
+    let mut photos = Vec::with_capacity(persons.len());
+    for chunk in persons.chunks(EMBED_CONCURRENCY) {
+        photos.extend(
+            futures::future::join_all(
+                chunk
+                    .iter()
+                    .map(|person| embed_photo(service, s3_client, person)),
+            )
+            .await,
+        );
+    }
+    photos
+}
+
+/// 导出人员
+///
+/// * `format` - `csv` (默认) / `xlsx` / `vcard`
+/// * `photo` - vCard 的照片：`embed` (默认) 嵌入照片，`link` 使用预签名 url，`none` 不包含照片。
+///   嵌入照片时最多导出 500 人，超过时返回 400；无法读取的照片会被跳过
+///
+/// 其余参数同 `GET /person`，分页参数被忽略；按姓名拼音排序
+#[get("/person/export?<format>&<photo>&<req..>")]
+#[allow(clippy::too_many_arguments)]
+async fn export(
+    s3_client: &State<S3Client>,
+    pool: &State<PgPool>,
+    image_services: &State<ImageServices>,
+    user: Require<perm::PersonRead>,
+    format: Option<ExportFormat>,
+    photo: Option<PhotoMode>,
+    req: ListPersonsReq,
+) -> Result<ExportFile, (Status, &'static str)> {
+    let viewer = viewer(pool.inner(), user.0.id).await;
+    let mut query = QueryBuilder::new("SELECT * FROM person");
+    req.push_conditions(&mut query, &viewer);
+    query.push(" ORDER BY name_pinyin, id");
+    let mut persons = query
+        .build_query_as::<PersonProfile>()
+        .fetch_all(pool.inner())
+        .await
+        .unwrap();
+    for person in &mut persons {
+        person.mask(&viewer);
+    }
+
+    Ok(match format.unwrap_or_default() {
+        ExportFormat::Csv => ExportFile::new(to_csv(&persons), ContentType::CSV, "csv"),
+        ExportFormat::Xlsx => ExportFile::new(
+            to_xlsx(&persons),
+            ContentType::new(
+                "application",
+                "vnd.openxmlformats-officedocument.spreadsheetml.sheet",
+            ),
+            "xlsx",
+        ),
+        ExportFormat::Vcard => {
+            let service = &image_services.person_photo;
+            let mut cards = String::new();
+            match photo.unwrap_or_default() {
+                PhotoMode::Embed => {
+                    if persons.len() > MAX_EMBED_PERSONS {
+                        return Err((
+                            Status::BadRequest,
+                            "Too many persons to embed photos, narrow the filter or use link",
+                        ));
+                    }
+                    let photos = embed_photos(service, s3_client, &persons).await;
+                    for (person, photo) in persons.iter().zip(&photos) {
+                        cards.push_str(&to_vcard(person, photo.as_deref()));
+                    }
+                }
+                PhotoMode::Link => {
+                    PersonProfile::sign_all(&mut persons, service, &s3_client.external)
+                        .await
+                        .unwrap();
+                    for person in &persons {
+                        let photo = Some(person.photo.as_str()).filter(|url| !url.is_empty());
+                        cards.push_str(&to_vcard(person, photo));
+                    }
+                }
+                PhotoMode::None => {
+                    for person in &persons {
+                        cards.push_str(&to_vcard(person, None));
+                    }
+                }
+            }
+            ExportFile::new(
+                cards.into_bytes(),
+                ContentType::new("text", "vcard").with_params(("charset", "utf-8")),
+                "vcf",
+            )
+        }
+    })
+}
+
+pub(crate) fn routes() -> Vec<rocket::Route> {
+    routes![export]
+}
@@ -0,0 +1,350 @@
+//! 教育和工作经历
+//!
+//! `/person/<id>/...` 供管理员维护，`/account/person/...` 供用户维护自己认领的人员
+use account::guards::{Require, UserId};
+use account::rbac::perm;
+use chrono::NaiveDate;
+use entity::history::{Education, EducationData, Employment, EmploymentData};
+use entity::person::Person;
+use rocket::form::Form;
+use rocket::http::Status;
+use rocket::serde::json::Json;
+use rocket::{delete, get, post, put, routes, FromForm, State};
+use sqlx::PgPool;
+use utils::validate_opt;
+use utils::validators::is_ymd_date;
+
+/// 去除首尾空白，空字符串视为未填写
+fn optional(value: &Option<String>) -> Option<&str> {
+    value.as_deref().map(str::trim).filter(|v| !v.is_empty())
+}
+
+/// 开始、结束日期
+type DateRange = (Option<NaiveDate>, Option<NaiveDate>);
+
+fn parse_dates(
+    start_date: &Option<String>,
+    end_date: &Option<String>,
+) -> Result<DateRange, (Status, &'static str)> {
+    let parse = |date: &Option<String>| {
+        date.as_deref()
+            .map(|date| NaiveDate::parse_from_str(date, "%Y-%m-%d").unwrap())
+    };
+    let (start_date, end_date) = (parse(start_date), parse(end_date));
+    if start_date
+        .zip(end_date)
+        .is_some_and(|(start, end)| start > end)
+    {
+        return Err((Status::UnprocessableEntity, "Start date is after end date"));
+    }
+    Ok((start_date, end_date))
+}
+
+async fn find_person(pool: &PgPool, id: i32) -> Result<i32, (Status, &'static str)> {
+    Person::find(pool, id)
+        .await
+        .unwrap()
+        .map(|person| person.id)
+        .ok_or((Status::NotFound, "Person not found"))
+}
+
+async fn own_person(pool: &PgPool, user_id: i32) -> Result<i32, (Status, &'static str)> {
+    Person::of_user(pool, user_id)
+        .await
+        .unwrap()
+        .map(|person| person.id)
+        .ok_or((Status::NotFound, "No linked person"))
+}
+
+fn deleted(deleted: bool) -> (Status, &'static str) {
+    if deleted {
+        (Status::Ok, "Success")
+    } else {
+        (Status::NotFound, "Entry not found")
+    }
+}
+
+#[derive(Debug, FromForm)]
+struct EducationReq {
+    #[field(validate = len(1..=128).or_else(msg!("Institution length must be between 1 and 128 characters")))]
+    institution: String,
+    #[field(validate = len(..=64).or_else(msg!("Degree length must be at most 64 characters")))]
+    degree: Option<String>,
+    #[field(validate = len(..=128).or_else(msg!("Major length must be at most 128 characters")))]
+    major: Option<String>,
+    /// YYYY-MM-DD
+    #[field(validate = validate_opt!(is_ymd_date())())]
+    start_date: Option<String>,
+    /// YYYY-MM-DD, 不填表示在读
+    #[field(validate = validate_opt!(is_ymd_date())())]
+    end_date: Option<String>,
+}
+
+impl EducationReq {
+    fn data(&self) -> Result<EducationData<'_>, (Status, &'static str)> {
+        let (start_date, end_date) = parse_dates(&self.start_date, &self.end_date)?;
+        Ok(EducationData {
+            institution: self.institution.trim(),
+            degree: optional(&self.degree),
+            major: optional(&self.major),
+            start_date,
+            end_date,
+        })
+    }
+}
+
+#[derive(Debug, FromForm)]
+struct EmploymentReq {
+    #[field(validate = len(1..=128).or_else(msg!("Organization length must be between 1 and 128 characters")))]
+    organization: String,
+    #[field(validate = len(..=128).or_else(msg!("Title length must be at most 128 characters")))]
+    title: Option<String>,
+    #[field(validate = len(..=64).or_else(msg!("City length must be at most 64 characters")))]
+    city: Option<String>,
+    /// YYYY-MM-DD
+    #[field(validate = validate_opt!(is_ymd_date())())]
+    start_date: Option<String>,
+    /// YYYY-MM-DD, 不填表示在职
+    #[field(validate = validate_opt!(is_ymd_date())())]
+    end_date: Option<String>,
+}
+
+impl EmploymentReq {
+    fn data(&self) -> Result<EmploymentData<'_>, (Status, &'static str)> {
+        let (start_date, end_date) = parse_dates(&self.start_date, &self.end_date)?;
+        Ok(EmploymentData {
+            organization: self.organization.trim(),
+            title: optional(&self.title),
+            city: optional(&self.city),
+            start_date,
+            end_date,
+        })
+    }
+}
+
+#[get("/person/<id>/education")]
+async fn educations(
+    pool: &State<PgPool>,
+    _user: Require<perm::PersonRead>,
+    id: i32,
+) -> Result<Json<Vec<Education>>, (Status, &'static str)> {
+    let id = find_person(pool.inner(), id).await?;
+    Ok(Json(Education::of_person(pool.inner(), id).await.unwrap()))
+}
+
+#[post("/person/<id>/education", data = "<data>")]
+async fn create_education(
+    pool: &State<PgPool>,
+    _user: Require<perm::PersonWrite>,
+    id: i32,
+    data: Form<EducationReq>,
+) -> Result<Json<Education>, (Status, &'static str)> {
+    let id = find_person(pool.inner(), id).await?;
+    Ok(Json(
+        Education::create(pool.inner(), id, &data.data()?)
+            .await
+            .unwrap(),
+    ))
+}
+
+#[put("/person/<id>/education/<entry_id>", data = "<data>")]
+async fn update_education(
+    pool: &State<PgPool>,
+    _user: Require<perm::PersonWrite>,
+    id: i32,
+    entry_id: i32,
+    data: Form<EducationReq>,
+) -> Result<Json<Education>, (Status, &'static str)> {
+    Education::update(pool.inner(), entry_id, id, &data.data()?)
+        .await
+        .unwrap()
+        .map(Json)
+        .ok_or((Status::NotFound, "Entry not found"))
+}
+
+#[delete("/person/<id>/education/<entry_id>")]
+async fn delete_education(
+    pool: &State<PgPool>,
+    _user: Require<perm::PersonWrite>,
+    id: i32,
+    entry_id: i32,
+) -> (Status, &'static str) {
+    deleted(Education::delete(pool.inner(), entry_id, id).await.unwrap())
+}
+
+#[get("/person/<id>/employment")]
+async fn employments(
+    pool: &State<PgPool>,
+    _user: Require<perm::PersonRead>,
+    id: i32,
+) -> Result<Json<Vec<Employment>>, (Status, &'static str)> {
+    let id = find_person(pool.inner(), id).await?;
+    Ok(Json(Employment::of_person(pool.inner(), id).await.unwrap()))
+}
+
+#[post("/person/<id>/employment", data = "<data>")]
+async fn create_employment(
+    pool: &State<PgPool>,
+    _user: Require<perm::PersonWrite>,
+    id: i32,
+    data: Form<EmploymentReq>,
+) -> Result<Json<Employment>, (Status, &'static str)> {
+    let id = find_person(pool.inner(), id).await?;
+    Ok(Json(
+        Employment::create(pool.inner(), id, &data.data()?)
+            .await
+            .unwrap(),
+    ))
+}
+
+#[put("/person/<id>/employment/<entry_id>", data = "<data>")]
+async fn update_employment(
+    pool: &State<PgPool>,
+    _user: Require<perm::PersonWrite>,
+    id: i32,
+    entry_id: i32,
+    data: Form<EmploymentReq>,
+) -> Result<Json<Employment>, (Status, &'static str)> {
+    Employment::update(pool.inner(), entry_id, id, &data.data()?)
+        .await
+        .unwrap()
+        .map(Json)
+        .ok_or((Status::NotFound, "Entry not found"))
+}
+
+#[delete("/person/<id>/employment/<entry_id>")]
+async fn delete_employment(
+    pool: &State<PgPool>,
+    _user: Require<perm::PersonWrite>,
+    id: i32,
+    entry_id: i32,
+) -> (Status, &'static str) {
+    deleted(
+        Employment::delete(pool.inner(), entry_id, id)
+            .await
+            .unwrap(),
+    )
+}
+
+#[get("/account/person/education")]
+async fn own_educations(
+    pool: &State<PgPool>,
+    user: UserId,
+) -> Result<Json<Vec<Education>>, (Status, &'static str)> {
+    let id = own_person(pool.inner(), user.0).await?;
+    Ok(Json(Education::of_person(pool.inner(), id).await.unwrap()))
+}
+
+#[post("/account/person/education", data = "<data>")]
+async fn create_own_education(
+    pool: &State<PgPool>,
+    user: UserId,
+    data: Form<EducationReq>,
+) -> Result<Json<Education>, (Status, &'static str)> {
+    let id = own_person(pool.inner(), user.0).await?;
+    Ok(Json(
+        Education::create(pool.inner(), id, &data.data()?)
+            .await
+            .unwrap(),
+    ))
+}
+
+#[put("/account/person/education/<entry_id>", data = "<data>")]
+async fn update_own_education(
+    pool: &State<PgPool>,
+    user: UserId,
+    entry_id: i32,
+    data: Form<EducationReq>,
+) -> Result<Json<Education>, (Status, &'static str)> {
+    let id = own_person(pool.inner(), user.0).await?;
+    Education::update(pool.inner(), entry_id, id, &data.data()?)
+        .await
+        .unwrap()
+        .map(Json)
+        .ok_or((Status::NotFound, "Entry not found"))
+}
+
+#[delete("/account/person/education/<entry_id>")]
+async fn delete_own_education(
+    pool: &State<PgPool>,
+    user: UserId,
+    entry_id: i32,
+) -> Result<(Status, &'static str), (Status, &'static str)> {
+    let id = own_person(pool.inner(), user.0).await?;
+    Ok(deleted(
+        Education::delete(pool.inner(), entry_id, id).await.unwrap(),
+    ))
+}
+
+#[get("/account/person/employment")]
+async fn own_employments(
+    pool: &State<PgPool>,
+    user: UserId,
+) -> Result<Json<Vec<Employment>>, (Status, &'static str)> {
+    let id = own_person(pool.inner(), user.0).await?;
+    Ok(Json(Employment::of_person(pool.inner(), id).await.unwrap()))
+}
+
+#[post("/account/person/employment", data = "<data>")]
+async fn create_own_employment(
+    pool: &State<PgPool>,
+    user: UserId,
+    data: Form<EmploymentReq>,
+) -> Result<Json<Employment>, (Status, &'static str)> {
+    let id = own_person(pool.inner(), user.0).await?;
+    Ok(Json(
+        Employment::create(pool.inner(), id, &data.data()?)
+            .await
+            .unwrap(),
+    ))
+}
+
+#[put("/account/person/employment/<entry_id>", data = "<data>")]
+async fn update_own_employment(
+    pool: &State<PgPool>,
+    user: UserId,
+    entry_id: i32,
+    data: Form<EmploymentReq>,
+) -> Result<Json<Employment>, (Status, &'static str)> {
+    let id = own_person(pool.inner(), user.0).await?;
+    Employment::update(pool.inner(), entry_id, id, &data.data()?)
+        .await
+        .unwrap()
+        .map(Json)
+        .ok_or((Status::NotFound, "Entry not found"))
+}
+
+#[delete("/account/person/employment/<entry_id>")]
+async fn delete_own_employment(
+    pool: &State<PgPool>,
+    user: UserId,
+    entry_id: i32,
+) -> Result<(Status, &'static str), (Status, &'static str)> {
+    let id = own_person(pool.inner(), user.0).await?;
+    Ok(deleted(
+        Employment::delete(pool.inner(), entry_id, id)
+            .await
+            .unwrap(),
+    ))
+}
+
+pub fn routes() -> Vec<rocket::Route> {
+    routes![
+        educations,
+        create_education,
+        update_education,
+        delete_education,
+        employments,
+        create_employment,
+        update_employment,
+        delete_employment,
+        own_educations,
+        create_own_education,
+        update_own_education,
+        delete_own_education,
+        own_employments,
+        create_own_employment,
+        update_own_employment,
+        delete_own_employment
+    ]
+}
@@ -0,0 +1,644 @@
+//! 从 CSV / XLSX 批量导入人员
+//!
+//! - 第一行为表头，按列名对应 [`Person`](entity::person::Person) 的字段，支持英文字段名和中文列名，
+//!   无法识别的列会被忽略
+//! - CSV 需为 UTF-8 编码；XLSX 只读取第一个工作表
+//! - 照片从 ZIP 压缩包导入，`photo` 列填写压缩包内的文件名
+//! - 生日为 [导出](crate::export) 的 `--MM-DD` (只显示月日) 时没有年份，生日留空
+//! - 任意一行校验失败时不导入任何数据，全部通过后在一个事务中写入
+//!
+//! 同时提供管理接口 `POST /person/import` 和命令行工具 `import_persons`
+use crate::routes::{normalize_phone, parse_date};
+use account::guards::{ClientInfo, Require};
+use account::rbac::perm;
+use calamine::{Data, DataType, Reader, Xlsx};
+use chrono::{NaiveDate, Utc};
+use entity::audit::AuditLog;
+use entity::person::{name_pinyin, Gender};
+use image_service::service::ImageService;
+use image_service::{ImageServices, S3Client};
+use rocket::form::Form;
+use rocket::fs::TempFile;
+use rocket::http::Status;
+use rocket::serde::json::{json, Json};
+use rocket::serde::Serialize;
+use rocket::{post, routes, FromForm, State};
+use sqlx::PgPool;
+use std::collections::HashMap;
+use std::io::{Cursor, Read};
+use utils::validators::{is_email, is_phone_number, is_ymd_date};
+
+/// 表格格式
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TableFormat {
+    Csv,
+    Xlsx,
+}
+
+impl TableFormat {
+    /// 根据文件内容判断，XLSX 为 ZIP 格式
+    pub fn detect(data: &[u8]) -> Self {
+        if data.starts_with(b"PK\x03\x04") {
+            TableFormat::Xlsx
+        } else {
+            TableFormat::Csv
+        }
+    }
+}
+
+/// 读取表格的全部行，包括表头
+pub fn read_table(data: &[u8]) -> Result<Vec<Vec<String>>, String> {
+    match TableFormat::detect(data) {
+        TableFormat::Csv => {
+            let data = data.strip_prefix("\u{feff}".as_bytes()).unwrap_or(data);
+            csv::ReaderBuilder::new()
+                .has_headers(false)
+                .flexible(true)
+                .from_reader(data)
+                .records()
+                .map(|record| {
+                    record
+                        .map(|record| record.iter().map(str::to_string).collect())
+                        .map_err(|e| format!("Invalid CSV: {}", e))
+                })
+                .collect()
+        }
+        TableFormat::Xlsx => {
+            let mut workbook =
+                Xlsx::new(Cursor::new(data)).map_err(|e| format!("Invalid XLSX: {}", e))?;
+            let range = workbook
+                .worksheet_range_at(0)
+                .ok_or("Empty workbook")?
+                .map_err(|e| format!("Invalid XLSX: {}", e))?;
+            Ok(range
+                .rows()
+                .map(|row| row.iter().map(cell_to_string).collect())
+                .collect())
+        }
+    }
+}
+
+fn cell_to_string(cell: &Data) -> String {
+    match cell {
+        Data::Empty => String::new(),
+        Data::DateTime(_) | Data::DateTimeIso(_) => cell
+            .as_date()
+            .map(|date| date.format("%Y-%m-%d").to_string())
+            .unwrap_or_else(|| cell.to_string()),
+        // 手机号、QQ 号常被保存为数字
+        Data::Float(value) if value.fract() == 0.0 => format!("{}", *value as i64),
+        _ => cell.to_string(),
+    }
+}
+
+/// 照片压缩包中的最大文件数
+const MAX_PHOTO_ENTRIES: usize = 10_000;
+
+/// 单张照片解压后的最大字节数
+const MAX_PHOTO_SIZE: u64 = 10 * 1024 * 1024;
+
+/// 照片压缩包解压后的最大总字节数
+const MAX_PHOTO_ARCHIVE_SIZE: u64 = 1024 * 1024 * 1024;
+
+/// 照片压缩包，文件名 (不含目录) 对应文件内容
+pub struct PhotoArchive(HashMap<String, Vec<u8>>);
+
+impl PhotoArchive {
+    /// 限制文件数和解压后的大小，防止 ZIP 炸弹；不以文件头中声明的大小为准
+    pub fn read(data: &[u8]) -> Result<Self, String> {
+        let mut archive =
+            zip::ZipArchive::new(Cursor::new(data)).map_err(|e| format!("Invalid ZIP: {}", e))?;
+        if archive.len() > MAX_PHOTO_ENTRIES {
+            return Err(format!(
+                "Too many files in ZIP, at most {}",
+                MAX_PHOTO_ENTRIES
+            ));
+        }
+        let mut files = HashMap::new();
+        let mut total = 0;
+        for i in 0..archive.len() {
+            let mut file = archive
+                .by_index(i)
+                .map_err(|e| format!("Invalid ZIP: {}", e))?;
+            if !file.is_file() {
+                continue;
+            }
+            let Some(name) = file
+                .enclosed_name()
+                .and_then(|path| path.file_name()?.to_str().map(str::to_string))
+            else {
+                continue;
+            };
+            let mut content = Vec::new();
+            (&mut file)
+                .take(MAX_PHOTO_SIZE + 1)
+                .read_to_end(&mut content)
+                .map_err(|e| format!("Invalid ZIP: {}", e))?;
+            if content.len() as u64 > MAX_PHOTO_SIZE {
+                return Err(format!("Photo too large: {}", name));
+            }
+            total += content.len() as u64;
+            if total > MAX_PHOTO_ARCHIVE_SIZE {
+                return Err("ZIP too large".to_string());
+            }
+            files.insert(name, content);
+        }
+        Ok(Self(files))
+    }
+
+    fn get(&self, name: &str) -> Option<&[u8]> {
+        self.0.get(name).map(Vec::as_slice)
+    }
+}
+
+/// 可导入的字段
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+enum Column {
+    Name,
+    Gender,
+    Birthday,
+    Phone,
+    Email,
+    Qq,
+    Wechat,
+    Photo,
+}
+
+impl Column {
+    fn from_header(header: &str) -> Option<Self> {
+        match header.trim().to_lowercase().as_str() {
+            "name" | "姓名" => Some(Column::Name),
+            "gender" | "性别" => Some(Column::Gender),
+            "birthday" | "生日" | "出生日期" => Some(Column::Birthday),
+            "phone" | "手机" | "手机号" | "电话" => Some(Column::Phone),
+            "email" | "邮箱" => Some(Column::Email),
+            "qq" => Some(Column::Qq),
+            "wechat" | "微信" | "微信号" => Some(Column::Wechat),
+            "photo" | "照片" => Some(Column::Photo),
+            _ => None,
+        }
+    }
+}
+
+/// 校验通过的一行
+struct PersonRow<'a> {
+    name: String,
+    gender: Gender,
+    birthday: Option<NaiveDate>,
+    phone: Option<String>,
+    email: Option<String>,
+    qq: Option<String>,
+    wechat: Option<String>,
+    photo: Option<&'a [u8]>,
+}
+
+/// 一行的全部错误
+#[derive(Debug, Serialize)]
+#[serde(crate = "rocket::serde")]
+pub struct RowError {
+    /// 表格中的行号，表头为第 1 行
+    pub row: usize,
+    pub errors: Vec<String>,
+}
+
+/// 导入结果
+#[derive(Debug, Serialize)]
+#[serde(crate = "rocket::serde")]
+pub struct ImportReport {
+    pub dry_run: bool,
+    /// 数据行数，不含表头和空行
+    pub rows: usize,
+    /// 写入的行数，有错误或 `dry_run` 时为 0
+    pub imported: usize,
+    /// 无法识别而被忽略的列
+    pub ignored_columns: Vec<String>,
+    pub errors: Vec<RowError>,
+}
+
+fn parse_gender(value: &str) -> Option<Gender> {
+    match value.to_lowercase().as_str() {
+        "male" | "m" | "男" => Some(Gender::Male),
+        "female" | "f" | "女" => Some(Gender::Female),
+        _ => None,
+    }
+}
+
+/// 导出的只有月日的生日 `--MM-DD`
+fn is_month_day(value: &str) -> bool {
+    value.strip_prefix("--").is_some_and(|month_day| {
+        NaiveDate::parse_from_str(&format!("2000-{}", month_day), "%Y-%m-%d").is_ok()
+    })
+}
+
+/// 校验一行，与 `POST /person` 的规则一致
+fn validate_row<'a>(
+    columns: &HashMap<Column, usize>,
+    row: &[String],
+    photos: Option<&'a PhotoArchive>,
+) -> Result<PersonRow<'a>, Vec<String>> {
+    let value = |column| {
+        columns
+            .get(&column)
+            .and_then(|&i| row.get(i))
+            .map(|value| value.trim().to_string())
+            .filter(|value| !value.is_empty())
+    };
+    let mut errors = Vec::new();
+
+    let name = value(Column::Name).unwrap_or_default();
+    if !(2..31).contains(&name.chars().count()) {
+        errors.push("Person name length must be between 2 and 32 characters".to_string());
+    }
+    let gender = match value(Column::Gender) {
+        Some(gender) => parse_gender(&gender).or_else(|| {
+            errors.push(format!("Invalid gender: {}", gender));
+            None
+        }),
+        None => {
+            errors.push("Gender is required".to_string());
+            None
+        }
+    };
+    let birthday = value(Column::Birthday)
+        .filter(|birthday| !is_month_day(birthday))
+        .map(|birthday| birthday.replace(['/', '.'], "-"));
+    if let Some(Err(e)) = birthday.as_ref().map(is_ymd_date) {
+        errors.extend(e.iter().map(|e| e.kind.to_string()));
+    }
+    let phone = value(Column::Phone);
+    if let Some(Err(e)) = phone.as_ref().map(is_phone_number) {
+        errors.extend(e.iter().map(|e| e.kind.to_string()));
+    }
+    let email = value(Column::Email);
+    if let Some(Err(e)) = email.as_ref().map(is_email) {
+        errors.extend(e.iter().map(|e| e.kind.to_string()));
+    }
+    let qq = value(Column::Qq);
+    if qq.as_ref().is_some_and(|qq| qq.len() > 16) {
+        errors.push("QQ length must be at most 16 characters".to_string());
+    }
+    let wechat = value(Column::Wechat);
+    if wechat.as_ref().is_some_and(|wechat| wechat.len() > 64) {
+        errors.push("WeChat length must be at most 64 characters".to_string());
+    }
+    let photo = match value(Column::Photo) {
+        Some(file) => match photos.and_then(|photos| photos.get(&file)) {
+            Some(data) if image::load_from_memory(data).is_ok() => Some(data),
+            Some(_) => {
+                errors.push(format!("Invalid photo: {}", file));
+                None
+            }
+            None => {
+                errors.push(format!("Photo not found: {}", file));
+                None
+            }
+        },
+        None => None,
+    };
+
+    match gender {
+        Some(gender) if errors.is_empty() => Ok(PersonRow {
+            name,
+            gender,
+            birthday: birthday.as_deref().map(parse_date),
+            phone: phone.as_deref().map(normalize_phone),
+            email,
+            qq,
+            wechat,
+            photo,
+        }),
+        _ => Err(errors),
+    }
+}
+
+/// 导入人员
+///
+/// * `table` - [`read_table`] 读取的全部行
+/// * `dry_run` - 只校验，不写入
+///
+/// 表头缺少必填列或写入数据库失败时返回错误，行内的错误记录在 [`ImportReport::errors`] 中
+pub async fn import_persons(
+    pool: &PgPool,
+    image_service: &ImageService,
+    s3_client: &S3Client,
+    table: Vec<Vec<String>>,
+    photos: Option<&PhotoArchive>,
+    dry_run: bool,
+) -> Result<ImportReport, String> {
+    let mut rows = table.into_iter().enumerate();
+    let (_, headers) = rows.next().ok_or("Empty table")?;
+    let mut columns = HashMap::new();
+    let mut ignored_columns = Vec::new();
+    for (i, header) in headers.iter().enumerate() {
+        match Column::from_header(header) {
+            Some(column) => {
+                columns.entry(column).or_insert(i);
+            }
+            None if header.trim().is_empty() => {}
+            None => ignored_columns.push(header.trim().to_string()),
+        }
+    }
+    for (column, name) in [(Column::Name, "name"), (Column::Gender, "gender")] {
+        if !columns.contains_key(&column) {
+            return Err(format!("Missing column: {}", name));
+        }
+    }
+
+    let mut persons = Vec::new();
+    let mut errors = Vec::new();
+    for (i, row) in rows {
+        if row.iter().all(|value| value.trim().is_empty()) {
+            continue;
+        }
+        match validate_row(&columns, &row, photos) {
+            Ok(person) => persons.push(person),
+            Err(row_errors) => errors.push(RowError {
+                row: i + 1,
+                errors: row_errors,
+            }),
+        }
+    }
+    let mut report = ImportReport {
+        dry_run,
+        rows: persons.len() + errors.len(),
+        imported: 0,
+        ignored_columns,
+        errors,
+    };
+    if dry_run || !report.errors.is_empty() {
+        return Ok(report);
+    }
+
+    // 照片无法随事务回滚，上传或写入失败时手动删除已上传的照片
+    let mut photo_ids = Vec::new();
+    let result = async {
+        for person in &persons {
+            photo_ids.push(match person.photo {
+                Some(data) => Some(
+                    image_service
+                        .upload_image(&s3_client.internal, image::load_from_memory(data).unwrap())
+                        .await
+                        .map_err(|_| "Cannot upload photo".to_string())?,
+                ),
+                None => None,
+            });
+        }
+        insert_persons(pool, &persons, &photo_ids)
+            .await
+            .map_err(|e| format!("Cannot import persons: {}", e))
+    }
+    .await;
+    if let Err(e) = result {
+        for key in photo_ids.iter().flatten() {
+            if let Err(e) = image_service.delete_image(&s3_client.internal, key).await {
+                eprintln!("Cannot delete photo {}: {:?}", key, e);
+            }
+        }
+        return Err(e);
+    }
+    report.imported = persons.len();
+    Ok(report)
+}
+
+async fn insert_persons(
+    pool: &PgPool,
+    persons: &[PersonRow<'_>],
+    photo_ids: &[Option<String>],
+) -> Result<(), sqlx::Error> {
+    let now = Utc::now();
+    let mut tx = pool.begin().await?;
+    for (person, photo_id) in persons.iter().zip(photo_ids) {
+        let (pinyin, initials) = name_pinyin(&person.name);
+        sqlx::query(
+            r#"INSERT INTO person (name, name_pinyin, name_initials, gender, birthday, phone, email, qq, wechat, photo_id, created_at, updated_at)
+               VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $11)"#,
+        )
+        .bind(&person.name)
+        .bind(pinyin)
+        .bind(initials)
+        .bind(&person.gender)
+        .bind(person.birthday)
+        .bind(&person.phone)
+        .bind(&person.email)
+        .bind(&person.qq)
+        .bind(&person.wechat)
+        .bind(photo_id)
+        .bind(now)
+        .execute(&mut *tx)
+        .await?;
+    }
+    tx.commit().await
+}
+
+#[derive(FromForm)]
+struct ImportReq<'r> {
+    /// CSV 或 XLSX
+    file: TempFile<'r>,
+    /// 照片 ZIP
+    photos: Option<TempFile<'r>>,
+}
+
+/// 批量导入人员
+///
+/// * `dry_run` - 为 `true` 时只校验并返回每行的错误
+///
+/// 有行校验失败时返回 422 和导入结果，不写入任何数据
+#[post("/person/import?<dry_run>", data = "<data>")]
+async fn import(
+    s3_client: &State<S3Client>,
+    pool: &State<PgPool>,
+    image_services: &State<ImageServices>,
+    admin: Require<perm::PersonWrite>,
+    client: ClientInfo,
+    dry_run: Option<bool>,
+    data: Form<ImportReq<'_>>,
+) -> Result<(Status, Json<ImportReport>), (Status, String)> {
+    let read = |file: &TempFile| {
+        file.path()
+            .map(std::fs::read)
+            .ok_or((Status::BadRequest, "Empty file".to_string()))?
+            .map_err(|_| (Status::BadRequest, "Cannot read file".to_string()))
+    };
+    let table = read_table(&read(&data.file)?).map_err(|e| (Status::BadRequest, e))?;
+    let photos = match &data.photos {
+        Some(photos) => {
+            Some(PhotoArchive::read(&read(photos)?).map_err(|e| (Status::BadRequest, e))?)
+        }
+        None => None,
+    };
+    let report = import_persons(
+        pool.inner(),
+        &image_services.person_photo,
+        s3_client.inner(),
+        table,
+        photos.as_ref(),
+        dry_run.unwrap_or(false),
+    )
+    .await
+    .map_err(|e| (Status::BadRequest, e))?;
+    if !report.errors.is_empty() {
+        return Ok((Status::UnprocessableEntity, Json(report)));
+    }
+    if report.imported > 0 {
+        AuditLog::record(
+            pool.inner(),
+            admin.0.id,
+            "person.import",
+            None,
+            json!({ "imported": report.imported }),
+            client.ip.as_deref(),
+        )
+        .await
+        .unwrap();
+    }
+    Ok((Status::Ok, Json(report)))
+}
+
+pub(crate) fn routes() -> Vec<rocket::Route> {
+    routes![import]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use zip::write::SimpleFileOptions;
+
+    fn zip(files: &[(&str, &[u8])]) -> Vec<u8> {
+        let mut writer = zip::ZipWriter::new(Cursor::new(Vec::new()));
+        for (name, content) in files {
+            writer
+                .start_file(*name, SimpleFileOptions::default())
+                .unwrap();
+            writer.write_all(content).unwrap();
+        }
+        writer.finish().unwrap().into_inner()
+    }
+
+    fn png() -> Vec<u8> {
+        let mut data = Vec::new();
+        image::RgbImage::new(1, 1)
+            .write_to(&mut Cursor::new(&mut data), image::ImageFormat::Png)
+            .unwrap();
+        data
+    }
+
+    fn columns(headers: &[&str]) -> HashMap<Column, usize> {
+        headers
+            .iter()
+            .enumerate()
+            .map(|(i, header)| (Column::from_header(header).unwrap(), i))
+            .collect()
+    }
+
+    fn row(values: &[&str]) -> Vec<String> {
+        values.iter().map(|value| value.to_string()).collect()
+    }
+
+    #[test]
+    fn read_csv_with_bom() {
+        let table = read_table("\u{feff}姓名,性别\n张三,男\n李四\n".as_bytes()).unwrap();
+        assert_eq!(
+            table,
+            vec![row(&["姓名", "性别"]), row(&["张三", "男"]), row(&["李四"])]
+        );
+    }
+
+    #[test]
+    fn read_xlsx() {
+        let mut workbook = rust_xlsxwriter::Workbook::new();
+        let worksheet = workbook.add_worksheet();
+        worksheet.write_string(0, 0, "name").unwrap();
+        worksheet.write_string(0, 1, "phone").unwrap();
+        worksheet.write_string(1, 0, "张三").unwrap();
+        worksheet.write_number(1, 1, 13800138000.0).unwrap();
+        let data = workbook.save_to_buffer().unwrap();
+        assert_eq!(TableFormat::detect(&data), TableFormat::Xlsx);
+        let table = read_table(&data).unwrap();
+        assert_eq!(
+            table,
+            vec![row(&["name", "phone"]), row(&["张三", "13800138000"])]
+        );
+    }
+
+    #[test]
+    fn cell_to_string_formats_numbers_and_dates() {
+        assert_eq!(cell_to_string(&Data::Empty), "");
+        assert_eq!(cell_to_string(&Data::Float(12345.0)), "12345");
+        assert_eq!(cell_to_string(&Data::Float(1.5)), "1.5");
+        assert_eq!(cell_to_string(&Data::String("qq".to_string())), "qq");
+        assert_eq!(
+            cell_to_string(&Data::DateTimeIso("2000-05-03T00:00:00".to_string())),
+            "2000-05-03"
+        );
+    }
+
+    #[test]
+    fn column_from_header() {
+        assert_eq!(Column::from_header(" Name "), Some(Column::Name));
+        assert_eq!(Column::from_header("出生日期"), Some(Column::Birthday));
+        assert_eq!(Column::from_header("微信号"), Some(Column::Wechat));
+        assert_eq!(Column::from_header("QQ"), Some(Column::Qq));
+        assert_eq!(Column::from_header("备注"), None);
+    }
+
+    #[test]
+    fn validate_valid_row() {
+        let columns = columns(&["姓名", "性别", "生日", "邮箱", "照片"]);
+        let photos = PhotoArchive::read(&zip(&[("dir/a.png", &png())])).unwrap();
+        let person = validate_row(
+            &columns,
+            &row(&["张三", "男", "2000/05/03", "a@example.com", "a.png"]),
+            Some(&photos),
+        )
+        .unwrap();
+        assert_eq!(person.name, "张三");
+        assert_eq!(person.gender, Gender::Male);
+        assert_eq!(person.birthday, NaiveDate::from_ymd_opt(2000, 5, 3));
+        assert_eq!(person.email.as_deref(), Some("a@example.com"));
+        assert!(person.photo.is_some());
+    }
+
+    #[test]
+    fn validate_month_day_birthday() {
+        let columns = columns(&["name", "gender", "birthday"]);
+        let person = validate_row(&columns, &row(&["张三", "female", "--05-03"]), None).unwrap();
+        assert_eq!(person.birthday, None);
+        assert!(validate_row(&columns, &row(&["张三", "female", "--13-03"]), None).is_err());
+    }
+
+    #[test]
+    fn validate_invalid_row() {
+        let columns = columns(&["name", "gender", "birthday", "email", "photo"]);
+        let errors = validate_row(
+            &columns,
+            &row(&["张", "unknown", "2000-13-01", "invalid", "missing.png"]),
+            None,
+        )
+        .err()
+        .unwrap();
+        assert_eq!(errors.len(), 5);
+        assert!(errors.contains(&"Invalid gender: unknown".to_string()));
+        assert!(errors.contains(&"Photo not found: missing.png".to_string()));
+        let errors = validate_row(&columns, &row(&["张三"]), None).err().unwrap();
+        assert_eq!(errors, vec!["Gender is required".to_string()]);
+    }
+
+    #[test]
+    fn photo_archive_rejects_large_entry() {
+        let data = zip(&[("a.png", &vec![0; MAX_PHOTO_SIZE as usize + 1])]);
+        assert_eq!(
+            PhotoArchive::read(&data).err(),
+            Some("Photo too large: a.png".to_string())
+        );
+    }
+
+    #[test]
+    fn photo_archive_rejects_too_many_entries() {
+        let names: Vec<String> = (0..=MAX_PHOTO_ENTRIES)
+            .map(|i| format!("{}.png", i))
+            .collect();
+        let files: Vec<(&str, &[u8])> = names.iter().map(|name| (name.as_str(), &[][..])).collect();
+        assert!(PhotoArchive::read(&zip(&files)).is_err());
+    }
+}
@@ -1,3 +1,18 @@
+mod claim;
+mod export;
+mod history;
+pub mod import;
 mod routes;
+mod school;
+mod visibility;
 
-pub use routes::routes;
+pub fn routes() -> Vec<rocket::Route> {
+    let mut routes = routes::routes();
+    routes.extend(claim::routes());
+    routes.extend(export::routes());
+    routes.extend(history::routes());
+    routes.extend(import::routes());
+    routes.extend(school::routes());
+    routes.extend(visibility::routes());
+    routes
+}
@@ -1,15 +1,34 @@
+use crate::visibility::{viewer, visible_column};
+use account::guards::{Require, UserId};
+use account::rbac::perm;
 use chrono::{NaiveDate, Utc};
-use entity::person::{Gender, PersonProfile};
+use entity::person::{name_pinyin, Gender, Person, PersonProfile, Viewer};
 use image_service::utils::open_image;
 use image_service::{ImageServices, S3Client};
 use rocket::fs::TempFile;
+use rocket::http::Status;
 use rocket::serde::json::Json;
-use rocket::{post, routes, FromForm, State};
-use sqlx::PgPool;
+use rocket::serde::Serialize;
+use rocket::{delete, get, patch, post, put, routes, FromForm, FromFormField, Responder, State};
+use sqlx::{FromRow, PgPool, Postgres, QueryBuilder};
+use utils::generate_partial_form;
 use utils::guards::{ValidateError, ValidatedForm, ValidatedFormResult};
+use utils::pagination::{Page, Pagination};
+use utils::query::like_pattern;
 use utils::validate_opt;
 use utils::validators::{is_email, is_image_file, is_phone_number, is_ymd_date};
 
+/// 统一保存为 E.164 格式，默认国家为中国
+pub(crate) fn normalize_phone(phone: &str) -> String {
+    phonenumber::parse(Some(phonenumber::country::CN), phone.trim())
+        .unwrap()
+        .to_string()
+}
+
+pub(crate) fn parse_date(date: &str) -> NaiveDate {
+    NaiveDate::parse_from_str(date, "%Y-%m-%d").unwrap()
+}
+
 #[derive(FromForm)]
 struct CreatePersonReq<'r> {
     #[field(validate = len(2..31).or_else(msg!("Person name length must be between 2 and 32 characters")))]
@@ -34,10 +53,12 @@ async fn create_person(
     s3_client: &State<S3Client>,
     pool: &State<PgPool>,
     image_services: &State<ImageServices>,
+    user: Require<perm::PersonWrite>,
     data: ValidatedFormResult<CreatePersonReq<'_>>,
 ) -> Result<Json<PersonProfile>, ValidateError> {
     let ValidatedForm(data) = data?;
-    let mut query = sqlx::QueryBuilder::new("INSERT INTO person (name, gender, ");
+    let mut query =
+        sqlx::QueryBuilder::new("INSERT INTO person (name, name_pinyin, name_initials, gender, ");
     if data.birthday.is_some() {
         query.push("birthday, ");
     }
@@ -57,20 +78,21 @@ async fn create_person(
         query.push("photo_id, ");
     }
     query.push("created_at, updated_at) VALUES (");
+    let (pinyin, initials) = name_pinyin(&data.name);
     query.push_bind(&data.name);
     query.push(", ");
+    query.push_bind(pinyin);
+    query.push(", ");
+    query.push_bind(initials);
+    query.push(", ");
     query.push_bind(&data.gender);
     query.push(", ");
     if let Some(birthday) = data.birthday {
-        query.push_bind(NaiveDate::parse_from_str(&birthday, "%Y-%m-%d").unwrap());
+        query.push_bind(parse_date(&birthday));
         query.push(", ");
     }
     if let Some(phone) = data.phone {
-        query.push_bind(
-            phonenumber::parse(Some(phonenumber::country::CN), phone.trim())
-                .unwrap()
-                .to_string(),
-        );
+        query.push_bind(normalize_phone(&phone));
         query.push(", ");
     }
     if let Some(email) = data.email {
@@ -96,15 +118,16 @@ async fn create_person(
         query.push(", ");
     }
     let now = Utc::now();
-    query.push_bind(&now);
+    query.push_bind(now);
     query.push(", ");
-    query.push_bind(&now);
+    query.push_bind(now);
     query.push(") RETURNING *");
     let mut person = query
         .build_query_as::<PersonProfile>()
         .fetch_one(pool.inner())
         .await
         .unwrap();
+    person.mask(&viewer(pool.inner(), user.0.id).await);
     person
         .sign(&image_services.person_photo, &s3_client.external)
         .await
@@ -112,6 +135,731 @@ async fn create_person(
     Ok(Json(person))
 }
 
+/// 列表查询参数
+///
+/// `has_*` 为 `true` 时只返回填写了该字段的记录，为 `false` 时只返回未填写的记录
+#[derive(FromForm)]
+pub(crate) struct ListPersonsReq {
+    gender: Option<Gender>,
+    /// 生日下限 YYYY-MM-DD (含)
+    #[field(validate = validate_opt!(is_ymd_date())())]
+    birthday_from: Option<String>,
+    /// 生日上限 YYYY-MM-DD (含)
+    #[field(validate = validate_opt!(is_ymd_date())())]
+    birthday_to: Option<String>,
+    has_photo: Option<bool>,
+    has_phone: Option<bool>,
+    has_email: Option<bool>,
+    has_qq: Option<bool>,
+    has_wechat: Option<bool>,
+    /// 只返回该学校的班级成员
+    school_id: Option<i32>,
+    /// 只返回该届的班级成员
+    cohort_id: Option<i32>,
+    /// 只返回该班级的成员
+    class_id: Option<i32>,
+    /// 当前工作单位包含
+    employer: Option<String>,
+    /// 当前工作城市包含
+    city: Option<String>,
+    /// 就读过的学校包含
+    university: Option<String>,
+    /// `name` (按拼音) / `birthday` / `created_at`, 默认 `created_at`
+    sort: Option<PersonSort>,
+    /// `asc` / `desc`, 默认 `asc`; 生日为空的记录总是排在最后
+    order: Option<SortOrder>,
+    /// 同 [`Pagination`]
+    #[field(default = 1, validate = range(1..))]
+    page: i64,
+    #[field(default = 20, validate = range(1..=100))]
+    per_page: i64,
+}
+
+impl ListPersonsReq {
+    fn pagination(&self) -> Pagination {
+        Pagination {
+            page: self.page,
+            per_page: self.per_page,
+        }
+    }
+
+    pub(crate) fn push_conditions<'a>(
+        &'a self,
+        query: &mut QueryBuilder<'a, Postgres>,
+        viewer: &Viewer,
+    ) {
+        query.push(" WHERE TRUE");
+        if let Some(gender) = &self.gender {
+            query.push(" AND gender = ");
+            query.push_bind(gender);
+        }
+        if let Some(birthday_from) = &self.birthday_from {
+            query.push(format!(" AND {} >= ", visible_column("birthday", viewer)));
+            query.push_bind(parse_date(birthday_from));
+        }
+        if let Some(birthday_to) = &self.birthday_to {
+            query.push(format!(" AND {} <= ", visible_column("birthday", viewer)));
+            query.push_bind(parse_date(birthday_to));
+        }
+        for (column, has) in [
+            ("photo_id", self.has_photo),
+            ("phone", self.has_phone),
+            ("email", self.has_email),
+            ("qq", self.has_qq),
+            ("wechat", self.has_wechat),
+        ] {
+            let column = visible_column(column, viewer);
+            match has {
+                Some(true) => query.push(format!(" AND NULLIF({}, '') IS NOT NULL", column)),
+                Some(false) => query.push(format!(" AND NULLIF({}, '') IS NULL", column)),
+                None => query,
+            };
+        }
+        if self.school_id.is_some() || self.cohort_id.is_some() || self.class_id.is_some() {
+            query.push(
+                " AND EXISTS (SELECT 1 FROM class_member \
+                 JOIN school_class ON school_class.id = class_member.class_id \
+                 JOIN cohort ON cohort.id = school_class.cohort_id \
+                 WHERE class_member.person_id = person.id",
+            );
+            if let Some(school_id) = self.school_id {
+                query.push(" AND cohort.school_id = ");
+                query.push_bind(school_id);
+            }
+            if let Some(cohort_id) = self.cohort_id {
+                query.push(" AND cohort.id = ");
+                query.push_bind(cohort_id);
+            }
+            if let Some(class_id) = self.class_id {
+                query.push(" AND school_class.id = ");
+                query.push_bind(class_id);
+            }
+            query.push(")");
+        }
+        // 结束日期为空或未到时视为当前工作
+        for (column, value) in [("organization", &self.employer), ("city", &self.city)] {
+            if let Some(value) = value.as_deref().map(str::trim).filter(|v| !v.is_empty()) {
+                query.push(format!(
+                    " AND EXISTS (SELECT 1 FROM person_employment \
+                     WHERE person_employment.person_id = person.id \
+                     AND (end_date IS NULL OR end_date >= CURRENT_DATE) AND {} ILIKE ",
+                    column
+                ));
+                query.push_bind(like_pattern(value));
+                query.push(")");
+            }
+        }
+        if let Some(university) = self
+            .university
+            .as_deref()
+            .map(str::trim)
+            .filter(|v| !v.is_empty())
+        {
+            query.push(
+                " AND EXISTS (SELECT 1 FROM person_education \
+                 WHERE person_education.person_id = person.id AND institution ILIKE ",
+            );
+            query.push_bind(like_pattern(university));
+            query.push(")");
+        }
+    }
+}
+
+/// 排序字段
+#[derive(Clone, Copy, Debug, Default, FromFormField)]
+enum PersonSort {
+    Name,
+    Birthday,
+    #[default]
+    #[field(value = "created_at")]
+    CreatedAt,
+}
+
+/// 排序方向
+#[derive(Clone, Copy, Debug, Default, FromFormField)]
+enum SortOrder {
+    #[default]
+    Asc,
+    Desc,
+}
+
+/// 人员列表
+#[get("/person?<req..>")]
+async fn list_persons(
+    s3_client: &State<S3Client>,
+    pool: &State<PgPool>,
+    image_services: &State<ImageServices>,
+    user: Require<perm::PersonRead>,
+    req: ListPersonsReq,
+) -> Json<Page<PersonProfile>> {
+    let viewer = viewer(pool.inner(), user.0.id).await;
+    let pagination = req.pagination();
+    let mut query = QueryBuilder::new("SELECT * FROM person");
+    req.push_conditions(&mut query, &viewer);
+    query.push(" ORDER BY ");
+    query.push(match req.sort.unwrap_or_default() {
+        PersonSort::Name => "name_pinyin".to_string(),
+        PersonSort::Birthday => visible_column("birthday", &viewer),
+        PersonSort::CreatedAt => "created_at".to_string(),
+    });
+    query.push(match req.order.unwrap_or_default() {
+        SortOrder::Asc => " ASC NULLS LAST, id ASC",
+        SortOrder::Desc => " DESC NULLS LAST, id DESC",
+    });
+    query.push(" LIMIT ");
+    query.push_bind(pagination.limit());
+    query.push(" OFFSET ");
+    query.push_bind(pagination.offset());
+    let mut items = query
+        .build_query_as::<PersonProfile>()
+        .fetch_all(pool.inner())
+        .await
+        .unwrap();
+    for person in &mut items {
+        person.mask(&viewer);
+    }
+    PersonProfile::sign_all(
+        &mut items,
+        &image_services.person_photo,
+        &s3_client.external,
+    )
+    .await
+    .unwrap();
+
+    let mut query = QueryBuilder::new("SELECT COUNT(*) FROM person");
+    req.push_conditions(&mut query, &viewer);
+    let total: i64 = query
+        .build_query_scalar()
+        .fetch_one(pool.inner())
+        .await
+        .unwrap();
+    Json(Page::new(items, total, pagination))
+}
+
+/// 搜索结果
+#[derive(Serialize, FromRow)]
+#[serde(crate = "rocket::serde")]
+struct PersonSearchResult {
+    #[serde(flatten)]
+    #[sqlx(flatten)]
+    person: PersonProfile,
+    /// 相关度 0 ~ 1
+    rank: f32,
+}
+
+/// 搜索条件
+///
+/// `$1` 输入, `$2` 包含输入的 LIKE 模式, `$3` 拼音, `$4` 手机号 LIKE 模式,
+/// `$5` / `$6` / `$7` 学校 / 届 / 班级 ID (可为 NULL)
+fn search_conditions(viewer: &Viewer) -> String {
+    format!(
+        r#"
+    (name ILIKE $2
+       OR name % $1
+       OR ($3 <> '' AND (name_pinyin LIKE $3 || '%' OR ' ' || name_initials LIKE '% ' || $3 || '%' OR $3 <% name_pinyin))
+       OR ($4 <> '' AND {phone} LIKE $4)
+       OR {email} ILIKE $2
+       OR {qq} ILIKE $2
+       OR {wechat} ILIKE $2)
+    AND (($5::INT IS NULL AND $6::INT IS NULL AND $7::INT IS NULL) OR EXISTS (
+        SELECT 1 FROM class_member
+        JOIN school_class ON school_class.id = class_member.class_id
+        JOIN cohort ON cohort.id = school_class.cohort_id
+        WHERE class_member.person_id = person.id
+          AND ($5::INT IS NULL OR cohort.school_id = $5)
+          AND ($6::INT IS NULL OR cohort.id = $6)
+          AND ($7::INT IS NULL OR school_class.id = $7)))
+"#,
+        phone = visible_column("phone", viewer),
+        email = visible_column("email", viewer),
+        qq = visible_column("qq", viewer),
+        wechat = visible_column("wechat", viewer),
+    )
+}
+
+/// 搜索人员
+///
+/// 按相关度排序，匹配：
+/// - 姓名：包含或 `pg_trgm` 相似
+/// - 拼音：全拼前缀或相似，首字母前缀 (如 `zhs` / `zs` 匹配 张三)
+/// - 手机号包含输入的数字 (至少 4 位)
+/// - 邮箱、QQ、微信包含输入
+///
+/// 可用 `school_id` / `cohort_id` / `class_id` 限定范围
+#[get("/person/search?<q>&<school_id>&<cohort_id>&<class_id>&<pagination..>")]
+#[allow(clippy::too_many_arguments)]
+async fn search_persons(
+    s3_client: &State<S3Client>,
+    pool: &State<PgPool>,
+    image_services: &State<ImageServices>,
+    user: Require<perm::PersonRead>,
+    q: &str,
+    school_id: Option<i32>,
+    cohort_id: Option<i32>,
+    class_id: Option<i32>,
+    pagination: Pagination,
+) -> Result<Json<Page<PersonSearchResult>>, (Status, &'static str)> {
+    let q = q.trim();
+    if q.is_empty() {
+        return Err((Status::BadRequest, "Empty search query"));
+    }
+    let pattern = like_pattern(q);
+    // 只有字母和空格时按拼音匹配
+    let pinyin: String = if q.chars().all(|c| c.is_ascii_alphabetic() || c == ' ') {
+        q.chars()
+            .filter(|c| c.is_ascii_alphabetic())
+            .map(|c| c.to_ascii_lowercase())
+            .collect()
+    } else {
+        String::new()
+    };
+    let digits: String = q.chars().filter(char::is_ascii_digit).collect();
+    let phone = if digits.len() >= 4 {
+        format!("%{}%", digits)
+    } else {
+        String::new()
+    };
+
+    let viewer = viewer(pool.inner(), user.0.id).await;
+    let conditions = search_conditions(&viewer);
+    let sql = format!(
+        r#"
+        SELECT * FROM (
+            SELECT *, GREATEST(
+                CASE WHEN name = $1 THEN 1.0 ELSE 0 END,
+                CASE WHEN name ILIKE $2 THEN 0.9 ELSE 0 END,
+                similarity(name, $1),
+                CASE WHEN $3 <> '' AND name_pinyin = $3 THEN 0.9 ELSE 0 END,
+                CASE WHEN $3 <> '' AND name_pinyin LIKE $3 || '%' THEN 0.8 ELSE 0 END,
+                CASE WHEN $3 <> '' AND ' ' || name_initials || ' ' LIKE '% ' || $3 || ' %' THEN 0.85 ELSE 0 END,
+                CASE WHEN $3 <> '' AND ' ' || name_initials LIKE '% ' || $3 || '%' THEN 0.7 ELSE 0 END,
+                CASE WHEN $3 <> '' THEN word_similarity($3, name_pinyin) * 0.7 ELSE 0 END,
+                CASE WHEN $4 <> '' AND {phone} LIKE $4 THEN 0.8 ELSE 0 END,
+                CASE WHEN {email} ILIKE $2 OR {qq} ILIKE $2 OR {wechat} ILIKE $2 THEN 0.8 ELSE 0 END
+            )::REAL AS rank
+            FROM person
+            WHERE {conditions}
+        ) AS result
+        ORDER BY rank DESC, id
+        LIMIT $8 OFFSET $9
+        "#,
+        phone = visible_column("phone", &viewer),
+        email = visible_column("email", &viewer),
+        qq = visible_column("qq", &viewer),
+        wechat = visible_column("wechat", &viewer),
+    );
+    let mut items = sqlx::query_as::<_, PersonSearchResult>(&sql)
+        .bind(q)
+        .bind(&pattern)
+        .bind(&pinyin)
+        .bind(&phone)
+        .bind(school_id)
+        .bind(cohort_id)
+        .bind(class_id)
+        .bind(pagination.limit())
+        .bind(pagination.offset())
+        .fetch_all(pool.inner())
+        .await
+        .unwrap();
+    let total: i64 =
+        sqlx::query_scalar(&format!("SELECT COUNT(*) FROM person WHERE {}", conditions))
+            .bind(q)
+            .bind(&pattern)
+            .bind(&pinyin)
+            .bind(&phone)
+            .bind(school_id)
+            .bind(cohort_id)
+            .bind(class_id)
+            .fetch_one(pool.inner())
+            .await
+            .unwrap();
+
+    for item in &mut items {
+        item.person.mask(&viewer);
+    }
+    PersonProfile::sign_all(
+        items.iter_mut().map(|item| &mut item.person),
+        &image_services.person_photo,
+        &s3_client.external,
+    )
+    .await
+    .unwrap();
+    Ok(Json(Page::new(items, total, pagination)))
+}
+
+#[get("/person/<id>")]
+async fn get_person(
+    s3_client: &State<S3Client>,
+    pool: &State<PgPool>,
+    image_services: &State<ImageServices>,
+    user: Require<perm::PersonRead>,
+    id: i32,
+) -> Result<Json<PersonProfile>, (Status, &'static str)> {
+    let person = Person::find(pool.inner(), id)
+        .await
+        .unwrap()
+        .ok_or((Status::NotFound, "Person not found"))?;
+    let mut person = PersonProfile::from_person(person);
+    person.mask(&viewer(pool.inner(), user.0.id).await);
+    person
+        .sign(&image_services.person_photo, &s3_client.external)
+        .await
+        .unwrap();
+    Ok(Json(person))
+}
+
+/// 修改失败
+#[derive(Responder)]
+enum UpdatePersonError {
+    Invalid(ValidateError),
+    NotFound((Status, &'static str)),
+}
+
+impl From<ValidateError> for UpdatePersonError {
+    fn from(error: ValidateError) -> Self {
+        Self::Invalid(error)
+    }
+}
+
+generate_partial_form! {
+    #[derive(FromForm)]
+    struct UpdatePersonReq<'r> {
+        #[validate(len(2..31).or_else(msg!("Person name length must be between 2 and 32 characters")))]
+        name: String,
+        /// 生日 YYYY-MM-DD
+        #[validate(validate_opt!(is_ymd_date())())]
+        birthday: Option<String>,
+        gender: Gender,
+        #[validate(validate_opt!(is_image_file())())]
+        photo: Option<TempFile<'r>>,
+        #[validate(validate_opt!(is_phone_number())())]
+        phone: Option<String>,
+        #[validate(validate_opt!(is_email())())]
+        email: Option<String>,
+        qq: Option<String>,
+        wechat: Option<String>,
+    }
+}
+
+/// 上传新照片并返回其 key
+async fn upload_photo(
+    s3_client: &S3Client,
+    image_services: &ImageServices,
+    photo: &TempFile<'_>,
+) -> String {
+    let photo = open_image(photo.path().unwrap());
+    image_services
+        .person_photo
+        .upload_image(&s3_client.internal, photo)
+        .await
+        .unwrap()
+}
+
+/// 修改失败时删除本次上传的照片，失败只记录日志
+async fn discard_photo(s3_client: &S3Client, image_services: &ImageServices, key: &str) {
+    if let Err(e) = image_services
+        .person_photo
+        .delete_image(&s3_client.internal, &key.to_string())
+        .await
+    {
+        eprintln!("Cannot delete photo {}: {:?}", key, e);
+    }
+}
+
+/// 修改完成后删除被替换的旧照片并签名新照片
+async fn finish_update(
+    s3_client: &S3Client,
+    image_services: &ImageServices,
+    old: &Person,
+    person: Person,
+    viewer: &Viewer,
+) -> PersonProfile {
+    if let Some(old_key) = &old.photo_id
+        && old.photo_id != person.photo_id
+    {
+        image_services
+            .person_photo
+            .delete_image(&s3_client.internal, old_key)
+            .await
+            .unwrap();
+    }
+    let mut person = PersonProfile::from_person(person);
+    person.mask(viewer);
+    person
+        .sign(&image_services.person_photo, &s3_client.external)
+        .await
+        .unwrap();
+    person
+}
+
+/// 替换全部信息
+async fn replace_person(
+    pool: &PgPool,
+    s3_client: &S3Client,
+    image_services: &ImageServices,
+    old: Person,
+    data: UpdatePersonReq<'_>,
+    viewer: &Viewer,
+) -> PersonProfile {
+    let new_photo = match &data.photo {
+        Some(photo) => Some(upload_photo(s3_client, image_services, photo).await),
+        None => None,
+    };
+    let photo_id = new_photo.clone().or_else(|| old.photo_id.clone());
+    let (pinyin, initials) = name_pinyin(&data.name);
+    let result = sqlx::query_as::<_, Person>(
+        r#"UPDATE person SET name=$1, name_pinyin=$2, name_initials=$3, birthday=$4, gender=$5, photo_id=$6, phone=$7, email=$8, qq=$9, wechat=$10 WHERE id=$11 RETURNING *"#,
+    )
+    .bind(&data.name)
+    .bind(pinyin)
+    .bind(initials)
+    .bind(data.birthday.as_deref().map(parse_date))
+    .bind(&data.gender)
+    .bind(photo_id)
+    .bind(data.phone.as_deref().map(normalize_phone))
+    .bind(&data.email)
+    .bind(&data.qq)
+    .bind(&data.wechat)
+    .bind(old.id)
+    .fetch_one(pool)
+    .await;
+    if result.is_err()
+        && let Some(key) = &new_photo
+    {
+        discard_photo(s3_client, image_services, key).await;
+    }
+    let person = result.unwrap();
+    finish_update(s3_client, image_services, &old, person, viewer).await
+}
+
+/// 修改提供的字段
+async fn patch_person(
+    pool: &PgPool,
+    s3_client: &S3Client,
+    image_services: &ImageServices,
+    old: Person,
+    data: PartialUpdatePersonReq<'_>,
+    viewer: &Viewer,
+) -> PersonProfile {
+    let mut query = sqlx::QueryBuilder::new("UPDATE person SET ");
+    if let Some(name) = data.name {
+        let (pinyin, initials) = name_pinyin(&name);
+        query.push("name=");
+        query.push_bind(name);
+        query.push(", name_pinyin=");
+        query.push_bind(pinyin);
+        query.push(", name_initials=");
+        query.push_bind(initials);
+        query.push(", ");
+    }
+    if let Some(birthday) = data.birthday.flatten() {
+        query.push("birthday=");
+        query.push_bind(parse_date(&birthday));
+        query.push(", ");
+    }
+    if let Some(gender) = data.gender {
+        query.push("gender=");
+        query.push_bind(gender);
+        query.push(", ");
+    }
+    let mut new_photo = None;
+    if let Some(photo) = data.photo.flatten() {
+        let key = upload_photo(s3_client, image_services, &photo).await;
+        query.push("photo_id=");
+        query.push_bind(key.clone());
+        query.push(", ");
+        new_photo = Some(key);
+    }
+    if let Some(phone) = data.phone.flatten() {
+        query.push("phone=");
+        query.push_bind(normalize_phone(&phone));
+        query.push(", ");
+    }
+    if let Some(email) = data.email.flatten() {
+        query.push("email=");
+        query.push_bind(email);
+        query.push(", ");
+    }
+    if let Some(qq) = data.qq.flatten() {
+        query.push("qq=");
+        query.push_bind(qq);
+        query.push(", ");
+    }
+    if let Some(wechat) = data.wechat.flatten() {
+        query.push("wechat=");
+        query.push_bind(wechat);
+        query.push(", ");
+    }
+    query.push("updated_at=");
+    query.push_bind(Utc::now());
+    query.push(" WHERE id=");
+    query.push_bind(old.id);
+    query.push(" RETURNING *");
+    let result = query.build_query_as::<Person>().fetch_one(pool).await;
+    if result.is_err()
+        && let Some(key) = &new_photo
+    {
+        discard_photo(s3_client, image_services, key).await;
+    }
+    let person = result.unwrap();
+    finish_update(s3_client, image_services, &old, person, viewer).await
+}
+
+/// 替换全部信息
+///
+/// 未上传 `photo` 时保留原照片，其余未提供的可选字段会被清空
+#[put("/person/<id>", data = "<data>")]
+async fn update_person(
+    s3_client: &State<S3Client>,
+    pool: &State<PgPool>,
+    image_services: &State<ImageServices>,
+    user: Require<perm::PersonWrite>,
+    id: i32,
+    data: ValidatedFormResult<UpdatePersonReq<'_>>,
+) -> Result<Json<PersonProfile>, UpdatePersonError> {
+    let ValidatedForm(data) = data?;
+    let old = Person::find(pool.inner(), id)
+        .await
+        .unwrap()
+        .ok_or(UpdatePersonError::NotFound((
+            Status::NotFound,
+            "Person not found",
+        )))?;
+    let viewer = viewer(pool.inner(), user.0.id).await;
+    Ok(Json(
+        replace_person(pool, s3_client, image_services, old, data, &viewer).await,
+    ))
+}
+
+/// 修改部分信息
+///
+/// 只修改提供的字段
+#[patch("/person/<id>", data = "<data>")]
+async fn partial_update_person(
+    s3_client: &State<S3Client>,
+    pool: &State<PgPool>,
+    image_services: &State<ImageServices>,
+    user: Require<perm::PersonWrite>,
+    id: i32,
+    data: ValidatedFormResult<PartialUpdatePersonReq<'_>>,
+) -> Result<Json<PersonProfile>, UpdatePersonError> {
+    let ValidatedForm(data) = data?;
+    let old = Person::find(pool.inner(), id)
+        .await
+        .unwrap()
+        .ok_or(UpdatePersonError::NotFound((
+            Status::NotFound,
+            "Person not found",
+        )))?;
+    let viewer = viewer(pool.inner(), user.0.id).await;
+    Ok(Json(
+        patch_person(pool, s3_client, image_services, old, data, &viewer).await,
+    ))
+}
+
+/// 当前账户认领的人员
+#[get("/account/person")]
+async fn own_person(
+    s3_client: &State<S3Client>,
+    pool: &State<PgPool>,
+    image_services: &State<ImageServices>,
+    user: UserId,
+) -> Result<Json<PersonProfile>, (Status, &'static str)> {
+    let person = Person::of_user(pool.inner(), user.0)
+        .await
+        .unwrap()
+        .ok_or((Status::NotFound, "No linked person"))?;
+    let mut person = PersonProfile::from_person(person);
+    person.mask(&viewer(pool.inner(), user.0).await);
+    person
+        .sign(&image_services.person_photo, &s3_client.external)
+        .await
+        .unwrap();
+    Ok(Json(person))
+}
+
+/// 修改自己认领的人员，同 `PUT /person/<id>`
+#[put("/account/person", data = "<data>")]
+async fn update_own_person(
+    s3_client: &State<S3Client>,
+    pool: &State<PgPool>,
+    image_services: &State<ImageServices>,
+    user: UserId,
+    data: ValidatedFormResult<UpdatePersonReq<'_>>,
+) -> Result<Json<PersonProfile>, UpdatePersonError> {
+    let ValidatedForm(data) = data?;
+    let old =
+        Person::of_user(pool.inner(), user.0)
+            .await
+            .unwrap()
+            .ok_or(UpdatePersonError::NotFound((
+                Status::NotFound,
+                "No linked person",
+            )))?;
+    let viewer = viewer(pool.inner(), user.0).await;
+    Ok(Json(
+        replace_person(pool, s3_client, image_services, old, data, &viewer).await,
+    ))
+}
+
+/// 修改自己认领的人员，同 `PATCH /person/<id>`
+#[patch("/account/person", data = "<data>")]
+async fn partial_update_own_person(
+    s3_client: &State<S3Client>,
+    pool: &State<PgPool>,
+    image_services: &State<ImageServices>,
+    user: UserId,
+    data: ValidatedFormResult<PartialUpdatePersonReq<'_>>,
+) -> Result<Json<PersonProfile>, UpdatePersonError> {
+    let ValidatedForm(data) = data?;
+    let old =
+        Person::of_user(pool.inner(), user.0)
+            .await
+            .unwrap()
+            .ok_or(UpdatePersonError::NotFound((
+                Status::NotFound,
+                "No linked person",
+            )))?;
+    let viewer = viewer(pool.inner(), user.0).await;
+    Ok(Json(
+        patch_person(pool, s3_client, image_services, old, data, &viewer).await,
+    ))
+}
+
+#[delete("/person/<id>")]
+async fn delete_person(
+    s3_client: &State<S3Client>,
+    pool: &State<PgPool>,
+    image_services: &State<ImageServices>,
+    _user: Require<perm::PersonDelete>,
+    id: i32,
+) -> (Status, &'static str) {
+    match Person::delete(pool.inner(), id).await.unwrap() {
+        Some(person) => {
+            if let Some(key) = &person.photo_id {
+                image_services
+                    .person_photo
+                    .delete_image(&s3_client.internal, key)
+                    .await
+                    .unwrap();
+            }
+            (Status::Ok, "Success")
+        }
+        None => (Status::NotFound, "Person not found"),
+    }
+}
+
 pub fn routes() -> Vec<rocket::Route> {
-    routes![create_person]
+    routes![
+        create_person,
+        list_persons,
+        search_persons,
+        get_person,
+        update_person,
+        partial_update_person,
+        delete_person,
+        own_person,
+        update_own_person,
+        partial_update_own_person
+    ]
 }
@@ -0,0 +1,329 @@
+//! 学校、届、班级及班级成员
+use account::guards::Require;
+use account::rbac::perm;
+use entity::person::Person;
+use entity::school::{
+    ClassMember, ClassMemberInfo, ClassRole, Cohort, PersonClass, School, SchoolClass,
+};
+use rocket::form::Form;
+use rocket::http::Status;
+use rocket::serde::json::Json;
+use rocket::{delete, get, post, put, routes, FromForm, State};
+use sqlx::PgPool;
+
+/// 唯一约束冲突时返回 409
+fn unique<T>(
+    result: Result<T, sqlx::Error>,
+    message: &'static str,
+) -> Result<T, (Status, &'static str)> {
+    match result {
+        Ok(value) => Ok(value),
+        Err(sqlx::Error::Database(e)) if e.is_unique_violation() => {
+            Err((Status::Conflict, message))
+        }
+        Err(e) => panic!("{}", e),
+    }
+}
+
+fn deleted(deleted: bool, message: &'static str) -> (Status, &'static str) {
+    if deleted {
+        (Status::Ok, "Success")
+    } else {
+        (Status::NotFound, message)
+    }
+}
+
+#[derive(Debug, FromForm)]
+struct SchoolReq {
+    #[field(validate = len(1..=128).or_else(msg!("School name length must be between 1 and 128 characters")))]
+    name: String,
+}
+
+#[get("/schools")]
+async fn schools(pool: &State<PgPool>, _user: Require<perm::PersonRead>) -> Json<Vec<School>> {
+    Json(School::all(pool.inner()).await.unwrap())
+}
+
+#[post("/schools", data = "<data>")]
+async fn create_school(
+    pool: &State<PgPool>,
+    _user: Require<perm::PersonWrite>,
+    data: Form<SchoolReq>,
+) -> Result<Json<School>, (Status, &'static str)> {
+    unique(
+        School::create(pool.inner(), data.name.trim()).await,
+        "School existed",
+    )
+    .map(Json)
+}
+
+#[put("/schools/<id>", data = "<data>")]
+async fn update_school(
+    pool: &State<PgPool>,
+    _user: Require<perm::PersonWrite>,
+    id: i32,
+    data: Form<SchoolReq>,
+) -> Result<Json<School>, (Status, &'static str)> {
+    unique(
+        School::update(pool.inner(), id, data.name.trim()).await,
+        "School existed",
+    )?
+    .map(Json)
+    .ok_or((Status::NotFound, "School not found"))
+}
+
+/// 删除学校，其下的届、班级和班级成员一并删除
+#[delete("/schools/<id>")]
+async fn delete_school(
+    pool: &State<PgPool>,
+    _user: Require<perm::PersonDelete>,
+    id: i32,
+) -> (Status, &'static str) {
+    deleted(
+        School::delete(pool.inner(), id).await.unwrap(),
+        "School not found",
+    )
+}
+
+#[derive(Debug, FromForm)]
+struct CohortReq {
+    /// 毕业年份
+    #[field(validate = range(1900..=2100))]
+    graduation_year: i16,
+    /// 展示名称，默认为 "<毕业年份>届"
+    #[field(validate = len(..=64).or_else(msg!("Cohort name length must be at most 64 characters")))]
+    name: Option<String>,
+}
+
+impl CohortReq {
+    fn name(&self) -> String {
+        self.name
+            .as_deref()
+            .map(str::trim)
+            .filter(|name| !name.is_empty())
+            .map(str::to_string)
+            .unwrap_or_else(|| format!("{}届", self.graduation_year))
+    }
+}
+
+#[get("/schools/<id>/cohorts")]
+async fn cohorts(
+    pool: &State<PgPool>,
+    _user: Require<perm::PersonRead>,
+    id: i32,
+) -> Result<Json<Vec<Cohort>>, (Status, &'static str)> {
+    School::find(pool.inner(), id)
+        .await
+        .unwrap()
+        .ok_or((Status::NotFound, "School not found"))?;
+    Ok(Json(Cohort::of_school(pool.inner(), id).await.unwrap()))
+}
+
+#[post("/schools/<id>/cohorts", data = "<data>")]
+async fn create_cohort(
+    pool: &State<PgPool>,
+    _user: Require<perm::PersonWrite>,
+    id: i32,
+    data: Form<CohortReq>,
+) -> Result<Json<Cohort>, (Status, &'static str)> {
+    School::find(pool.inner(), id)
+        .await
+        .unwrap()
+        .ok_or((Status::NotFound, "School not found"))?;
+    unique(
+        Cohort::create(pool.inner(), id, data.graduation_year, &data.name()).await,
+        "Cohort existed",
+    )
+    .map(Json)
+}
+
+#[put("/cohorts/<id>", data = "<data>")]
+async fn update_cohort(
+    pool: &State<PgPool>,
+    _user: Require<perm::PersonWrite>,
+    id: i32,
+    data: Form<CohortReq>,
+) -> Result<Json<Cohort>, (Status, &'static str)> {
+    unique(
+        Cohort::update(pool.inner(), id, data.graduation_year, &data.name()).await,
+        "Cohort existed",
+    )?
+    .map(Json)
+    .ok_or((Status::NotFound, "Cohort not found"))
+}
+
+/// 删除届，其下的班级和班级成员一并删除
+#[delete("/cohorts/<id>")]
+async fn delete_cohort(
+    pool: &State<PgPool>,
+    _user: Require<perm::PersonDelete>,
+    id: i32,
+) -> (Status, &'static str) {
+    deleted(
+        Cohort::delete(pool.inner(), id).await.unwrap(),
+        "Cohort not found",
+    )
+}
+
+#[derive(Debug, FromForm)]
+struct ClassReq {
+    #[field(validate = len(1..=64).or_else(msg!("Class name length must be between 1 and 64 characters")))]
+    name: String,
+}
+
+#[get("/cohorts/<id>/classes")]
+async fn classes(
+    pool: &State<PgPool>,
+    _user: Require<perm::PersonRead>,
+    id: i32,
+) -> Result<Json<Vec<SchoolClass>>, (Status, &'static str)> {
+    Cohort::find(pool.inner(), id)
+        .await
+        .unwrap()
+        .ok_or((Status::NotFound, "Cohort not found"))?;
+    Ok(Json(
+        SchoolClass::of_cohort(pool.inner(), id).await.unwrap(),
+    ))
+}
+
+#[post("/cohorts/<id>/classes", data = "<data>")]
+async fn create_class(
+    pool: &State<PgPool>,
+    _user: Require<perm::PersonWrite>,
+    id: i32,
+    data: Form<ClassReq>,
+) -> Result<Json<SchoolClass>, (Status, &'static str)> {
+    Cohort::find(pool.inner(), id)
+        .await
+        .unwrap()
+        .ok_or((Status::NotFound, "Cohort not found"))?;
+    unique(
+        SchoolClass::create(pool.inner(), id, data.name.trim()).await,
+        "Class existed",
+    )
+    .map(Json)
+}
+
+#[put("/classes/<id>", data = "<data>")]
+async fn update_class(
+    pool: &State<PgPool>,
+    _user: Require<perm::PersonWrite>,
+    id: i32,
+    data: Form<ClassReq>,
+) -> Result<Json<SchoolClass>, (Status, &'static str)> {
+    unique(
+        SchoolClass::update(pool.inner(), id, data.name.trim()).await,
+        "Class existed",
+    )?
+    .map(Json)
+    .ok_or((Status::NotFound, "Class not found"))
+}
+
+/// 删除班级，班级成员一并删除
+#[delete("/classes/<id>")]
+async fn delete_class(
+    pool: &State<PgPool>,
+    _user: Require<perm::PersonDelete>,
+    id: i32,
+) -> (Status, &'static str) {
+    deleted(
+        SchoolClass::delete(pool.inner(), id).await.unwrap(),
+        "Class not found",
+    )
+}
+
+#[get("/classes/<id>/members")]
+async fn members(
+    pool: &State<PgPool>,
+    _user: Require<perm::PersonRead>,
+    id: i32,
+) -> Result<Json<Vec<ClassMemberInfo>>, (Status, &'static str)> {
+    SchoolClass::find(pool.inner(), id)
+        .await
+        .unwrap()
+        .ok_or((Status::NotFound, "Class not found"))?;
+    Ok(Json(ClassMember::of_class(pool.inner(), id).await.unwrap()))
+}
+
+#[derive(Debug, FromForm)]
+struct MemberReq {
+    /// `student` (默认) / `head_teacher` / `monitor`
+    #[field(default = ClassRole::Student)]
+    role: ClassRole,
+}
+
+/// 加入班级或修改班级身份
+#[put("/classes/<id>/members/<person_id>", data = "<data>")]
+async fn set_member(
+    pool: &State<PgPool>,
+    _user: Require<perm::PersonWrite>,
+    id: i32,
+    person_id: i32,
+    data: Form<MemberReq>,
+) -> Result<Json<ClassMember>, (Status, &'static str)> {
+    SchoolClass::find(pool.inner(), id)
+        .await
+        .unwrap()
+        .ok_or((Status::NotFound, "Class not found"))?;
+    Person::find(pool.inner(), person_id)
+        .await
+        .unwrap()
+        .ok_or((Status::NotFound, "Person not found"))?;
+    Ok(Json(
+        ClassMember::set(pool.inner(), id, person_id, &data.role)
+            .await
+            .unwrap(),
+    ))
+}
+
+#[delete("/classes/<id>/members/<person_id>")]
+async fn remove_member(
+    pool: &State<PgPool>,
+    _user: Require<perm::PersonWrite>,
+    id: i32,
+    person_id: i32,
+) -> (Status, &'static str) {
+    deleted(
+        ClassMember::remove(pool.inner(), id, person_id)
+            .await
+            .unwrap(),
+        "Member not found",
+    )
+}
+
+/// 人员所在的班级
+#[get("/person/<id>/classes")]
+async fn person_classes(
+    pool: &State<PgPool>,
+    _user: Require<perm::PersonRead>,
+    id: i32,
+) -> Result<Json<Vec<PersonClass>>, (Status, &'static str)> {
+    Person::find(pool.inner(), id)
+        .await
+        .unwrap()
+        .ok_or((Status::NotFound, "Person not found"))?;
+    Ok(Json(
+        ClassMember::of_person(pool.inner(), id).await.unwrap(),
+    ))
+}
+
+pub fn routes() -> Vec<rocket::Route> {
+    routes![
+        schools,
+        create_school,
+        update_school,
+        delete_school,
+        cohorts,
+        create_cohort,
+        update_cohort,
+        delete_cohort,
+        classes,
+        create_class,
+        update_class,
+        delete_class,
+        members,
+        set_member,
+        remove_member,
+        person_classes
+    ]
+}
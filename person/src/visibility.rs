@@ -0,0 +1,119 @@
+//! 联系方式和生日的可见性
+//!
+//! 返回 [`PersonProfile`](entity::person::PersonProfile) 前都应调用 `mask` 隐藏查看者无权看到的字段。
+//! 非管理员筛选、排序、搜索时只使用所有登录用户可见的字段，避免通过查询结果推断出隐藏的内容；
+//! 管理员同样不能通过查询匹配其他人设为 `hidden` 的字段
+use account::guards::{Require, UserId};
+use account::rbac::{perm, PermissionType};
+use entity::person::{Person, PersonVisibility, Viewer, Visibility};
+use entity::role::Permission;
+use rocket::form::Form;
+use rocket::http::Status;
+use rocket::serde::json::Json;
+use rocket::{patch, routes, FromForm, State};
+use sqlx::PgPool;
+
+/// 有 `person.write` 权限的账户视为管理员
+pub(crate) async fn viewer(pool: &PgPool, user_id: i32) -> Viewer {
+    let admin = Permission::user_has(pool, user_id, perm::PersonWrite::CODE)
+        .await
+        .unwrap();
+    Viewer::load(pool, user_id, admin).await.unwrap()
+}
+
+/// 查询中使用的列，非管理员时对所有登录用户不可见的值视为 NULL,
+/// 管理员时除本人外 `hidden` 的值视为 NULL
+pub(crate) fn visible_column(column: &str, viewer: &Viewer) -> String {
+    if viewer.is_admin() {
+        let owner = match viewer.user_id() {
+            Some(user_id) => format!("user_id = {}", user_id),
+            None => "FALSE".to_string(),
+        };
+        return match column {
+            "birthday" | "phone" | "email" | "qq" | "wechat" => format!(
+                "CASE WHEN {0}_visibility <> 'hidden' OR {1} THEN {0} END",
+                column, owner
+            ),
+            _ => column.to_string(),
+        };
+    }
+    match column {
+        // 只显示月日时年份不可用于筛选、排序
+        "birthday" => "CASE WHEN birthday_visibility IN ('public', 'logged_in') \
+                       AND NOT birthday_month_day_only THEN birthday END"
+            .to_string(),
+        "phone" | "email" | "qq" | "wechat" => format!(
+            "CASE WHEN {0}_visibility IN ('public', 'logged_in') THEN {0} END",
+            column
+        ),
+        _ => column.to_string(),
+    }
+}
+
+/// 修改可见性，未填写的字段保持不变
+///
+/// 可见性: `public` / `logged_in` / `classmates` / `admins` / `hidden`
+#[derive(Debug, FromForm)]
+struct UpdateVisibilityReq {
+    phone: Option<Visibility>,
+    email: Option<Visibility>,
+    qq: Option<Visibility>,
+    wechat: Option<Visibility>,
+    birthday: Option<Visibility>,
+    /// 生日只显示月日
+    birthday_month_day_only: Option<bool>,
+}
+
+async fn update_visibility(
+    pool: &PgPool,
+    person: Person,
+    data: &UpdateVisibilityReq,
+) -> PersonVisibility {
+    let old = person.visibility;
+    let visibility = PersonVisibility {
+        phone_visibility: data.phone.unwrap_or(old.phone_visibility),
+        email_visibility: data.email.unwrap_or(old.email_visibility),
+        qq_visibility: data.qq.unwrap_or(old.qq_visibility),
+        wechat_visibility: data.wechat.unwrap_or(old.wechat_visibility),
+        birthday_visibility: data.birthday.unwrap_or(old.birthday_visibility),
+        birthday_month_day_only: data
+            .birthday_month_day_only
+            .unwrap_or(old.birthday_month_day_only),
+    };
+    PersonVisibility::update(pool, person.id, &visibility)
+        .await
+        .unwrap();
+    visibility
+}
+
+#[patch("/person/<id>/visibility", data = "<data>")]
+async fn update_person_visibility(
+    pool: &State<PgPool>,
+    _user: Require<perm::PersonWrite>,
+    id: i32,
+    data: Form<UpdateVisibilityReq>,
+) -> Result<Json<PersonVisibility>, (Status, &'static str)> {
+    let person = Person::find(pool.inner(), id)
+        .await
+        .unwrap()
+        .ok_or((Status::NotFound, "Person not found"))?;
+    Ok(Json(update_visibility(pool.inner(), person, &data).await))
+}
+
+/// 修改自己认领的人员的可见性
+#[patch("/account/person/visibility", data = "<data>")]
+async fn update_own_visibility(
+    pool: &State<PgPool>,
+    user: UserId,
+    data: Form<UpdateVisibilityReq>,
+) -> Result<Json<PersonVisibility>, (Status, &'static str)> {
+    let person = Person::of_user(pool.inner(), user.0)
+        .await
+        .unwrap()
+        .ok_or((Status::NotFound, "No linked person"))?;
+    Ok(Json(update_visibility(pool.inner(), person, &data).await))
+}
+
+pub fn routes() -> Vec<rocket::Route> {
+    routes![update_person_visibility, update_own_visibility]
+}
@@ -13,7 +13,12 @@ extern crate rocket;
 
 use crate::tests::image;
 use account::auth::jwt::JWTConfig;
+use account::auth::lockout::LockoutConfig;
+use account::auth::oidc::OidcConfig;
+use account::auth::registration::RegistrationConfig;
 use email::EmailBackend;
+use entity::password::PasswordConfig;
+use entity::person::Person;
 use image_service::storage::create_client;
 use image_service::{ImageServices, S3Client};
 use sqlx::postgres::{PgConnectOptions, PgPoolOptions};
@@ -69,6 +74,8 @@ async fn rocket() -> _ {
 
     // 执行迁移
     sqlx::migrate!("./migrations").run(&db).await.unwrap();
+    // 为迁移前写入的人员生成搜索用拼音
+    Person::backfill_pinyin(&db).await.unwrap();
 
     // 初始化 MinIO
     let internal_endpoint = std::env::var("MINIO_ENDPOINT").expect("MINIO_ENDPOINT must be set");
@@ -109,7 +116,12 @@ async fn rocket() -> _ {
         .manage(s3_client)
         .manage(image_services)
         .manage(JWTConfig::from_env())
+        .manage(LockoutConfig::from_env())
+        .manage(OidcConfig::from_env())
+        .manage(RegistrationConfig::from_env())
+        .manage(PasswordConfig::from_env())
         .manage(email)
+        .attach(account::tasks::fairing())
         .mount("/", routes![index])
         .mount("/", account::routes())
         .mount("/", person::routes())
@@ -20,7 +20,7 @@ pub struct ValidatedForm<T>(pub T);
 /// # use utils::guards::{ValidateError, ValidatedForm, ValidatedFormResult};
 /// # type YourResp = ();
 /// # #[derive(FromForm)]
-/// # struct YourDataReq {}
+/// # struct YourDataReq { field: String }
 /// #
 /// #[post("/your_route", data="<data>")]
 /// async fn your_route(data: ValidatedFormResult<YourDataReq>) -> Result<YourResp, ValidateError> {
@@ -28,7 +28,7 @@ pub struct ValidatedForm<T>(pub T);
 ///
 ///     // your code
 ///
-///     Ok(YourResp {})
+///     Ok(())
 /// }
 /// ```
 ///
@@ -41,7 +41,7 @@ pub struct ValidatedForm<T>(pub T);
 /// # use utils::guards::{ValidateError, ValidatedForm, ValidatedFormResult};
 /// # type YourResp = ();
 /// # #[derive(FromForm)]
-/// # struct YourDataReq {}
+/// # struct YourDataReq { field: String }
 /// #
 ///
 /// #[post("/your_route", data="<data>")]
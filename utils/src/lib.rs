@@ -1,6 +1,8 @@
 //! Utils
 
 pub mod guards;
+pub mod pagination;
+pub mod query;
 pub mod validators;
 
 pub use partial_form_codegen::generate_partial_form;
@@ -0,0 +1,60 @@
+//! 分页
+use rocket::serde::Serialize;
+use rocket::FromForm;
+
+/// 分页参数
+///
+/// * `page` - 页码，从 1 开始，默认 1
+/// * `per_page` - 每页数量，1 ~ 100, 默认 20
+///
+/// Examples:
+/// ```
+/// # use rocket::get;
+/// # use rocket::serde::json::Json;
+/// use utils::pagination::{Page, Pagination};
+///
+/// #[get("/items?<pagination..>")]
+/// async fn items(pagination: Pagination) -> Json<Page<i32>> {
+///     // SELECT ... LIMIT pagination.limit() OFFSET pagination.offset()
+///     Json(Page::new(vec![], 0, pagination))
+/// }
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq, FromForm)]
+pub struct Pagination {
+    #[field(default = 1, validate = range(1..))]
+    pub page: i64,
+    #[field(default = 20, validate = range(1..=100))]
+    pub per_page: i64,
+}
+
+impl Pagination {
+    pub fn limit(&self) -> i64 {
+        self.per_page
+    }
+
+    pub fn offset(&self) -> i64 {
+        (self.page - 1) * self.per_page
+    }
+}
+
+/// 分页结果
+#[derive(Clone, Debug, Serialize)]
+#[serde(crate = "rocket::serde")]
+pub struct Page<T> {
+    /// 总数
+    pub total: i64,
+    pub page: i64,
+    pub per_page: i64,
+    pub items: Vec<T>,
+}
+
+impl<T> Page<T> {
+    pub fn new(items: Vec<T>, total: i64, pagination: Pagination) -> Self {
+        Self {
+            total,
+            page: pagination.page,
+            per_page: pagination.per_page,
+            items,
+        }
+    }
+}
@@ -0,0 +1,22 @@
+//! 查询辅助
+
+/// 包含 `value` 的 LIKE 模式
+///
+/// 转义 `\`、`%` 和 `_`，使 `value` 按字面匹配
+///
+/// Examples:
+/// ```
+/// use utils::query::like_pattern;
+///
+/// assert_eq!(like_pattern("a_b"), "%a\\_b%");
+/// assert_eq!(like_pattern("100%"), "%100\\%%");
+/// ```
+pub fn like_pattern(value: &str) -> String {
+    format!(
+        "%{}%",
+        value
+            .replace('\\', "\\\\")
+            .replace('%', "\\%")
+            .replace('_', "\\_")
+    )
+}
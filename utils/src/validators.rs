@@ -1,3 +1,6 @@
+// validate_opt! 依赖参数类型 `&String` 推断 `Option<_>`，不能改为 `&str`
+#![allow(clippy::ptr_arg)]
+
 use chrono::NaiveDate;
 use lettre::Address;
 use phonenumber::{country, parse};
@@ -14,12 +17,12 @@ use rocket::fs::TempFile;
 /// #[derive(FromForm)]
 /// struct PartialUpdateReq {
 ///     #[field(validate=validate_opt!(len(2..32))())]
-///     username: String,
+///     username: Option<String>,
 ///     // other fields
 /// }
 /// ```
 /// will expand to
-/// ```
+/// ```ignore
 /// # use utils::validate_opt;
 /// # use rocket::FromForm;
 /// #[derive(FromForm)]
@@ -30,7 +33,7 @@ use rocket::fs::TempFile;
 ///             None => Ok(()),
 ///         }
 ///     )]
-///     username: String,
+///     username: Option<String>,
 ///     // other fields
 /// }
 /// ```
@@ -50,10 +53,10 @@ macro_rules! validate_opt {
 
 /// 校验文件类型是否是 image/*
 pub fn is_image_file<'v>(file: &TempFile<'_>) -> Result<'v, ()> {
-    if let Some(ct) = file.content_type() {
-        if ct.top() == "image" {
-            return Ok(());
-        }
+    if let Some(ct) = file.content_type()
+        && ct.top() == "image"
+    {
+        return Ok(());
     }
     Err(Error::validation("content_type must be image/*").into())
 }